@@ -0,0 +1,137 @@
+//! قياس الفهرس المكاني مقابل البحث الخطي على باترون صناعي من 10k عنصر
+//! (4000 نقطة، 3000 خط، 2000 منحنى، 1000 منصف) — التشغيل: cargo bench --bench spatial
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use valentina::geometry::Point2D;
+use valentina::object::{VPoint, VLine, VCubicBezier, VBisector, SelectedItem};
+use valentina::spatial::{SpatialIndex, Shape};
+use valentina::{geometry, hit_test};
+
+struct Pattern {
+    points: Vec<VPoint>,
+    lines: Vec<VLine>,
+    splines: Vec<VCubicBezier>,
+    bisectors: Vec<VBisector>,
+}
+
+/// مولد أرقام بسيط بذرة ثابتة حتى تتكرر النتائج
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> f64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: u32) -> u32 {
+        (self.next() * n as f64) as u32
+    }
+}
+
+fn pattern() -> Pattern {
+    let mut rng = Lcg(42);
+    let points: Vec<VPoint> = (1..=4000).map(|id| VPoint::new(id, &format!("P{id}"), rng.next() * 1000.0, rng.next() * 1000.0)).collect();
+    // الأشكال تربط نقاطاً متقاربة في الترتيب كما في الباترون الحقيقي
+    let near = |rng: &mut Lcg, base: u32| 1 + (base + rng.below(5)) % 4000;
+    let mut next_id = 4001;
+    let mut take = || { next_id += 1; next_id - 1 };
+    let lines = (0..3000).map(|i| {
+        let a = near(&mut rng, i);
+        VLine::new(take(), "L", a, near(&mut rng, a))
+    }).collect();
+    let splines = (0..2000).map(|i| {
+        let a = near(&mut rng, i * 2);
+        VCubicBezier::new(take(), "S", a, near(&mut rng, a), near(&mut rng, a + 1), near(&mut rng, a + 2))
+    }).collect();
+    let bisectors = (0..1000).map(|i| {
+        let v = near(&mut rng, i * 4);
+        VBisector::new(take(), "B", near(&mut rng, v), v, near(&mut rng, v + 3), 20.0)
+    }).collect();
+    Pattern { points, lines, splines, bisectors }
+}
+
+/// نقاط اختبار الالتقاط: 200 موضع ثابت
+fn probes() -> Vec<Point2D> {
+    let mut rng = Lcg(7);
+    (0..200).map(|_| Point2D::new(rng.next() * 1000.0, rng.next() * 1000.0)).collect()
+}
+
+/// الطريقة القديمة: البحث عن كل نقطة بـ iter().find
+fn linear_shapes(p: &Pattern) -> Vec<(SelectedItem, Shape)> {
+    let find = |id: u32| p.points.iter().find(|pt| pt.metadata.id == id).map(|pt| pt.coords);
+    let mut out: Vec<(SelectedItem, Shape)> = p.points.iter().map(|pt| (SelectedItem::Point(pt.metadata.id), Shape::Point(pt.coords))).collect();
+    for l in &p.lines {
+        if let (Some(a), Some(b)) = (find(l.start_point_id), find(l.end_point_id)) {
+            out.push((SelectedItem::Line(l.metadata.id), Shape::Segment(a, b)));
+        }
+    }
+    for s in &p.splines {
+        if let (Some(a), Some(b), Some(c), Some(d)) = (find(s.p1_id), find(s.p2_id), find(s.p3_id), find(s.p4_id)) {
+            out.push((SelectedItem::Spline(s.metadata.id), Shape::Cubic([a, b, c, d])));
+        }
+    }
+    out
+}
+
+fn lookups(c: &mut Criterion) {
+    let p = pattern();
+    let index = SpatialIndex::build(&p.points, &p.lines, &p.splines, &p.bisectors, geometry::Tolerance::default().0);
+    let mut group = c.benchmark_group("point lookups for all lines and splines");
+    group.bench_function("linear", |b| b.iter(|| {
+        let find = |id: u32| p.points.iter().find(|pt| pt.metadata.id == id);
+        let mut n = 0;
+        for l in &p.lines {
+            n += find(l.start_point_id).is_some() as usize + find(l.end_point_id).is_some() as usize;
+        }
+        for s in &p.splines {
+            n += [s.p1_id, s.p2_id, s.p3_id, s.p4_id].iter().filter(|id| find(**id).is_some()).count();
+        }
+        black_box(n)
+    }));
+    group.bench_function("indexed", |b| b.iter(|| {
+        let find = |id: u32| index.point(&p.points, id);
+        let mut n = 0;
+        for l in &p.lines {
+            n += find(l.start_point_id).is_some() as usize + find(l.end_point_id).is_some() as usize;
+        }
+        for s in &p.splines {
+            n += [s.p1_id, s.p2_id, s.p3_id, s.p4_id].iter().filter(|id| find(**id).is_some()).count();
+        }
+        black_box(n)
+    }));
+    group.finish();
+}
+
+fn hits(c: &mut Criterion) {
+    let p = pattern();
+    let index = SpatialIndex::build(&p.points, &p.lines, &p.splines, &p.bisectors, geometry::Tolerance::default().0);
+    let shapes = linear_shapes(&p);
+    let probes = probes();
+    let tolerance = 6.0;
+    let mut group = c.benchmark_group("200 hit-tests");
+    group.sample_size(10);
+    group.bench_function("linear", |b| b.iter(|| {
+        for at in &probes {
+            let best = shapes.iter()
+                .map(|(item, shape)| (item, shape.distance_to(at)))
+                .filter(|(_, d)| *d <= hit_test::POINT_RADIUS + tolerance)
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            black_box(best);
+        }
+    }));
+    group.bench_function("indexed", |b| b.iter(|| {
+        for at in &probes {
+            black_box(hit_test::hit_first(at, tolerance, &index));
+        }
+    }));
+    group.finish();
+}
+
+fn build(c: &mut Criterion) {
+    let p = pattern();
+    c.bench_function("index build", |b| b.iter(|| {
+        black_box(SpatialIndex::build(&p.points, &p.lines, &p.splines, &p.bisectors, geometry::Tolerance::default().0))
+    }));
+}
+
+criterion_group!(benches, lookups, hits, build);
+criterion_main!(benches);
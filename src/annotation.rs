@@ -0,0 +1,92 @@
+use crate::geometry::Point2D;
+use crate::object::VPoint;
+use crate::piece::VPiece;
+use serde::{Serialize, Deserialize};
+
+/// قالب ملصق القطعة الافتراضي
+pub const DEFAULT_PIECE_LABEL: &str = "%pName%\nCut %pQuantity% of %pMaterial%";
+/// قالب ملصق الباترون الافتراضي
+pub const DEFAULT_PATTERN_LABEL: &str = "%patternName%\nSize %size%";
+pub const DEFAULT_FONT_SIZE: f64 = 14.0;
+
+/// بيانات الباترون العامة المستخدمة في الملصقات
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PatternInfo {
+    pub name: String,
+    pub size: String,
+}
+
+/// اتجاه نسيج القماش على القطعة
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Grainline {
+    /// بين نقطتين من الرسم
+    Points { start_id: u32, end_id: u32 },
+    /// منتصف (نسبةً لمركز القطعة) مع طول وزاوية
+    Centered { offset: Point2D, length: f64, angle: f64 },
+}
+
+impl Grainline {
+    /// طرفا خط النسيج في إحداثيات الرسم
+    pub fn endpoints(&self, anchor: &Point2D, points: &[VPoint]) -> Option<(Point2D, Point2D)> {
+        match self {
+            Grainline::Points { start_id, end_id } => {
+                let find = |id: u32| points.iter().find(|p| p.metadata.id == id).map(|p| p.coords);
+                Some((find(*start_id)?, find(*end_id)?))
+            }
+            Grainline::Centered { offset, length, angle } => {
+                let center = Point2D::new(anchor.x + offset.x, anchor.y + offset.y);
+                Some((center.point_at(length / 2.0, angle + 180.0), center.point_at(length / 2.0, *angle)))
+            }
+        }
+    }
+
+    /// الخط مع رأسي سهم في الطرفين كخطوط متعددة
+    pub fn arrow(&self, anchor: &Point2D, points: &[VPoint]) -> Vec<Vec<Point2D>> {
+        let Some((a, b)) = self.endpoints(anchor, points) else { return Vec::new() };
+        let len = a.distance_to(&b);
+        if len == 0.0 {
+            return Vec::new();
+        }
+        let head = (len / 8.0).min(15.0);
+        let ang = a.angle_to(&b);
+        vec![
+            vec![a, b],
+            vec![b.point_at(head, ang + 150.0), b, b.point_at(head, ang - 150.0)],
+            vec![a.point_at(head, ang + 30.0), a, a.point_at(head, ang - 30.0)],
+        ]
+    }
+}
+
+/// ملصق نصي بقالب، موضعه نسبةً لمركز القطعة
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextLabel {
+    pub template: String,
+    pub offset: Point2D,
+    pub font_size: f64,
+}
+
+impl TextLabel {
+    pub fn new(template: &str, offset: Point2D) -> Self {
+        Self { template: template.to_string(), offset, font_size: DEFAULT_FONT_SIZE }
+    }
+
+    pub fn position(&self, anchor: &Point2D) -> Point2D {
+        Point2D::new(anchor.x + self.offset.x, anchor.y + self.offset.y)
+    }
+
+    /// أسطر النص بعد استبدال المتغيرات
+    pub fn lines(&self, piece: &VPiece, pattern: &PatternInfo) -> Vec<String> {
+        expand(&self.template, piece, pattern).lines().map(str::to_string).collect()
+    }
+}
+
+/// استبدال المتغيرات في القالب:
+/// %pName% اسم القطعة، %pQuantity% عدد القص، %pMaterial% الخامة، %size% المقاس، %patternName% اسم الباترون
+pub fn expand(template: &str, piece: &VPiece, pattern: &PatternInfo) -> String {
+    template
+        .replace("%pName%", &piece.metadata.name)
+        .replace("%pQuantity%", &piece.cut_quantity.to_string())
+        .replace("%pMaterial%", &piece.material)
+        .replace("%size%", &pattern.size)
+        .replace("%patternName%", &pattern.name)
+}
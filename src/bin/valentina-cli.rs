@@ -0,0 +1,465 @@
+//! أداة سطر الأوامر: فحص المشاريع وإعادة حسابها وتصديرها وتحويلها بدون واجهة (للتشغيل الآلي)
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use serde_json::{json, Value};
+use valentina::error::Error;
+use valentina::project::{ProjectData, Issue, Severity, has_errors};
+use valentina::measurements::MeasurementTable;
+use valentina::export::{self, ExportOptions, ExportContent};
+use valentina::export::dxf::{DxfOptions, SplineMode};
+use valentina::export::pdf::{PdfOptions, PaperSize};
+use valentina::export::png::PngOptions;
+use valentina::export::hpgl::HpglOptions;
+use valentina::geometry::Point2D;
+use valentina::spatial::{Bounds, CurveResolution};
+use valentina::layout;
+use valentina::script;
+
+const USAGE: &str = "\
+Usage: valentina-cli <command> [options]
+
+Commands:
+  validate <project>              check ids, references, contours and formulas
+  recalc <project>                recalculate formulas and derived points
+         [--measurements FILE] [--size N] [--height N] [-o OUT]
+  export <project> --format svg|dxf|pdf|png|hpgl [-o OUT]
+         [--content all|drawing|pieces] [--measurements FILE] [--size N] [--height N]
+         [--dpi N] [--region X0,Y0,X1,Y1] [--paper a4|letter] [--no-tile] [--curves polyline|spline]
+  grade <project> [--measurements FILE] [--format svg|dxf|pdf|png|hpgl] [-o DIR]
+                                  one export per size/height plus a nest-of-sizes SVG
+  script <project> <script.rhai> [--measurements FILE] [--size N] [--height N] [-o OUT]
+                                  run a drafting script and save the result
+  stats <project>                 counts, piece areas and layout usage
+  convert <input> <output>        convert between .json projects and Valentina .val
+
+Projects may be .json or .val files. Add --json for machine-readable output.
+Without -o, recalc and script write <project>.recalc.json and <project>.script.json.
+
+Exit codes: 0 success, 1 validation or formula errors, 2 usage error, 3 file or parse error";
+
+/// رموز الخروج
+const EXIT_INVALID: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_IO: u8 = 3;
+
+struct Failure {
+    code: u8,
+    message: String,
+}
+
+impl Failure {
+    fn usage(message: impl Into<String>) -> Self {
+        Self { code: EXIT_USAGE, message: message.into() }
+    }
+}
+
+/// أخطاء الملفات رمزها 3 وأخطاء المحتوى (تحقق، معادلات، هندسة) رمزها 1
+impl From<Error> for Failure {
+    fn from(e: Error) -> Self {
+        let code = if e.is_file_error() { EXIT_IO } else { EXIT_INVALID };
+        Self { code, message: e.to_string() }
+    }
+}
+
+/// نتيجة أمر ناجح: رمز الخروج، النص المقروء، والكائن المكتوب بـ --json
+struct Outcome {
+    code: u8,
+    text: String,
+    data: Value,
+}
+
+const VALUE_OPTIONS: [&str; 11] = ["-o", "--output", "--measurements", "--size", "--height", "--format", "--content", "--dpi", "--region", "--paper", "--curves"];
+const FLAG_OPTIONS: [&str; 2] = ["--json", "--no-tile"];
+
+struct Args {
+    command: String,
+    inputs: Vec<String>,
+    options: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    fn parse(raw: &[String]) -> Result<Self, Failure> {
+        let mut it = raw.iter();
+        let command = it.next().ok_or(Failure::usage("missing command"))?.clone();
+        let mut args = Args { command, inputs: Vec::new(), options: Vec::new(), flags: Vec::new() };
+        while let Some(a) = it.next() {
+            if VALUE_OPTIONS.contains(&a.as_str()) {
+                let v = it.next().ok_or(Failure::usage(format!("option {} needs a value", a)))?;
+                let key = if a == "-o" { "--output" } else { a.as_str() };
+                args.options.push((key.to_string(), v.clone()));
+            } else if FLAG_OPTIONS.contains(&a.as_str()) {
+                args.flags.push(a.clone());
+            } else if a.starts_with('-') && a.len() > 1 {
+                return Err(Failure::usage(format!("unknown option {}", a)));
+            } else {
+                args.inputs.push(a.clone());
+            }
+        }
+        Ok(args)
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.options.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.flags.iter().any(|f| f == key)
+    }
+
+    fn number(&self, key: &str) -> Result<Option<f64>, Failure> {
+        self.get(key)
+            .map(|v| v.parse::<f64>().map_err(|_| Failure::usage(format!("{} expects a number, got '{}'", key, v))))
+            .transpose()
+    }
+
+    /// منطقة مستطيلة "x0,y0,x1,y1" بوحدات الرسم
+    fn region(&self, key: &str) -> Result<Option<Bounds>, Failure> {
+        let Some(v) = self.get(key) else { return Ok(None) };
+        let bad = || Failure::usage(format!("{} expects x0,y0,x1,y1 with x0 < x1 and y0 < y1, got '{}'", key, v));
+        let n: Vec<f64> = v.split(',').map(|p| p.trim().parse::<f64>()).collect::<Result<_, _>>().map_err(|_| bad())?;
+        match n[..] {
+            [x0, y0, x1, y1] if x0 < x1 && y0 < y1 => Ok(Some(Bounds::new(Point2D::new(x0, y0), Point2D::new(x1, y1)))),
+            _ => Err(bad()),
+        }
+    }
+
+    fn input(&self, count: usize) -> Result<&[String], Failure> {
+        if self.inputs.len() != count {
+            return Err(Failure::usage(format!("{} expects {} file argument(s)", self.command, count)));
+        }
+        Ok(&self.inputs)
+    }
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), Failure> {
+    fs::write(path, bytes).map_err(|e| Error::io(path, e).into())
+}
+
+/// استبدال جدول قياسات المشروع بالملف المعطى في --measurements (إن وُجد)
+fn load_measurements(project: &mut ProjectData, args: &Args) -> Result<(), Failure> {
+    if let Some(file) = args.get("--measurements") {
+        project.measurements = MeasurementTable::load(Path::new(file))?;
+    }
+    Ok(())
+}
+
+/// قيم القياسات للمقاس والطول المطلوبين
+fn grade_values(project: &mut ProjectData, args: &Args) -> Result<Vec<(String, f64)>, Failure> {
+    load_measurements(project, args)?;
+    let (size, height) = (args.number("--size")?, args.number("--height")?);
+    project.measurements.check_grade(size, height)?;
+    Ok(project.measurements.values(size, height))
+}
+
+/// تطبيق جدول القياسات (من الملف أو من المشروع) والمقاس والطول المطلوبين
+fn apply_grade(project: &mut ProjectData, args: &Args) -> Result<Vec<Issue>, Failure> {
+    let values = grade_values(project, args)?;
+    Ok(project.recalculate(&values))
+}
+
+fn issue_lines(issues: &[Issue]) -> String {
+    issues.iter()
+        .map(|i| match i.severity {
+            Severity::Error => format!("error: {}", i.message),
+            Severity::Warning => format!("warning: {}", i.message),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn validate(args: &Args) -> Result<Outcome, Failure> {
+    let path = PathBuf::from(&args.input(1)?[0]);
+    let (project, warnings) = ProjectData::load(&path)?;
+    let issues = project.validate();
+    let ok = !has_errors(&issues);
+    let text = if issues.is_empty() { format!("{}: ok", path.display()) } else { issue_lines(&issues) };
+    Ok(Outcome {
+        code: if ok { 0 } else { EXIT_INVALID },
+        text,
+        data: json!({ "valid": ok, "issues": issues, "warnings": warnings }),
+    })
+}
+
+/// مسار الناتج الافتراضي بجوار المدخل: p.json -> p.recalc.json، حتى لا يُستبدل ملف المشروع نفسه
+fn derived_path(path: &Path, suffix: &str) -> PathBuf {
+    path.with_extension(format!("{}.json", suffix))
+}
+
+fn recalc(args: &Args) -> Result<Outcome, Failure> {
+    let path = PathBuf::from(&args.input(1)?[0]);
+    let (mut project, warnings) = ProjectData::load(&path)?;
+    let issues = apply_grade(&mut project, args)?;
+    if has_errors(&issues) {
+        return Ok(Outcome { code: EXIT_INVALID, text: issue_lines(&issues), data: json!({ "written": false, "issues": issues }) });
+    }
+    let out = args.get("--output").map(PathBuf::from).unwrap_or(derived_path(&path, "recalc"));
+    project.save(&out)?;
+    Ok(Outcome {
+        code: 0,
+        text: format!("recalculated {} -> {}", path.display(), out.display()),
+        data: json!({ "written": true, "output": out, "issues": issues, "warnings": warnings }),
+    })
+}
+
+fn export_options(args: &Args) -> Result<ExportOptions, Failure> {
+    let content = match args.get("--content").unwrap_or("all") {
+        "all" => ExportContent::All,
+        "drawing" => ExportContent::DrawingOnly,
+        "pieces" => ExportContent::PiecesOnly,
+        other => return Err(Failure::usage(format!("unknown content '{}'", other))),
+    };
+    Ok(ExportOptions { content, ..ExportOptions::default() })
+}
+
+/// امتداد ملف الصيغة، أو خطأ استخدام للصيغ غير المعروفة
+fn format_extension(format: &str) -> Result<&'static str, Failure> {
+    match format {
+        "svg" => Ok("svg"),
+        "dxf" => Ok("dxf"),
+        "pdf" => Ok("pdf"),
+        "png" => Ok("png"),
+        "hpgl" | "plt" => Ok("plt"),
+        other => Err(Failure::usage(format!("unknown format '{}'", other))),
+    }
+}
+
+/// محتوى ملف التصدير بالصيغة المطلوبة
+/// `fresh_layout` يعيد ترتيب القطع للراسم بدلاً من التخطيط المحفوظ (للمقاسات المدرجة)
+fn render(project: &ProjectData, format: &str, args: &Args, fresh_layout: bool) -> Result<Vec<u8>, Failure> {
+    let opts = export_options(args)?;
+    let content = opts.content;
+    Ok(match format {
+        "svg" => export::svg::write_svg(&project.export_scene(content), &opts).into_bytes(),
+        "dxf" => {
+            let spline_mode = match args.get("--curves").unwrap_or("polyline") {
+                "polyline" => SplineMode::Flatten,
+                "spline" => SplineMode::Spline,
+                other => return Err(Failure::usage(format!("unknown curve mode '{}'", other))),
+            };
+            export::dxf::write_dxf(&project.export_scene(content), &opts, &DxfOptions { spline_mode, ..DxfOptions::default() }).into_bytes()
+        }
+        "pdf" => {
+            let mut pdf = PdfOptions { tiled: !args.flag("--no-tile"), ..PdfOptions::default() };
+            if let Some(p) = args.get("--paper") {
+                pdf.paper = PaperSize::ALL.into_iter().find(|s| s.label().eq_ignore_ascii_case(p))
+                    .ok_or(Failure::usage(format!("unknown paper '{}'", p)))?;
+            }
+            export::pdf::write_pdf(&project.export_scene(content), &opts, &pdf)
+        }
+        "png" => {
+            let png = PngOptions { dpi: args.number("--dpi")?.unwrap_or(PngOptions::default().dpi), region: args.region("--region")? };
+            export::png::render_png(&project.export_scene(content), &opts, &png)
+                .ok_or(Failure { code: EXIT_INVALID, message: "nothing to render or image too large for this DPI".to_string() })?
+        }
+        "hpgl" | "plt" => {
+            // تخطيط المشروع المحفوظ، أو تخطيط جديد إن لم يوجد
+            let hp = HpglOptions::default();
+            let geos = project.piece_geometries(Some(CurveResolution::Tolerance(hp.curve_tolerance(&opts))));
+            let lay = if fresh_layout || project.layout.placements.is_empty() {
+                layout::nest(&geos, &project.layout_options)
+            } else {
+                project.layout.clone()
+            };
+            export::hpgl::write_hpgl(&lay.placed_pieces(&geos), lay.fabric_width, &opts, &hp)?.into_bytes()
+        }
+        other => return Err(Failure::usage(format!("unknown format '{}'", other))),
+    })
+}
+
+fn export(args: &Args) -> Result<Outcome, Failure> {
+    let path = PathBuf::from(&args.input(1)?[0]);
+    let (mut project, _) = ProjectData::load(&path)?;
+    let graded = ["--measurements", "--size", "--height"].iter().any(|k| args.get(k).is_some());
+    let issues = if graded { apply_grade(&mut project, args)? } else { Vec::new() };
+    if has_errors(&issues) {
+        return Ok(Outcome { code: EXIT_INVALID, text: issue_lines(&issues), data: json!({ "written": false, "issues": issues }) });
+    }
+
+    let format = args.get("--format").ok_or(Failure::usage("export needs --format"))?.to_lowercase();
+    let ext = format_extension(&format)?;
+    let bytes = render(&project, &format, args, false)?;
+    let out = args.get("--output").map(PathBuf::from).unwrap_or(path.with_extension(ext));
+    write_file(&out, &bytes)?;
+    Ok(Outcome {
+        code: 0,
+        text: format!("exported {} -> {}", path.display(), out.display()),
+        data: json!({ "written": true, "output": out, "format": format, "bytes": bytes.len(), "issues": issues }),
+    })
+}
+
+/// تدريج جماعي: ملف لكل مقاس وطول في الجدول، وتراكب المقاسات في ملف SVG واحد
+fn grade(args: &Args) -> Result<Outcome, Failure> {
+    let path = PathBuf::from(&args.input(1)?[0]);
+    let (mut project, _) = ProjectData::load(&path)?;
+    load_measurements(&mut project, args)?;
+    if !project.measurements.is_multisize() {
+        return Err(Failure { code: EXIT_INVALID, message: "the measurement table has no sizes or heights to grade".to_string() });
+    }
+    let format = args.get("--format").unwrap_or("svg").to_lowercase();
+    let ext = format_extension(&format)?;
+    let dir = args.get("--output").map(PathBuf::from)
+        .unwrap_or(path.parent().map(Path::to_path_buf).unwrap_or_default());
+    fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or("pattern".to_string());
+    let opts = export_options(args)?;
+
+    let mut files = Vec::new();
+    let mut failed = Vec::new();
+    let mut nest = Vec::new();
+    for (grade, graded, issues) in project.graded() {
+        let label = grade.label();
+        if has_errors(&issues) {
+            failed.push(json!({ "grade": grade, "issues": issues }));
+            continue;
+        }
+        let out = dir.join(format!("{}_{}.{}", stem, label, ext));
+        write_file(&out, &render(&graded, &format, args, true)?)?;
+        files.push(json!({ "grade": grade, "output": out }));
+        nest.push((label, graded.export_scene(opts.content)));
+    }
+    let nest_path = dir.join(format!("{}_nest.svg", stem));
+    write_file(&nest_path, export::svg::write_size_nest(&nest, &opts).as_bytes())?;
+
+    let mut text = vec![format!("graded {} size(s) -> {}", files.len(), dir.display())];
+    text.push(format!("nest of sizes -> {}", nest_path.display()));
+    for f in &failed {
+        text.push(format!("error: grade {} skipped: {}", f["grade"], f["issues"]));
+    }
+    Ok(Outcome {
+        code: if failed.is_empty() { 0 } else { EXIT_INVALID },
+        text: text.join("\n"),
+        data: json!({ "files": files, "nest": nest_path, "failed": failed }),
+    })
+}
+
+/// تشغيل سكربت رسم على المشروع ثم حفظه (لا شيء يُكتب إذا فشل السكربت)
+fn script(args: &Args) -> Result<Outcome, Failure> {
+    let files = args.input(2)?;
+    let (path, script_path) = (PathBuf::from(&files[0]), PathBuf::from(&files[1]));
+    let (mut project, _) = ProjectData::load(&path)?;
+    let source = fs::read_to_string(&script_path).map_err(|e| Error::io(&script_path, e))?;
+    let values = grade_values(&mut project, args)?;
+    let report = match script::run_script(&mut project, &source, &values) {
+        Ok(report) => report,
+        Err(e) => return Ok(Outcome {
+            code: EXIT_INVALID,
+            text: format!("{}: {}", script_path.display(), e),
+            data: json!({ "written": false, "error": e }),
+        }),
+    };
+    let out = args.get("--output").map(PathBuf::from).unwrap_or(derived_path(&path, "script"));
+    project.save(&out)?;
+    let mut text = report.log.clone();
+    text.push(format!("created {} object(s) -> {}", report.created.len(), out.display()));
+    Ok(Outcome { code: 0, text: text.join("\n"), data: json!({ "written": true, "output": out, "log": report.log, "created": report.created }) })
+}
+
+fn stats(args: &Args) -> Result<Outcome, Failure> {
+    let path = PathBuf::from(&args.input(1)?[0]);
+    let (project, _) = ProjectData::load(&path)?;
+    let reports = project.contour_reports();
+    let pieces: Vec<Value> = project.pieces.iter().map(|p| {
+        let report = reports.iter().find(|(id, _)| *id == p.contour_id).map(|(_, r)| r);
+        let metrics = report.map(|r| r.metrics());
+        json!({
+            "id": p.metadata.id,
+            "name": p.metadata.name,
+            "closed": report.is_some_and(|r| r.closed),
+            "perimeter": metrics.as_ref().map(|m| m.perimeter),
+            "area": metrics.as_ref().map(|m| m.area),
+            "quantity": p.cut_quantity,
+        })
+    }).collect();
+    let geos = project.piece_geometries(None);
+    let lay = &project.layout;
+    let data = json!({
+        "points": project.points.len(),
+        "lines": project.lines.len(),
+        "splines": project.splines.len(),
+        "bisectors": project.bisectors.len(),
+        "contours": project.contours.len(),
+        "operations": project.operations.len(),
+        "seam_pairs": project.seam_pairs.len(),
+        "measurements": project.measurements.measurements.len(),
+        "pieces": pieces,
+        "layout": {
+            "placements": lay.placements.len(),
+            "unplaced": lay.unplaced.len(),
+            "length": lay.length(&geos),
+            "efficiency": lay.efficiency(&geos),
+        },
+    });
+    let mut text = format!(
+        "points {}, lines {}, splines {}, bisectors {}, contours {}, operations {}, pieces {}",
+        project.points.len(), project.lines.len(), project.splines.len(), project.bisectors.len(),
+        project.contours.len(), project.operations.len(), project.pieces.len(),
+    );
+    for p in data["pieces"].as_array().into_iter().flatten() {
+        text.push_str(&format!("\n  {}: area {:.1} mm², perimeter {:.1} mm, x{}",
+            p["name"].as_str().unwrap_or(""), p["area"].as_f64().unwrap_or(0.0), p["perimeter"].as_f64().unwrap_or(0.0), p["quantity"]));
+    }
+    if !lay.placements.is_empty() {
+        text.push_str(&format!("\nlayout: {:.0} mm long, {:.1}% used", lay.length(&geos), lay.efficiency(&geos) * 100.0));
+    }
+    Ok(Outcome { code: 0, text, data })
+}
+
+fn convert(args: &Args) -> Result<Outcome, Failure> {
+    let files = args.input(2)?;
+    let (input, output) = (PathBuf::from(&files[0]), PathBuf::from(&files[1]));
+    let (project, mut warnings) = ProjectData::load(&input)?;
+    warnings.extend(project.save(&output)?);
+    let mut lines = vec![format!("converted {} -> {}", input.display(), output.display())];
+    lines.extend(warnings.iter().map(|w| format!("warning: {}", w)));
+    Ok(Outcome { code: 0, text: lines.join("\n"), data: json!({ "written": true, "output": output, "warnings": warnings }) })
+}
+
+fn main() -> ExitCode {
+    let raw: Vec<String> = std::env::args().skip(1).collect();
+    // بدون وسائط خطأ استخدام فتُكتب المساعدة على stderr كبقية أخطاء الاستخدام
+    if raw.is_empty() {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(EXIT_USAGE);
+    }
+    if raw.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let json_output = raw.iter().any(|a| a == "--json");
+    let result = Args::parse(&raw).and_then(|args| match args.command.as_str() {
+        "validate" => validate(&args),
+        "recalc" => recalc(&args),
+        "export" => export(&args),
+        "grade" => grade(&args),
+        "script" => script(&args),
+        "stats" => stats(&args),
+        "convert" => convert(&args),
+        other => Err(Failure::usage(format!("unknown command '{}'", other))),
+    });
+    let code = match result {
+        Ok(outcome) => {
+            if json_output {
+                let mut data = outcome.data;
+                data["ok"] = json!(outcome.code == 0);
+                println!("{}", data);
+            } else {
+                println!("{}", outcome.text);
+            }
+            outcome.code
+        }
+        Err(f) => {
+            if json_output {
+                println!("{}", json!({ "ok": false, "error": f.message, "code": f.code }));
+            } else {
+                eprintln!("error: {}", f.message);
+                if f.code == EXIT_USAGE {
+                    eprintln!("\n{}", USAGE);
+                }
+            }
+            f.code
+        }
+    };
+    ExitCode::from(code)
+}
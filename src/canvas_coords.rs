@@ -1,4 +1,4 @@
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct SvgViewBox {
     pub min_x: f64,
     pub min_y: f64,
@@ -6,7 +6,9 @@ pub struct SvgViewBox {
     pub height: f64,
 }
 
+/// الكانفاس يستخدم Meet فقط، والباقي لاكتمال قيم preserveAspectRatio
 #[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
 pub enum AspectRatioMode {
     /// xMidYMid meet — letterbox (bars on sides/top)
     Meet,
@@ -50,4 +52,15 @@ impl CoordMapper {
             (pixel_y - offset_y) / scale + vb.min_y,
         )
     }
+
+    /// تحويل مسافة بالبكسل (مثل سماحية الالتقاط) إلى وحدات الـ viewBox
+    pub fn pixels_to_svg(&self, pixels: f64, elem_w: f64, elem_h: f64) -> f64 {
+        let vb = &self.viewbox;
+        let scale = match self.preserve_aspect_ratio {
+            AspectRatioMode::Meet => f64::min(elem_w / vb.width, elem_h / vb.height),
+            AspectRatioMode::Slice => f64::max(elem_w / vb.width, elem_h / vb.height),
+            AspectRatioMode::None => f64::min(elem_w / vb.width, elem_h / vb.height),
+        };
+        pixels / scale
+    }
 }
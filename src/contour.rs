@@ -0,0 +1,347 @@
+use crate::geometry::{Point2D, segment_intersection};
+use crate::object::{VPoint, VLine, VCubicBezier, VBisector, VContour, EntityRef};
+use crate::spatial::{Shape, CurveResolution};
+
+/// أقصى مسافة بين نهاية قطعة وبداية التالية لاعتبارهما متصلتين (بوحدات الرسم)
+pub const CONTOUR_TOLERANCE: f64 = 0.5;
+
+/// اتجاه دوران المسار المغلق كما يظهر على الشاشة (المحور Y لأسفل في الـ SVG)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Clockwise,
+    CounterClockwise,
+    /// مساحة صفرية (مسار مفتوح جداً أو نقاط على خط واحد)
+    Degenerate,
+}
+
+/// قطعة من المسار بعد الترتيب، مع اتجاهها الفعلي
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderedSegment {
+    pub entity: EntityRef,
+    /// هل تم عكس اتجاه الكيان ليتصل بما قبله
+    pub reversed: bool,
+    pub shape: Shape,
+}
+
+/// فجوة بين نهاية قطعة وبداية القطعة التالية أكبر من السماحية
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gap {
+    pub after: EntityRef,
+    pub before: EntityRef,
+    pub at: Point2D,
+    pub distance: f64,
+}
+
+/// نتيجة ترتيب المسار والتحقق منه
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContourReport {
+    pub segments: Vec<OrderedSegment>,
+    pub gaps: Vec<Gap>,
+    pub closed: bool,
+    pub orientation: Orientation,
+    /// كيانات المسار التي لم يعد لها وجود (محذوفة)
+    pub missing: Vec<EntityRef>,
+}
+
+/// ترتيب كيانات المسار طرفاً لطرف بدءاً من أول كيان
+/// في كل خطوة نختار أقرب طرف متبقٍ لنهاية القطعة الحالية ونعكس الكيان إذا لزم
+pub fn order_contour(
+    contour: &VContour,
+    points: &[VPoint],
+    lines: &[VLine],
+    splines: &[VCubicBezier],
+    bisectors: &[VBisector],
+    tolerance: f64,
+) -> ContourReport {
+    let mut missing = Vec::new();
+    let mut remaining: Vec<(EntityRef, Shape)> = Vec::new();
+    for entity in &contour.entities {
+        match entity.shape(points, lines, splines, bisectors) {
+            Some(shape) => remaining.push((*entity, shape)),
+            None => missing.push(*entity),
+        }
+    }
+
+    let mut segments: Vec<OrderedSegment> = Vec::new();
+    let mut gaps: Vec<Gap> = Vec::new();
+    if !remaining.is_empty() {
+        let (entity, shape) = remaining.remove(0);
+        segments.push(OrderedSegment { entity, reversed: false, shape });
+    }
+    // القطعة الأولى قد تكون معكوسة: نقرر ذلك عند ربطها بالثانية
+    let mut first_fixed = false;
+
+    while !remaining.is_empty() {
+        let last = segments.last().expect("at least one segment");
+        let tail = last.shape.end();
+        let head = last.shape.start();
+
+        // (موقع الكيان، هل يُعكس، المسافة، هل نعكس القطعة الأولى)
+        let mut best: Option<(usize, bool, f64, bool)> = None;
+        for (i, (_, shape)) in remaining.iter().enumerate() {
+            let mut options = vec![
+                (false, tail.distance_to(&shape.start()), false),
+                (true, tail.distance_to(&shape.end()), false),
+            ];
+            if !first_fixed && segments.len() == 1 {
+                options.push((false, head.distance_to(&shape.start()), true));
+                options.push((true, head.distance_to(&shape.end()), true));
+            }
+            for (reverse, dist, flip_first) in options {
+                if best.is_none_or(|b| dist < b.2) {
+                    best = Some((i, reverse, dist, flip_first));
+                }
+            }
+        }
+
+        let (i, reverse, dist, flip_first) = best.expect("remaining is not empty");
+        if flip_first {
+            let first = &mut segments[0];
+            first.reversed = !first.reversed;
+            first.shape = first.shape.reversed();
+        }
+        first_fixed = true;
+
+        let (entity, shape) = remaining.remove(i);
+        let shape = if reverse { shape.reversed() } else { shape };
+        let prev = segments.last().expect("at least one segment");
+        if dist > tolerance {
+            gaps.push(Gap { after: prev.entity, before: entity, at: prev.shape.end(), distance: dist });
+        }
+        segments.push(OrderedSegment { entity, reversed: reverse, shape });
+    }
+
+    let closed = match (segments.first(), segments.last()) {
+        (Some(first), Some(last)) if segments.len() > 1 || matches!(first.shape, Shape::Cubic(_)) => {
+            last.shape.end().distance_to(&first.shape.start()) <= tolerance
+        }
+        _ => false,
+    };
+
+    let outline = outline_points(&segments, CurveResolution::Segments(16));
+    let area = signed_area(&outline);
+    let orientation = if area.abs() < f64::EPSILON {
+        Orientation::Degenerate
+    } else if area > 0.0 {
+        // في نظام إحداثيات Y لأسفل، المساحة الموجبة تعني دوراناً مع عقارب الساعة على الشاشة
+        Orientation::Clockwise
+    } else {
+        Orientation::CounterClockwise
+    };
+
+    ContourReport { segments, gaps, closed, orientation, missing }
+}
+
+/// دقة تقسيم المنحنيات عند حساب المساحة والتقاطعات
+const ANALYSIS_CURVE_SEGMENTS: usize = 32;
+
+/// الخصائص الهندسية للمسار المرتب
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContourMetrics {
+    /// المحيط (أطوال الأقواس الفعلية للمنحنيات)
+    pub perimeter: f64,
+    /// المساحة (للمسار المغلق فقط)
+    pub area: f64,
+    pub centroid: Option<Point2D>,
+    pub self_intersections: Vec<Point2D>,
+}
+
+/// المساحة الموقعة لمضلع (صيغة Shoelace)
+pub fn signed_area(poly: &[Point2D]) -> f64 {
+    if poly.len() < 3 {
+        return 0.0;
+    }
+    let mut sum = 0.0;
+    for i in 0..poly.len() {
+        let a = &poly[i];
+        let b = &poly[(i + 1) % poly.len()];
+        sum += a.x * b.y - b.x * a.y;
+    }
+    sum / 2.0
+}
+
+/// المسار المرتب كخط متعدد الأضلاع (المنحنيات تُقسم بالدقة `resolution`)
+/// بدون تكرار نقاط الاتصال بين القطع
+pub fn outline_points(segments: &[OrderedSegment], resolution: CurveResolution) -> Vec<Point2D> {
+    let mut result: Vec<Point2D> = Vec::new();
+    for seg in segments {
+        let pts = seg.shape.flatten(resolution);
+        let skip = match (result.last(), pts.first()) {
+            (Some(last), Some(first)) if last.distance_to(first) <= CONTOUR_TOLERANCE => 1,
+            _ => 0,
+        };
+        result.extend(pts.into_iter().skip(skip));
+    }
+    if result.len() > 1 && result[0].distance_to(&result[result.len() - 1]) <= CONTOUR_TOLERANCE {
+        result.pop();
+    }
+    result
+}
+
+impl ContourReport {
+    /// المسار المرتب بصيغة SVG path مع الحفاظ على المنحنيات كأوامر C
+    pub fn to_svg_path(&self) -> String {
+        let mut d = String::new();
+        let mut cursor: Option<Point2D> = None;
+        for seg in &self.segments {
+            let start = seg.shape.start();
+            if cursor.is_none_or(|c| c.distance_to(&start) > CONTOUR_TOLERANCE) {
+                d.push_str(&format!("M {} {} ", start.x, start.y));
+            }
+            match &seg.shape {
+                Shape::Segment(_, b) => d.push_str(&format!("L {} {} ", b.x, b.y)),
+                Shape::Cubic([_, c1, c2, e]) => {
+                    d.push_str(&format!("C {} {}, {} {}, {} {} ", c1.x, c1.y, c2.x, c2.y, e.x, e.y))
+                }
+                Shape::Point(_) => {}
+            }
+            cursor = Some(seg.shape.end());
+        }
+        if self.closed {
+            d.push('Z');
+        }
+        d.trim_end().to_string()
+    }
+
+    pub fn outline(&self) -> Vec<Point2D> {
+        self.outline_at(CurveResolution::Segments(16))
+    }
+
+    pub fn outline_at(&self, resolution: CurveResolution) -> Vec<Point2D> {
+        outline_points(&self.segments, resolution)
+    }
+
+    pub fn perimeter(&self) -> f64 {
+        self.segments.iter().map(|s| s.shape.length()).sum()
+    }
+
+    pub fn metrics(&self) -> ContourMetrics {
+        let poly = outline_points(&self.segments, CurveResolution::Segments(ANALYSIS_CURVE_SEGMENTS));
+        let (area, centroid) = if self.closed { (signed_area(&poly).abs(), centroid(&poly)) } else { (0.0, None) };
+        ContourMetrics {
+            perimeter: self.perimeter(),
+            area,
+            centroid,
+            self_intersections: self_intersections(&poly, self.closed),
+        }
+    }
+
+    /// ترتيب الكيانات كما تم حسابه (لحفظه في VContour)
+    pub fn ordered_entities(&self) -> Vec<EntityRef> {
+        self.segments.iter().map(|s| s.entity).collect()
+    }
+}
+
+/// مركز ثقل المضلع المغلق
+pub fn centroid(poly: &[Point2D]) -> Option<Point2D> {
+    let area = signed_area(poly);
+    if area.abs() < 1e-9 {
+        return None;
+    }
+    let (mut cx, mut cy) = (0.0, 0.0);
+    for i in 0..poly.len() {
+        let a = &poly[i];
+        let b = &poly[(i + 1) % poly.len()];
+        let cross = a.x * b.y - b.x * a.y;
+        cx += (a.x + b.x) * cross;
+        cy += (a.y + b.y) * cross;
+    }
+    Some(Point2D::new(cx / (6.0 * area), cy / (6.0 * area)))
+}
+
+/// نقاط تقاطع الخط المتعدد مع نفسه (الأضلاع المتجاورة مستثناة)
+pub fn self_intersections(poly: &[Point2D], closed: bool) -> Vec<Point2D> {
+    let mut edges: Vec<(Point2D, Point2D)> = poly.windows(2).map(|w| (w[0], w[1])).collect();
+    if closed && poly.len() > 2 {
+        edges.push((poly[poly.len() - 1], poly[0]));
+    }
+    let n = edges.len();
+    let mut result: Vec<Point2D> = Vec::new();
+    for i in 0..n {
+        for j in (i + 2)..n {
+            // الضلع الأول والأخير متجاوران في المسار المغلق
+            if closed && i == 0 && j == n - 1 {
+                continue;
+            }
+            let (a, b) = edges[i];
+            let (c, d) = edges[j];
+            if a.x.max(b.x) < c.x.min(d.x) || c.x.max(d.x) < a.x.min(b.x)
+                || a.y.max(b.y) < c.y.min(d.y) || c.y.max(d.y) < a.y.min(b.y) {
+                continue;
+            }
+            if let Some(p) = segment_intersection(&a, &b, &c, &d)
+                && !result.iter().any(|q| q.distance_to(&p) < CONTOUR_TOLERANCE)
+            {
+                result.push(p);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(coords: &[(f64, f64)]) -> Vec<VPoint> {
+        coords.iter().enumerate().map(|(i, &(x, y))| VPoint::new(i as u32 + 1, &format!("P{}", i + 1), x, y)).collect()
+    }
+
+    /// خطوط بمعرفات تبدأ من 10 بين أزواج النقاط المعطاة
+    fn lines(pairs: &[(u32, u32)]) -> Vec<VLine> {
+        pairs.iter().enumerate().map(|(i, &(a, b))| VLine::new(i as u32 + 10, &format!("L{}", i), a, b)).collect()
+    }
+
+    fn report(points: &[VPoint], lines: &[VLine]) -> ContourReport {
+        let mut contour = VContour::new(99, "C");
+        contour.entities = lines.iter().map(|l| EntityRef::Line(l.metadata.id)).collect();
+        order_contour(&contour, points, lines, &[], &[], CONTOUR_TOLERANCE)
+    }
+
+    #[test]
+    fn reversed_lines_are_flipped_to_connect() {
+        let pts = points(&[(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)]);
+        // الخط الأول والثالث مرسومان بعكس اتجاه المسار
+        let r = report(&pts, &lines(&[(2, 1), (2, 3), (4, 3), (4, 1)]));
+        assert!(r.closed);
+        assert!(r.gaps.is_empty());
+        assert_eq!(r.ordered_entities(), [EntityRef::Line(10), EntityRef::Line(11), EntityRef::Line(12), EntityRef::Line(13)]);
+        assert_eq!(r.segments.iter().map(|s| s.reversed).collect::<Vec<_>>(), [true, false, true, false]);
+        assert_eq!(r.outline(), [Point2D::new(0.0, 0.0), Point2D::new(100.0, 0.0), Point2D::new(100.0, 100.0), Point2D::new(0.0, 100.0)]);
+    }
+
+    #[test]
+    fn gap_over_the_tolerance_is_reported() {
+        // نهاية الخط الثاني تبعد 5 عن بداية الثالث
+        let pts = points(&[(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (95.0, 100.0), (0.0, 100.0)]);
+        let r = report(&pts, &lines(&[(1, 2), (2, 3), (4, 5), (5, 1)]));
+        assert_eq!(r.gaps, [Gap { after: EntityRef::Line(11), before: EntityRef::Line(12), at: Point2D::new(100.0, 100.0), distance: 5.0 }]);
+        // الفجوة داخل السماحية لا تُعد
+        let pts = points(&[(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (99.8, 100.0), (0.0, 100.0)]);
+        assert!(report(&pts, &lines(&[(1, 2), (2, 3), (4, 5), (5, 1)])).gaps.is_empty());
+    }
+
+    #[test]
+    fn orientation_follows_the_screen() {
+        let pts = points(&[(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)]);
+        // Y لأسفل: يمين ثم أسفل = مع عقارب الساعة
+        assert_eq!(report(&pts, &lines(&[(1, 2), (2, 3), (3, 4), (4, 1)])).orientation, Orientation::Clockwise);
+        assert_eq!(report(&pts, &lines(&[(1, 4), (4, 3), (3, 2), (2, 1)])).orientation, Orientation::CounterClockwise);
+        assert_eq!(report(&pts, &lines(&[(1, 2), (2, 1)])).orientation, Orientation::Degenerate);
+    }
+
+    #[test]
+    fn figure_eight_metrics() {
+        // الضلعان القطريان يتقاطعان في (50, 50) فتتعادل مساحتا الحلقتين
+        let pts = points(&[(0.0, 0.0), (100.0, 100.0), (100.0, 0.0), (0.0, 100.0)]);
+        let r = report(&pts, &lines(&[(1, 2), (2, 3), (3, 4), (4, 1)]));
+        assert!(r.closed);
+        let m = r.metrics();
+        assert!((m.perimeter - (200.0 + 200.0 * 2f64.sqrt())).abs() < 1e-9, "{}", m.perimeter);
+        assert!(m.area.abs() < 1e-9, "{}", m.area);
+        assert_eq!(m.centroid, None);
+        assert_eq!(m.self_intersections, [Point2D::new(50.0, 50.0)]);
+        assert_eq!(r.orientation, Orientation::Degenerate);
+    }
+}
+
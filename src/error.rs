@@ -0,0 +1,75 @@
+//! نوع الخطأ الموحد للمكتبة: الملفات والتحليل والتحقق والمعادلات والهندسة
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::formula::FormulaError;
+use crate::geometry::GeometryError;
+
+#[derive(Debug)]
+pub enum Error {
+    /// فشل قراءة ملف أو كتابته
+    Io { path: PathBuf, source: io::Error },
+    /// محتوى غير صالح (JSON أو XML أو صيغة غير معروفة)
+    Parse { source: String, message: String },
+    /// مدخلات سليمة الصيغة لكنها غير متسقة مع المشروع
+    Validation(String),
+    /// معادلة لا يمكن حسابها، مع اسم الكائن أو القياس صاحبها
+    Formula { owner: String, source: FormulaError },
+    /// حساب هندسي على مدخلات منحلة
+    Geometry { object_id: Option<u32>, source: GeometryError },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        Error::Io { path: path.to_path_buf(), source }
+    }
+
+    pub fn parse(source: impl fmt::Display, message: impl fmt::Display) -> Self {
+        Error::Parse { source: source.to_string(), message: message.to_string() }
+    }
+
+    /// نسب خطأ التحليل إلى الملف الذي قُرئ منه
+    pub fn in_file(self, path: &Path) -> Self {
+        match self {
+            Error::Parse { message, .. } => Error::Parse { source: path.display().to_string(), message },
+            other => other,
+        }
+    }
+
+    /// أخطاء الملفات والتحليل (مقابل أخطاء محتوى المشروع)
+    pub fn is_file_error(&self) -> bool {
+        matches!(self, Error::Io { .. } | Error::Parse { .. })
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Parse { source, message } => write!(f, "{}: {}", source, message),
+            Error::Validation(message) => write!(f, "{}", message),
+            Error::Formula { owner, source } => write!(f, "{}: {}", owner, source),
+            Error::Geometry { object_id: Some(id), source } => write!(f, "object {}: {}", id, source),
+            Error::Geometry { object_id: None, source } => write!(f, "{}", source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Formula { source, .. } => Some(source),
+            Error::Geometry { source, .. } => Some(source),
+            Error::Parse { .. } | Error::Validation(_) => None,
+        }
+    }
+}
+
+impl From<GeometryError> for Error {
+    fn from(source: GeometryError) -> Self {
+        Error::Geometry { object_id: None, source }
+    }
+}
@@ -0,0 +1,393 @@
+use std::fmt::Write;
+use crate::geometry::{Point2D, flatten_cubic};
+use crate::piece::PieceGeometry;
+use super::{ExportScene, ExportOptions, Layer, piece_polylines, fmt_num};
+
+/// طريقة كتابة منحنيات الرسم. حدود القطع تبقى خطوطاً متعددة دائماً
+/// (هندستها مقسمة ومزاحة مسبقاً، وهذا ما يتوقعه AAMA)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplineMode {
+    /// خطوط متعددة (متوافق تماماً مع R12)
+    #[default]
+    Flatten,
+    /// كيانات SPLINE؛ الملف يُعلن حينها R2000 (AC1015) لأن SPLINE غير موجود في R12
+    Spline,
+}
+
+impl SplineMode {
+    pub const ALL: [SplineMode; 2] = [SplineMode::Flatten, SplineMode::Spline];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SplineMode::Flatten => "Drawing curves as polylines (R12)",
+            SplineMode::Spline => "Drawing curves as splines (R2000)",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.label() == label)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DxfOptions {
+    pub spline_mode: SplineMode,
+    /// عدد القطع لكل منحنى عند التقسيم
+    pub flatten_segments: usize,
+}
+
+impl Default for DxfOptions {
+    fn default() -> Self {
+        Self { spline_mode: SplineMode::Flatten, flatten_segments: 32 }
+    }
+}
+
+/// أرقام طبقات AAMA/ASTM D6673 لعناصر القطع، وأسماء عادية لعناصر الرسم
+pub fn aama_layer(layer: Layer) -> &'static str {
+    match layer {
+        Layer::CutLine => "1",
+        Layer::Notches => "4",
+        Layer::Grainline => "7",
+        Layer::InternalDraw => "8",
+        Layer::InternalCut => "11",
+        Layer::Placements => "13",
+        Layer::SeamLine => "14",
+        Layer::Text => "15",
+        Layer::DrawingPoints => "DRAWING_POINTS",
+        Layer::DrawingLines => "DRAWING_LINES",
+        Layer::DrawingSplines => "DRAWING_SPLINES",
+        Layer::DrawingBisectors => "DRAWING_BISECTORS",
+    }
+}
+
+/// كاتب أزواج (كود المجموعة، القيمة) بصيغة DXF النصية
+struct DxfWriter {
+    out: String,
+    scale: f64,
+}
+
+impl DxfWriter {
+    fn pair(&mut self, code: i32, value: &str) {
+        writeln!(self.out, "{:>3}\n{}", code, value).unwrap();
+    }
+
+    /// نقطة بالمليمتر مع قلب المحور Y (محور DXF لأعلى)
+    fn point(&mut self, code: i32, p: &Point2D) {
+        self.pair(code, &fmt_num(p.x * self.scale));
+        self.pair(code + 10, &fmt_num(-p.y * self.scale));
+        self.pair(code + 20, "0");
+    }
+
+    fn polyline(&mut self, layer: &str, poly: &[Point2D]) {
+        let closed = poly.len() > 2 && poly.first() == poly.last();
+        let pts = if closed { &poly[..poly.len() - 1] } else { poly };
+        self.pair(0, "POLYLINE");
+        self.pair(8, layer);
+        self.pair(66, "1");
+        self.point(10, &Point2D::new(0.0, 0.0));
+        self.pair(70, if closed { "1" } else { "0" });
+        for p in pts {
+            self.pair(0, "VERTEX");
+            self.pair(8, layer);
+            self.point(10, p);
+        }
+        self.pair(0, "SEQEND");
+        self.pair(8, layer);
+    }
+
+    /// منحنى بيزيه تكعيبي كـ SPLINE من الدرجة الثالثة بأربع نقاط تحكم
+    fn spline(&mut self, layer: &str, c: &[Point2D; 4]) {
+        self.pair(0, "SPLINE");
+        self.pair(8, layer);
+        self.pair(70, "8");
+        self.pair(71, "3");
+        self.pair(72, "8");
+        self.pair(73, "4");
+        self.pair(74, "0");
+        for k in [0, 0, 0, 0, 1, 1, 1, 1] {
+            self.pair(40, &k.to_string());
+        }
+        for p in c {
+            self.point(10, p);
+        }
+    }
+
+    fn text(&mut self, layer: &str, at: &Point2D, height: f64, text: &str) {
+        self.pair(0, "TEXT");
+        self.pair(8, layer);
+        self.point(10, at);
+        self.pair(40, &fmt_num(height));
+        self.pair(1, text);
+    }
+}
+
+/// اسم كتلة صالح في DXF لكل قطعة
+fn block_name(piece: &PieceGeometry) -> String {
+    let clean: String = piece.name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    format!("{}_{}", clean, piece.id)
+}
+
+/// إصدار الملف: R12 ما لم تُكتب كيانات SPLINE فعلاً
+fn acad_version(scene: &ExportScene, dxf: &DxfOptions) -> &'static str {
+    if dxf.spline_mode == SplineMode::Spline && !scene.drawing.splines.is_empty() {
+        "AC1015"
+    } else {
+        "AC1009"
+    }
+}
+
+/// كتابة المشهد كملف DXF بطبقات AAMA (R12، أو R2000 عند كتابة SPLINE):
+/// كل قطعة في BLOCK مستقل يُدرج في ENTITIES، وعناصر الرسم كيانات مباشرة
+pub fn write_dxf(scene: &ExportScene, opts: &ExportOptions, dxf: &DxfOptions) -> String {
+    let mut w = DxfWriter { out: String::new(), scale: opts.mm_per_unit };
+
+    w.pair(0, "SECTION");
+    w.pair(2, "HEADER");
+    w.pair(9, "$ACADVER");
+    w.pair(1, acad_version(scene, dxf));
+    w.pair(0, "ENDSEC");
+
+    // جدول أنواع الخطوط والطبقات
+    let layers = [
+        Layer::CutLine, Layer::Notches, Layer::Grainline, Layer::InternalDraw, Layer::InternalCut,
+        Layer::Placements, Layer::SeamLine, Layer::Text, Layer::DrawingPoints, Layer::DrawingLines,
+        Layer::DrawingSplines, Layer::DrawingBisectors,
+    ];
+    w.pair(0, "SECTION");
+    w.pair(2, "TABLES");
+    w.pair(0, "TABLE");
+    w.pair(2, "LTYPE");
+    w.pair(70, "1");
+    w.pair(0, "LTYPE");
+    w.pair(2, "CONTINUOUS");
+    w.pair(70, "0");
+    w.pair(3, "Solid line");
+    w.pair(72, "65");
+    w.pair(73, "0");
+    w.pair(40, "0");
+    w.pair(0, "ENDTAB");
+    w.pair(0, "TABLE");
+    w.pair(2, "LAYER");
+    w.pair(70, &layers.len().to_string());
+    for layer in layers {
+        w.pair(0, "LAYER");
+        w.pair(2, aama_layer(layer));
+        w.pair(70, "0");
+        w.pair(62, "7");
+        w.pair(6, "CONTINUOUS");
+    }
+    w.pair(0, "ENDTAB");
+    w.pair(0, "ENDSEC");
+
+    // كتلة لكل قطعة
+    w.pair(0, "SECTION");
+    w.pair(2, "BLOCKS");
+    for piece in &scene.pieces {
+        let name = block_name(piece);
+        w.pair(0, "BLOCK");
+        w.pair(8, "0");
+        w.pair(2, &name);
+        w.pair(70, "0");
+        w.point(10, &Point2D::new(0.0, 0.0));
+        w.pair(3, &name);
+        for (layer, poly) in piece_polylines(piece) {
+            w.polyline(aama_layer(layer), &poly);
+        }
+        // اسم القطعة كما يتوقعه AAMA على طبقة الحدود
+        if let Some(first) = piece.cut_outline().first() {
+            w.text(aama_layer(Layer::CutLine), first, 2.5, &format!("Piece Name: {}", piece.name));
+        }
+        for label in &piece.labels {
+            let step = label.font_size * 1.2;
+            for (i, line) in label.lines.iter().enumerate() {
+                let at = Point2D::new(label.at.x, label.at.y + step * i as f64);
+                w.text(aama_layer(Layer::Text), &at, label.font_size * opts.mm_per_unit, line);
+            }
+        }
+        w.pair(0, "ENDBLK");
+        w.pair(8, "0");
+    }
+    w.pair(0, "ENDSEC");
+
+    w.pair(0, "SECTION");
+    w.pair(2, "ENTITIES");
+    for piece in &scene.pieces {
+        w.pair(0, "INSERT");
+        w.pair(8, "0");
+        w.pair(2, &block_name(piece));
+        w.point(10, &Point2D::new(0.0, 0.0));
+    }
+    let d = &scene.drawing;
+    for (name, p) in &d.points {
+        w.pair(0, "POINT");
+        w.pair(8, aama_layer(Layer::DrawingPoints));
+        w.point(10, p);
+        w.text(aama_layer(Layer::DrawingPoints), &Point2D::new(p.x + 1.5, p.y - 1.5), 3.0, name);
+    }
+    let lines = d.lines.iter().map(|l| (Layer::DrawingLines, l))
+        .chain(d.bisectors.iter().map(|b| (Layer::DrawingBisectors, b)));
+    for (layer, (a, b)) in lines {
+        w.pair(0, "LINE");
+        w.pair(8, aama_layer(layer));
+        w.point(10, a);
+        w.point(11, b);
+    }
+    for c in &d.splines {
+        match dxf.spline_mode {
+            SplineMode::Spline => w.spline(aama_layer(Layer::DrawingSplines), c),
+            SplineMode::Flatten => {
+                let poly = flatten_cubic(&c[0], &c[1], &c[2], &c[3], dxf.flatten_segments);
+                w.polyline(aama_layer(Layer::DrawingSplines), &poly);
+            }
+        }
+    }
+    w.pair(0, "ENDSEC");
+    w.pair(0, "EOF");
+    w.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// قراءة أزواج (الكود، القيمة) من نص DXF
+    fn parse(dxf: &str) -> Vec<(i32, String)> {
+        let lines: Vec<&str> = dxf.lines().collect();
+        assert_eq!(lines.len() % 2, 0, "odd number of DXF lines");
+        lines.chunks(2)
+            .map(|c| (c[0].trim().parse().expect("group code"), c[1].to_string()))
+            .collect()
+    }
+
+    /// الأزواج بين SECTION/2 name و ENDSEC
+    fn section<'a>(pairs: &'a [(i32, String)], name: &str) -> &'a [(i32, String)] {
+        let start = pairs.windows(2)
+            .position(|w| w[0] == (0, "SECTION".into()) && w[1] == (2, name.into()))
+            .unwrap_or_else(|| panic!("missing {name} section"));
+        let len = pairs[start..].iter().position(|p| *p == (0, "ENDSEC".into())).unwrap();
+        &pairs[start + 2..start + len]
+    }
+
+    /// كل كيان مع طبقته (الكود 8 الأول بعد الكود 0)
+    fn entities(pairs: &[(i32, String)]) -> Vec<(String, String)> {
+        let mut out = Vec::new();
+        for (i, (code, kind)) in pairs.iter().enumerate() {
+            if *code != 0 {
+                continue;
+            }
+            let layer = pairs[i + 1..].iter()
+                .take_while(|(c, _)| *c != 0)
+                .find(|(c, _)| *c == 8)
+                .map(|(_, l)| l.clone())
+                .unwrap_or_default();
+            out.push((kind.clone(), layer));
+        }
+        out
+    }
+
+    fn version(pairs: &[(i32, String)]) -> String {
+        let header = section(pairs, "HEADER");
+        let at = header.iter().position(|p| *p == (9, "$ACADVER".into())).unwrap();
+        header[at + 1].1.clone()
+    }
+
+    fn square(x: f64) -> Vec<Point2D> {
+        vec![Point2D::new(x, 0.0), Point2D::new(x + 100.0, 0.0), Point2D::new(x + 100.0, 100.0), Point2D::new(x, 100.0)]
+    }
+
+    fn scene() -> ExportScene {
+        let mut scene = ExportScene::default();
+        scene.drawing.points = vec![("A".into(), Point2D::new(0.0, 0.0)), ("B".into(), Point2D::new(100.0, 0.0))];
+        scene.drawing.lines = vec![(Point2D::new(0.0, 0.0), Point2D::new(100.0, 0.0))];
+        scene.drawing.splines = vec![[Point2D::new(0.0, 0.0), Point2D::new(30.0, 50.0), Point2D::new(70.0, 50.0), Point2D::new(100.0, 0.0)]];
+        scene.pieces = vec![PieceGeometry {
+            id: 7,
+            name: "Front".into(),
+            seam: square(0.0),
+            allowance: Some(square(-10.0)),
+            notches: vec![vec![Point2D::new(50.0, 0.0), Point2D::new(50.0, -5.0)]],
+            grainline: vec![vec![Point2D::new(50.0, 20.0), Point2D::new(50.0, 80.0)]],
+            labels: Vec::new(),
+            cut_paths: Vec::new(),
+            draw_paths: Vec::new(),
+            placements: Vec::new(),
+            quantity: 1,
+            mirrored_pairs: false,
+        }];
+        scene
+    }
+
+    #[test]
+    fn layers_table_lists_aama_layers() {
+        let pairs = parse(&write_dxf(&scene(), &ExportOptions::default(), &DxfOptions::default()));
+        let tables = section(&pairs, "TABLES");
+        let names: Vec<&str> = tables.windows(2)
+            .filter(|w| w[0] == (0, "LAYER".into()) && w[1].0 == 2)
+            .map(|w| w[1].1.as_str())
+            .collect();
+        for layer in ["1", "4", "7", "8", "11", "13", "14", "15", "DRAWING_SPLINES"] {
+            assert!(names.contains(&layer), "layer {layer} missing from {names:?}");
+        }
+    }
+
+    #[test]
+    fn piece_block_uses_aama_layers() {
+        let pairs = parse(&write_dxf(&scene(), &ExportOptions::default(), &DxfOptions::default()));
+        let blocks = entities(section(&pairs, "BLOCKS"));
+        assert!(blocks.contains(&("BLOCK".into(), "0".into())));
+        let polylines: Vec<&str> = blocks.iter()
+            .filter(|(k, _)| k == "POLYLINE")
+            .map(|(_, l)| l.as_str())
+            .collect();
+        // القص ثم الخياطة ثم العلامة ثم خط النسيج
+        assert_eq!(polylines, ["1", "14", "4", "7"]);
+        assert!(blocks.contains(&("TEXT".into(), "1".into())));
+
+        let ents = entities(section(&pairs, "ENTITIES"));
+        assert!(ents.contains(&("INSERT".into(), "0".into())));
+        let insert = section(&pairs, "ENTITIES").iter().position(|p| *p == (0, "INSERT".into())).unwrap();
+        assert_eq!(section(&pairs, "ENTITIES")[insert + 2], (2, "FRONT_7".into()));
+    }
+
+    #[test]
+    fn flatten_mode_stays_r12() {
+        let pairs = parse(&write_dxf(&scene(), &ExportOptions::default(), &DxfOptions::default()));
+        assert_eq!(version(&pairs), "AC1009");
+        let ents = entities(section(&pairs, "ENTITIES"));
+        assert!(!ents.iter().any(|(k, _)| k == "SPLINE"));
+        assert!(ents.contains(&("POLYLINE".into(), "DRAWING_SPLINES".into())));
+        assert!(ents.contains(&("LINE".into(), "DRAWING_LINES".into())));
+        assert_eq!(ents.iter().filter(|(k, _)| k == "POINT").count(), 2);
+    }
+
+    #[test]
+    fn spline_mode_declares_r2000() {
+        let opts = DxfOptions { spline_mode: SplineMode::Spline, ..DxfOptions::default() };
+        let pairs = parse(&write_dxf(&scene(), &ExportOptions::default(), &opts));
+        assert_eq!(version(&pairs), "AC1015");
+        let ents = entities(section(&pairs, "ENTITIES"));
+        assert!(ents.contains(&("SPLINE".into(), "DRAWING_SPLINES".into())));
+        // حدود القطع تبقى خطوطاً متعددة
+        let blocks = entities(section(&pairs, "BLOCKS"));
+        assert!(!blocks.iter().any(|(k, _)| k == "SPLINE"));
+
+        // بدون منحنيات لا داعي لرفع الإصدار
+        let mut plain = scene();
+        plain.drawing.splines.clear();
+        let pairs = parse(&write_dxf(&plain, &ExportOptions::default(), &opts));
+        assert_eq!(version(&pairs), "AC1009");
+    }
+
+    #[test]
+    fn y_axis_points_up() {
+        let mut scene = ExportScene::default();
+        scene.drawing.lines = vec![(Point2D::new(10.0, 20.0), Point2D::new(30.0, 40.0))];
+        let pairs = parse(&write_dxf(&scene, &ExportOptions { mm_per_unit: 2.0, ..ExportOptions::default() }, &DxfOptions::default()));
+        let ents = section(&pairs, "ENTITIES");
+        let line = ents.iter().position(|p| *p == (0, "LINE".into())).unwrap();
+        let coords: Vec<&str> = ents[line + 2..line + 8].iter().map(|(_, v)| v.as_str()).collect();
+        assert_eq!(coords, ["20", "-40", "0", "60", "-80", "0"]);
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt::Write;
+use crate::error::{Error, Result};
+use crate::geometry::Point2D;
+use crate::piece::PieceGeometry;
+use super::{ExportOptions, Layer, piece_polylines, fmt_num};
+
+/// وحدات HP-GL القياسية: 40 وحدة لكل مليمتر (0.025 مم)
+pub const DEFAULT_UNITS_PER_MM: f64 = 40.0;
+
+/// إعدادات الراسم: دقة الوحدات وأرقام الأقلام لكل نوع خط
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HpglOptions {
+    pub units_per_mm: f64,
+    pub cut_pen: u8,
+    pub seam_pen: u8,
+    /// العلامات وخط النسيج والمسارات الداخلية
+    pub mark_pen: u8,
+    pub text_pen: u8,
+}
+
+impl Default for HpglOptions {
+    fn default() -> Self {
+        Self { units_per_mm: DEFAULT_UNITS_PER_MM, cut_pen: 1, seam_pen: 2, mark_pen: 3, text_pen: 4 }
+    }
+}
+
+impl HpglOptions {
+    pub fn pen_for(&self, layer: Layer) -> u8 {
+        match layer {
+            Layer::CutLine => self.cut_pen,
+            Layer::SeamLine => self.seam_pen,
+            Layer::Text | Layer::DrawingPoints => self.text_pen,
+            _ => self.mark_pen,
+        }
+    }
+
+    /// القلم 0 في HP-GL يعني إعادة القلم إلى مكانه، فالأقلام تبدأ من 1
+    pub fn validate(&self) -> Result<()> {
+        for (kind, pen) in [("cut", self.cut_pen), ("seam", self.seam_pen), ("mark", self.mark_pen), ("text", self.text_pen)] {
+            if pen < 1 {
+                return Err(Error::Validation(format!("{} pen must be 1 or higher (pen 0 puts the pen away)", kind)));
+            }
+        }
+        Ok(())
+    }
+
+    /// وحدة راسم واحدة بوحدات الرسم: الدقة المطلوبة لتقسيم المنحنيات
+    pub fn curve_tolerance(&self, opts: &ExportOptions) -> f64 {
+        1.0 / (self.units_per_mm * opts.mm_per_unit)
+    }
+}
+
+/// كتابة تخطيط (قطع موضوعة على قماش بعرض `fabric_width`) كملف HP-GL/PLT
+/// المحور X على طول القماش والمحور Y على عرضه (لأعلى كما في HP-GL)
+/// كل قلم يُرسم مرة واحدة، والقص في النهاية بعد الرسم والنصوص
+pub fn write_hpgl(pieces: &[PieceGeometry], fabric_width: f64, opts: &ExportOptions, hpgl: &HpglOptions) -> Result<String> {
+    hpgl.validate()?;
+    let k = opts.mm_per_unit * hpgl.units_per_mm;
+    let unit = |p: &Point2D| ((p.x * k).round() as i64, ((fabric_width - p.y) * k).round() as i64);
+
+    let mut out = String::from("IN;\n");
+    let polylines: Vec<(Layer, Vec<Point2D>)> = pieces.iter().flat_map(piece_polylines).collect();
+    let mut pens: Vec<u8> = vec![hpgl.mark_pen, hpgl.text_pen, hpgl.seam_pen, hpgl.cut_pen];
+    let mut seen = Vec::new();
+    pens.retain(|p| if seen.contains(p) { false } else { seen.push(*p); true });
+
+    for pen in pens {
+        let mut body = String::new();
+        for (_, poly) in polylines.iter().filter(|(l, _)| hpgl.pen_for(*l) == pen) {
+            let mut pts: Vec<(i64, i64)> = poly.iter().map(unit).collect();
+            pts.dedup();
+            let Some((first, rest)) = pts.split_first() else { continue };
+            write!(body, "PU{},{};", first.0, first.1).unwrap();
+            if !rest.is_empty() {
+                let coords: Vec<String> = rest.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+                write!(body, "PD{};", coords.join(",")).unwrap();
+            }
+            body.push('\n');
+        }
+        if hpgl.text_pen == pen {
+            for label in pieces.iter().flat_map(|p| p.labels.iter()) {
+                // SI بالسنتيمتر: عرض الحرف وارتفاعه
+                let height_cm = label.font_size * opts.mm_per_unit / 10.0;
+                writeln!(body, "SI{},{};LO4;", fmt_num(height_cm * 0.6), fmt_num(height_cm * 0.7)).unwrap();
+                for (i, line) in label.lines.iter().enumerate() {
+                    let at = Point2D::new(label.at.x, label.at.y + label.font_size * 1.2 * i as f64);
+                    let (x, y) = unit(&at);
+                    // محارف التحكم تنهي الأمر LB، فتُستبعد
+                    let text: String = line.chars().filter(|c| !c.is_control()).collect();
+                    writeln!(body, "PU{},{};LB{}\u{3}", x, y, text).unwrap();
+                }
+            }
+        }
+        if !body.is_empty() {
+            writeln!(out, "SP{};", pen).unwrap();
+            out.push_str(&body);
+        }
+    }
+    out.push_str("PU;SP0;\n");
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::PlacedText;
+
+    fn rect(x: f64, y: f64, w: f64, h: f64) -> Vec<Point2D> {
+        vec![Point2D::new(x, y), Point2D::new(x + w, y), Point2D::new(x + w, y + h), Point2D::new(x, y + h)]
+    }
+
+    /// مستطيل 10×5 مم بهامش 1 مم، علامة واحدة وملصق بسطرين أحدهما فيه محرف تحكم
+    fn piece() -> PieceGeometry {
+        PieceGeometry {
+            id: 1,
+            name: "Back".into(),
+            seam: rect(1.0, 1.0, 10.0, 5.0),
+            allowance: Some(rect(0.0, 0.0, 12.0, 7.0)),
+            notches: vec![vec![Point2D::new(6.0, 0.0), Point2D::new(6.0, 1.0)]],
+            grainline: Vec::new(),
+            labels: vec![PlacedText { at: Point2D::new(2.0, 3.0), font_size: 5.0, lines: vec!["Back".into(), "Cut\u{3} 2".into()] }],
+            cut_paths: Vec::new(),
+            draw_paths: Vec::new(),
+            placements: Vec::new(),
+            quantity: 1,
+            mirrored_pairs: false,
+        }
+    }
+
+    #[test]
+    fn golden_output() {
+        let out = write_hpgl(&[piece()], 20.0, &ExportOptions::default(), &HpglOptions::default()).unwrap();
+        let expected = "IN;\n\
+            SP3;\n\
+            PU240,800;PD240,760;\n\
+            SP4;\n\
+            SI0.3,0.35;LO4;\n\
+            PU80,680;LBBack\u{3}\n\
+            PU80,440;LBCut 2\u{3}\n\
+            SP2;\n\
+            PU40,760;PD440,760,440,560,40,560,40,760;\n\
+            SP1;\n\
+            PU0,800;PD480,800,480,520,0,520,0,800;\n\
+            PU;SP0;\n";
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn cut_pen_is_last_even_when_renumbered() {
+        let hpgl = HpglOptions { cut_pen: 2, seam_pen: 5, mark_pen: 1, text_pen: 1, ..HpglOptions::default() };
+        let out = write_hpgl(&[piece()], 20.0, &ExportOptions::default(), &hpgl).unwrap();
+        let pens: Vec<&str> = out.lines().filter_map(|l| l.split(';').find(|c| c.starts_with("SP"))).collect();
+        // قلم مشترك للعلامات والنصوص يُختار مرة واحدة
+        assert_eq!(pens, ["SP1", "SP5", "SP2", "SP0"]);
+    }
+
+    #[test]
+    fn y_flips_against_fabric_width() {
+        let opts = ExportOptions { mm_per_unit: 10.0, ..ExportOptions::default() };
+        let hpgl = HpglOptions { units_per_mm: 1.0, ..HpglOptions::default() };
+        let mut p = piece();
+        p.allowance = None;
+        p.notches.clear();
+        p.labels.clear();
+        p.seam = vec![Point2D::new(0.0, 0.0), Point2D::new(3.0, 0.0), Point2D::new(3.0, 2.0)];
+        // العرض 8: y=0 على الحافة العليا (80) و y=2 أقرب للمحور (60)
+        let out = write_hpgl(&[p], 8.0, &opts, &hpgl).unwrap();
+        assert!(out.contains("SP1;\nPU0,80;PD30,80,30,60,0,80;\n"), "{out}");
+    }
+
+    #[test]
+    fn labels_end_with_etx() {
+        let out = write_hpgl(&[piece()], 20.0, &ExportOptions::default(), &HpglOptions::default()).unwrap();
+        let labels: Vec<&str> = out.lines().filter(|l| l.contains("LB")).collect();
+        assert_eq!(labels.len(), 2);
+        for l in labels {
+            let text = &l[l.find("LB").unwrap() + 2..];
+            assert_eq!(text.matches('\u{3}').count(), 1, "{l:?}");
+            assert!(text.ends_with('\u{3}'), "{l:?}");
+        }
+    }
+
+    #[test]
+    fn pen_zero_is_rejected() {
+        for hpgl in [
+            HpglOptions { cut_pen: 0, ..HpglOptions::default() },
+            HpglOptions { text_pen: 0, ..HpglOptions::default() },
+        ] {
+            let Err(err) = write_hpgl(&[piece()], 20.0, &ExportOptions::default(), &hpgl) else { panic!("pen 0 accepted") };
+            assert!(err.to_string().contains("pen must be 1"));
+        }
+    }
+}
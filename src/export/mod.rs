@@ -0,0 +1,223 @@
+//! التصدير: مشهد موحد (الرسم + القطع) تكتبه كل صيغة بطريقتها
+pub mod svg;
+pub mod dxf;
+pub mod pdf;
+pub mod hpgl;
+pub mod png;
+
+use crate::geometry::Point2D;
+use crate::object::{VPoint, VLine, VCubicBezier, VBisector};
+use crate::contour::ContourReport;
+use crate::piece::{VPiece, PieceGeometry};
+use crate::annotation::PatternInfo;
+use crate::spatial::{IdIndex, Bounds};
+
+/// ماذا نصدّر من المشروع
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportContent {
+    #[default]
+    All,
+    DrawingOnly,
+    PiecesOnly,
+}
+
+impl ExportContent {
+    pub const ALL: [ExportContent; 3] = [ExportContent::All, ExportContent::DrawingOnly, ExportContent::PiecesOnly];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportContent::All => "Drawing + pieces",
+            ExportContent::DrawingOnly => "Drawing only",
+            ExportContent::PiecesOnly => "Pieces only",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.label() == label)
+    }
+
+    fn drawing(&self) -> bool {
+        matches!(self, ExportContent::All | ExportContent::DrawingOnly)
+    }
+
+    fn pieces(&self) -> bool {
+        matches!(self, ExportContent::All | ExportContent::PiecesOnly)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportOptions {
+    pub content: ExportContent,
+    /// عدد المليمترات لكل وحدة رسم (وحدة الرسم = 1 مم افتراضياً)
+    pub mm_per_unit: f64,
+    /// هامش فارغ حول المحتوى بالمليمتر
+    pub margin_mm: f64,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self { content: ExportContent::All, mm_per_unit: 1.0, margin_mm: 10.0 }
+    }
+}
+
+/// طبقات المخرجات: كل نوع عنصر في طبقة مستقلة (مجموعة SVG أو طبقة DXF أو قلم راسم)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Layer {
+    DrawingPoints,
+    DrawingLines,
+    DrawingSplines,
+    DrawingBisectors,
+    CutLine,
+    SeamLine,
+    Notches,
+    Grainline,
+    InternalCut,
+    InternalDraw,
+    Placements,
+    Text,
+}
+
+impl Layer {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layer::DrawingPoints => "drawing-points",
+            Layer::DrawingLines => "drawing-lines",
+            Layer::DrawingSplines => "drawing-splines",
+            Layer::DrawingBisectors => "drawing-bisectors",
+            Layer::CutLine => "cut-line",
+            Layer::SeamLine => "seam-line",
+            Layer::Notches => "notches",
+            Layer::Grainline => "grainline",
+            Layer::InternalCut => "internal-cut",
+            Layer::InternalDraw => "internal-draw",
+            Layer::Placements => "placements",
+            Layer::Text => "text",
+        }
+    }
+}
+
+/// خطوط الرسم الأساسي بعد حل معرفات النقاط
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DrawingGeometry {
+    pub points: Vec<(String, Point2D)>,
+    pub lines: Vec<(Point2D, Point2D)>,
+    pub splines: Vec<[Point2D; 4]>,
+    pub bisectors: Vec<(Point2D, Point2D)>,
+    /// مسارات لم تُبنَ عليها قطعة (مرتبة، والمغلقة تنتهي بنقطة البداية)
+    pub contours: Vec<Vec<Point2D>>,
+}
+
+/// كل ما يُصدّر، في وحدات الرسم
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExportScene {
+    pub drawing: DrawingGeometry,
+    pub pieces: Vec<PieceGeometry>,
+}
+
+impl ExportScene {
+    #[allow(clippy::too_many_arguments)]
+    pub fn build(
+        points: &[VPoint],
+        lines: &[VLine],
+        splines: &[VCubicBezier],
+        bisectors: &[VBisector],
+        pieces: &[VPiece],
+        reports: &[(u32, ContourReport)],
+        pattern: &PatternInfo,
+        content: ExportContent,
+    ) -> Self {
+        let mut scene = ExportScene::default();
+        if content.drawing() {
+            let ids = IdIndex::build(points, |p| p.metadata.id);
+            let find = |id: u32| ids.get(points, id);
+            let d = &mut scene.drawing;
+            d.points = points.iter().map(|p| (p.metadata.name.clone(), p.coords)).collect();
+            d.lines = lines.iter()
+                .filter_map(|l| Some((find(l.start_point_id)?.coords, find(l.end_point_id)?.coords)))
+                .collect();
+            d.splines = splines.iter()
+                .filter_map(|s| Some([find(s.p1_id)?.coords, find(s.p2_id)?.coords, find(s.p3_id)?.coords, find(s.p4_id)?.coords]))
+                .collect();
+            d.bisectors = bisectors.iter()
+                .filter_map(|b| {
+                    let (p1, v, p3) = (find(b.p1_id)?, find(b.vertex_id)?, find(b.p3_id)?);
+                    // المنصف المنحل لا اتجاه له فلا يُصدَّر
+                    Some((v.coords, b.calculate_end_point(p1, v, p3, 0.0).ok()?))
+                })
+                .collect();
+            d.contours = reports.iter()
+                .filter(|(id, _)| !pieces.iter().any(|p| p.contour_id == *id))
+                .map(|(_, r)| {
+                    let mut poly = r.outline();
+                    if r.closed && let Some(first) = poly.first().copied() {
+                        poly.push(first);
+                    }
+                    poly
+                })
+                .filter(|poly| poly.len() > 1)
+                .collect();
+        }
+        if content.pieces() {
+            scene.pieces = pieces.iter()
+                .filter_map(|p| PieceGeometry::build(p, reports, points, pattern))
+                .collect();
+        }
+        scene
+    }
+
+    /// كل الخطوط المتعددة مع طبقاتها (بدون النصوص والنقاط)
+    pub fn polylines(&self) -> Vec<(Layer, Vec<Point2D>)> {
+        let mut out: Vec<(Layer, Vec<Point2D>)> = Vec::new();
+        let d = &self.drawing;
+        out.extend(d.lines.iter().map(|(a, b)| (Layer::DrawingLines, vec![*a, *b])));
+        out.extend(d.splines.iter().map(|[p0, p1, p2, p3]| {
+            (Layer::DrawingSplines, crate::geometry::flatten_cubic(p0, p1, p2, p3, 32))
+        }));
+        out.extend(d.bisectors.iter().map(|(a, b)| (Layer::DrawingBisectors, vec![*a, *b])));
+        for piece in &self.pieces {
+            out.extend(piece_polylines(piece));
+        }
+        out
+    }
+
+    /// المستطيل المحيط بكل المحتوى (وحدات الرسم)
+    pub fn bounds(&self) -> Option<Bounds> {
+        let mut pts: Vec<Point2D> = self.polylines().into_iter().flat_map(|(_, p)| p).collect();
+        pts.extend(self.drawing.points.iter().map(|(_, p)| *p));
+        pts.extend(self.drawing.contours.iter().flatten().copied());
+        pts.extend(self.pieces.iter().flat_map(|p| p.labels.iter().map(|l| l.at)));
+        Bounds::from_points(&pts)
+    }
+}
+
+/// رقم بثلاث خانات عشرية كحد أقصى وبدون أصفار زائدة
+pub fn fmt_num(v: f64) -> String {
+    let s = format!("{:.3}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s.is_empty() || s == "-0" { "0".to_string() } else { s.to_string() }
+}
+
+/// خطوط قطعة واحدة مع طبقاتها؛ الخطوط المغلقة تنتهي بنقطة البداية
+pub fn piece_polylines(piece: &PieceGeometry) -> Vec<(Layer, Vec<Point2D>)> {
+    let closed = |poly: &[Point2D]| {
+        let mut p = poly.to_vec();
+        if let Some(first) = p.first().copied() {
+            p.push(first);
+        }
+        p
+    };
+    let mut out = Vec::new();
+    if let Some(sa) = &piece.allowance {
+        out.push((Layer::CutLine, closed(sa)));
+        out.push((Layer::SeamLine, closed(&piece.seam)));
+    } else {
+        // بدون هامش: خط الخياطة هو خط القص
+        out.push((Layer::CutLine, closed(&piece.seam)));
+    }
+    out.extend(piece.notches.iter().map(|p| (Layer::Notches, p.clone())));
+    out.extend(piece.grainline.iter().map(|p| (Layer::Grainline, p.clone())));
+    out.extend(piece.cut_paths.iter().map(|p| (Layer::InternalCut, p.clone())));
+    out.extend(piece.draw_paths.iter().map(|p| (Layer::InternalDraw, p.clone())));
+    out.extend(piece.placements.iter().map(|p| (Layer::Placements, p.clone())));
+    out
+}
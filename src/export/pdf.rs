@@ -0,0 +1,421 @@
+use std::fmt::Write;
+use crate::geometry::Point2D;
+use super::{ExportScene, ExportOptions, Layer, fmt_num};
+use super::svg::layer_style;
+
+/// عدد النقاط (pt) في المليمتر
+const PT_PER_MM: f64 = 72.0 / 25.4;
+
+/// ضلع مربع اختبار المقاس بالمليمتر
+pub const TEST_SQUARE_MM: f64 = 50.0;
+
+/// مقاس الورق للطباعة المقسمة
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaperSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl PaperSize {
+    pub const ALL: [PaperSize; 2] = [PaperSize::A4, PaperSize::Letter];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PaperSize::A4 => "A4",
+            PaperSize::Letter => "Letter",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.label() == label)
+    }
+
+    /// العرض والارتفاع بالمليمتر (عمودي)
+    pub fn size_mm(&self) -> (f64, f64) {
+        match self {
+            PaperSize::A4 => (210.0, 297.0),
+            PaperSize::Letter => (215.9, 279.4),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PdfOptions {
+    /// تقسيم المخطط على صفحات بمقاس الورق، وإلا صفحة واحدة بمقاس المحتوى
+    pub tiled: bool,
+    pub paper: PaperSize,
+    /// هامش غير مطبوع حول كل صفحة
+    pub page_margin_mm: f64,
+    /// التداخل بين الصفحات المتجاورة للصق
+    pub overlap_mm: f64,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self { tiled: true, paper: PaperSize::A4, page_margin_mm: 10.0, overlap_mm: 10.0 }
+    }
+}
+
+impl PdfOptions {
+    /// المساحة المطبوعة من الورقة بعد الهامش
+    pub fn printable_mm(&self) -> (f64, f64) {
+        let (w, h) = self.paper.size_mm();
+        (w - 2.0 * self.page_margin_mm, h - 2.0 * self.page_margin_mm)
+    }
+
+    /// أكبر تداخل مسموح: كل صفحة تتقدم مليمتراً واحداً على الأقل
+    pub fn max_overlap_mm(&self) -> f64 {
+        let (pw, ph) = self.printable_mm();
+        (pw.min(ph) - 1.0).max(0.0)
+    }
+}
+
+/// صفحة واحدة: الجزء المعروض من المخطط (بالمليمتر) وموقعها في الشبكة
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub row: usize,
+    pub col: usize,
+    /// الركن العلوي الأيسر للمنطقة المطبوعة في إحداثيات المخطط
+    pub origin: Point2D,
+}
+
+impl Tile {
+    /// اسم الصفحة في الشبكة: حرف للصف ورقم للعمود (A1, A2, B1...)
+    pub fn label(&self) -> String {
+        let mut row = String::new();
+        let mut r = self.row + 1;
+        while r > 0 {
+            row.insert(0, (b'A' + ((r - 1) % 26) as u8) as char);
+            r = (r - 1) / 26;
+        }
+        format!("{}{}", row, self.col + 1)
+    }
+}
+
+/// تقسيم منطقة بعرض `w` وارتفاع `h` على صفحات مساحتها المطبوعة `pw`×`ph`
+/// كل صفحة تبدأ بعد سابقتها بمقدار المساحة ناقص التداخل (ومليمتر واحد على الأقل)
+pub fn tile_grid(min: Point2D, w: f64, h: f64, pw: f64, ph: f64, overlap: f64) -> (usize, usize, Vec<Tile>) {
+    let step = |page: f64| (page - overlap).max(1.0);
+    let count = |len: f64, page: f64| {
+        if len <= page { 1 } else { ((len - page) / step(page)).ceil() as usize + 1 }
+    };
+    let (cols, rows) = (count(w, pw), count(h, ph));
+    let (step_x, step_y) = (step(pw), step(ph));
+    let tiles = (0..rows)
+        .flat_map(|row| (0..cols).map(move |col| Tile {
+            row,
+            col,
+            origin: Point2D::new(min.x + col as f64 * step_x, min.y + row as f64 * step_y),
+        }))
+        .collect();
+    (cols, rows, tiles)
+}
+
+/// لون "#rrggbb" كمكونات PDF بين 0 و1
+fn rgb(hex: &str) -> String {
+    let c = |i: usize| u8::from_str_radix(hex.get(i..i + 2).unwrap_or("00"), 16).unwrap_or(0) as f64 / 255.0;
+    format!("{} {} {}", fmt_num(c(1)), fmt_num(c(3)), fmt_num(c(5)))
+}
+
+/// نص PDF بترميز WinAnsi: المحارف خارج Latin-1 تُستبدل بـ "?"
+fn pdf_string(text: &str) -> String {
+    let mut out = String::from("(");
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => { out.push('\\'); out.push(c); }
+            ' '..='~' => out.push(c),
+            c if (0xA0..=0xFF).contains(&(c as u32)) => { write!(out, "\\{:03o}", c as u32).unwrap(); }
+            _ => out.push('?'),
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// كاتب محتوى صفحة: إحداثيات بالمليمتر من الركن العلوي الأيسر للصفحة
+struct Page {
+    ops: String,
+    height_mm: f64,
+}
+
+impl Page {
+    fn xy(&self, p: &Point2D) -> String {
+        format!("{} {}", fmt_num(p.x * PT_PER_MM), fmt_num((self.height_mm - p.y) * PT_PER_MM))
+    }
+
+    fn stroke_style(&mut self, color: &str, width_mm: f64, dash_mm: Option<(f64, f64)>) {
+        let dash = dash_mm
+            .map(|(on, off)| format!("[{} {}]", fmt_num(on * PT_PER_MM), fmt_num(off * PT_PER_MM)))
+            .unwrap_or_else(|| "[]".to_string());
+        writeln!(self.ops, "{} RG {} w {} 0 d", rgb(color), fmt_num(width_mm.max(0.1) * PT_PER_MM), dash).unwrap();
+    }
+
+    fn polyline(&mut self, poly: &[Point2D]) {
+        for (i, p) in poly.iter().enumerate() {
+            writeln!(self.ops, "{} {}", self.xy(p), if i == 0 { "m" } else { "l" }).unwrap();
+        }
+        writeln!(self.ops, "S").unwrap();
+    }
+
+    /// نص في موضع `at`؛ `centered` يوسّطه تقريبياً حول الموضع
+    fn text(&mut self, at: &Point2D, size_mm: f64, text: &str, centered: bool) {
+        let width = if centered { text.chars().count() as f64 * size_mm * 0.5 } else { 0.0 };
+        let p = Point2D::new(at.x - width / 2.0, at.y);
+        writeln!(self.ops, "BT /F1 {} Tf {} Td {} Tj ET", fmt_num(size_mm * PT_PER_MM), self.xy(&p), pdf_string(text)).unwrap();
+    }
+
+    fn cross(&mut self, at: &Point2D, r: f64) {
+        self.polyline(&[Point2D::new(at.x - r, at.y), Point2D::new(at.x + r, at.y)]);
+        self.polyline(&[Point2D::new(at.x, at.y - r), Point2D::new(at.x, at.y + r)]);
+        self.polyline(&circle(at, r * 0.6));
+    }
+}
+
+fn circle(c: &Point2D, r: f64) -> Vec<Point2D> {
+    (0..=16)
+        .map(|i| {
+            let t = std::f64::consts::TAU * i as f64 / 16.0;
+            Point2D::new(c.x + r * t.cos(), c.y + r * t.sin())
+        })
+        .collect()
+}
+
+/// رسم المخطط كاملاً بإزاحة `shift` (مم) ومقياس `s` (مم لكل وحدة)
+fn draw_scene(page: &mut Page, scene: &ExportScene, s: f64, shift: Point2D) {
+    let at = |p: &Point2D| Point2D::new(p.x * s + shift.x, p.y * s + shift.y);
+    let mut current: Option<Layer> = None;
+    for (layer, poly) in scene.polylines() {
+        if current != Some(layer) {
+            let style = layer_style(layer);
+            page.stroke_style(style.stroke, style.width_mm, style.dash_mm);
+            current = Some(layer);
+        }
+        let poly: Vec<Point2D> = poly.iter().map(at).collect();
+        page.polyline(&poly);
+    }
+    let style = layer_style(Layer::DrawingPoints);
+    page.stroke_style(style.stroke, style.width_mm, None);
+    writeln!(page.ops, "0 0 0 rg").unwrap();
+    for (name, p) in &scene.drawing.points {
+        let p = at(p);
+        page.polyline(&circle(&p, 0.8));
+        page.text(&Point2D::new(p.x + 1.5, p.y - 1.5), 3.0, name, false);
+    }
+    for label in scene.pieces.iter().flat_map(|p| p.labels.iter()) {
+        let size = label.font_size * s;
+        let base = at(&label.at);
+        for (i, line) in label.lines.iter().enumerate() {
+            page.text(&Point2D::new(base.x, base.y + size * 1.2 * i as f64), size, line, true);
+        }
+    }
+}
+
+/// مربع اختبار المقاس بركنه العلوي الأيسر في `at`
+fn draw_test_square(page: &mut Page, at: Point2D) {
+    let a = TEST_SQUARE_MM;
+    page.stroke_style("#000000", 0.3, None);
+    page.polyline(&[
+        at,
+        Point2D::new(at.x + a, at.y),
+        Point2D::new(at.x + a, at.y + a),
+        Point2D::new(at.x, at.y + a),
+        at,
+    ]);
+    page.text(&Point2D::new(at.x + a / 2.0, at.y + a / 2.0), 4.0, &format!("{} mm", fmt_num(a)), true);
+    page.text(&Point2D::new(at.x + a / 2.0, at.y + a / 2.0 + 5.0), 2.5, "test square", true);
+}
+
+/// تجميع ملف PDF من محتويات الصفحات (بدون ضغط) بخط Helvetica
+fn assemble(pages: &[(f64, f64, String)]) -> Vec<u8> {
+    let mut objects: Vec<String> = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        String::new(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+    ];
+    let mut kids = Vec::new();
+    for (w, h, ops) in pages {
+        let page_id = objects.len() + 1;
+        kids.push(format!("{} 0 R", page_id));
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            fmt_num(w * PT_PER_MM), fmt_num(h * PT_PER_MM), page_id + 1
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{}endstream", ops.len(), ops));
+    }
+    objects[1] = format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len());
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, obj) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        writeln!(out, "{} 0 obj\n{}\nendobj", i + 1, obj).unwrap();
+    }
+    let xref = out.len();
+    writeln!(out, "xref\n0 {}\n0000000000 65535 f ", objects.len() + 1).unwrap();
+    for off in offsets {
+        writeln!(out, "{:010} 00000 n ", off).unwrap();
+    }
+    write!(out, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).unwrap();
+    out.into_bytes()
+}
+
+/// كتابة المشهد كملف PDF بالمقاس الحقيقي 1:1
+/// في وضع التقسيم: صفحات متداخلة بعلامات محاذاة وأسماء شبكة ومربع اختبار في الصفحة الأولى
+pub fn write_pdf(scene: &ExportScene, opts: &ExportOptions, pdf: &PdfOptions) -> Vec<u8> {
+    let s = opts.mm_per_unit;
+    let (min, max) = match scene.bounds() {
+        Some(b) => (Point2D::new(b.min.x * s, b.min.y * s), Point2D::new(b.max.x * s, b.max.y * s)),
+        None => (Point2D::new(0.0, 0.0), Point2D::new(0.0, 0.0)),
+    };
+    // مربع الاختبار فوق المحتوى في الركن الأيسر
+    let gap = 10.0;
+    let square_at = Point2D::new(min.x, min.y - TEST_SQUARE_MM - gap);
+    let region_min = Point2D::new(min.x - opts.margin_mm, square_at.y - opts.margin_mm);
+    let region_w = max.x - min.x + 2.0 * opts.margin_mm;
+    let region_h = max.y - region_min.y + opts.margin_mm;
+
+    if !pdf.tiled {
+        let mut page = Page { ops: String::new(), height_mm: region_h };
+        let shift = Point2D::new(-region_min.x, -region_min.y);
+        draw_scene(&mut page, scene, s, shift);
+        draw_test_square(&mut page, Point2D::new(square_at.x + shift.x, square_at.y + shift.y));
+        return assemble(&[(region_w, region_h, page.ops)]);
+    }
+
+    let (paper_w, paper_h) = pdf.paper.size_mm();
+    let m = pdf.page_margin_mm;
+    let (pw, ph) = pdf.printable_mm();
+    let overlap = pdf.overlap_mm.clamp(0.0, pdf.max_overlap_mm());
+    let (cols, rows, tiles) = tile_grid(region_min, region_w, region_h, pw, ph, overlap);
+
+    let pages: Vec<(f64, f64, String)> = tiles.iter().map(|tile| {
+        let mut page = Page { ops: String::new(), height_mm: paper_h };
+        let shift = Point2D::new(m - tile.origin.x, m - tile.origin.y);
+        let local = |p: Point2D| Point2D::new(p.x + shift.x, p.y + shift.y);
+
+        // المحتوى مقصوص على المساحة المطبوعة
+        let corner = page.xy(&Point2D::new(m, m + ph));
+        writeln!(page.ops, "q {} {} {} re W n", corner, fmt_num(pw * PT_PER_MM), fmt_num(ph * PT_PER_MM)).unwrap();
+        draw_scene(&mut page, scene, s, shift);
+        if tile.row == 0 && tile.col == 0 {
+            draw_test_square(&mut page, local(square_at));
+        }
+        writeln!(page.ops, "Q").unwrap();
+
+        // إطار المساحة المطبوعة وخطوط بداية التداخل مع الصفحات المجاورة
+        page.stroke_style("#999999", 0.1, None);
+        page.polyline(&[
+            Point2D::new(m, m),
+            Point2D::new(m + pw, m),
+            Point2D::new(m + pw, m + ph),
+            Point2D::new(m, m + ph),
+            Point2D::new(m, m),
+        ]);
+        page.stroke_style("#999999", 0.1, Some((2.0, 2.0)));
+        if tile.col > 0 {
+            page.polyline(&[Point2D::new(m + overlap, m), Point2D::new(m + overlap, m + ph)]);
+        }
+        if tile.col + 1 < cols {
+            page.polyline(&[Point2D::new(m + pw - overlap, m), Point2D::new(m + pw - overlap, m + ph)]);
+        }
+        if tile.row > 0 {
+            page.polyline(&[Point2D::new(m, m + overlap), Point2D::new(m + pw, m + overlap)]);
+        }
+        if tile.row + 1 < rows {
+            page.polyline(&[Point2D::new(m, m + ph - overlap), Point2D::new(m + pw, m + ph - overlap)]);
+        }
+
+        // علامات المحاذاة في منتصف شرائط التداخل: تظهر في نفس الموضع على الصفحتين
+        page.stroke_style("#000000", 0.2, None);
+        let half = overlap / 2.0;
+        if tile.col > 0 {
+            for y in [m + ph / 4.0, m + ph * 3.0 / 4.0] {
+                page.cross(&Point2D::new(m + half, y), 3.0);
+            }
+        }
+        if tile.col + 1 < cols {
+            for y in [m + ph / 4.0, m + ph * 3.0 / 4.0] {
+                page.cross(&Point2D::new(m + pw - half, y), 3.0);
+            }
+        }
+        if tile.row > 0 {
+            for x in [m + pw / 4.0, m + pw * 3.0 / 4.0] {
+                page.cross(&Point2D::new(x, m + half), 3.0);
+            }
+        }
+        if tile.row + 1 < rows {
+            for x in [m + pw / 4.0, m + pw * 3.0 / 4.0] {
+                page.cross(&Point2D::new(x, m + ph - half), 3.0);
+            }
+        }
+
+        // اسم الصفحة كبيراً بلون فاتح في الوسط، وسطر معلومات في الهامش السفلي
+        writeln!(page.ops, "0.85 0.85 0.85 rg").unwrap();
+        page.text(&Point2D::new(m + pw / 2.0, m + ph / 2.0), 12.0, &tile.label(), true);
+        writeln!(page.ops, "0 0 0 rg").unwrap();
+        page.text(
+            &Point2D::new(m, paper_h - m / 2.0),
+            3.0,
+            &format!("{}  -  row {}/{}, column {}/{}  -  overlap {} mm  -  scale 1:1", tile.label(), tile.row + 1, rows, tile.col + 1, cols, fmt_num(overlap)),
+            false,
+        );
+        (paper_w, paper_h, page.ops)
+    }).collect();
+
+    assemble(&pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(row: usize, col: usize) -> Tile {
+        Tile { row, col, origin: Point2D::new(0.0, 0.0) }
+    }
+
+    #[test]
+    fn labels_rows_with_letters_and_columns_with_numbers() {
+        assert_eq!(tile(0, 0).label(), "A1");
+        assert_eq!(tile(1, 2).label(), "B3");
+        assert_eq!(tile(25, 0).label(), "Z1");
+        assert_eq!(tile(26, 0).label(), "AA1");
+        assert_eq!(tile(27, 9).label(), "AB10");
+    }
+
+    #[test]
+    fn small_region_fits_one_page() {
+        let (cols, rows, tiles) = tile_grid(Point2D::new(5.0, 7.0), 100.0, 100.0, 190.0, 277.0, 10.0);
+        assert_eq!((cols, rows), (1, 1));
+        assert_eq!(tiles, vec![Tile { row: 0, col: 0, origin: Point2D::new(5.0, 7.0) }]);
+    }
+
+    #[test]
+    fn pages_advance_by_printable_area_minus_overlap() {
+        // 100×50 على صفحات 40×30 بتداخل 10: الخطوة 30×20
+        let (cols, rows, tiles) = tile_grid(Point2D::new(0.0, 0.0), 100.0, 50.0, 40.0, 30.0, 10.0);
+        assert_eq!((cols, rows), (3, 2));
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[2].origin, Point2D::new(60.0, 0.0));
+        assert_eq!(tiles[5].origin, Point2D::new(60.0, 20.0));
+        // آخر صفحة تغطي نهاية المنطقة
+        assert!(tiles[5].origin.x + 40.0 >= 100.0 && tiles[5].origin.y + 30.0 >= 50.0);
+    }
+
+    #[test]
+    fn overlap_as_large_as_the_page_still_covers_the_region() {
+        // الخطوة تُحصر بمليمتر واحد في العد وفي مواضع الصفحات معاً
+        let (cols, rows, tiles) = tile_grid(Point2D::new(0.0, 0.0), 45.0, 40.0, 40.0, 40.0, 40.0);
+        assert_eq!((cols, rows), (6, 1));
+        assert_eq!(tiles.last().unwrap().origin, Point2D::new(5.0, 0.0));
+        assert!(tiles.last().unwrap().origin.x + 40.0 >= 45.0);
+    }
+
+    #[test]
+    fn overlap_is_limited_by_the_printable_area() {
+        let opts = PdfOptions::default();
+        let (pw, ph) = opts.printable_mm();
+        assert_eq!((pw, ph), (190.0, 277.0));
+        assert_eq!(opts.max_overlap_mm(), 189.0);
+    }
+}
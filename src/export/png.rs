@@ -0,0 +1,236 @@
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont, point};
+use tiny_skia::{Color, FillRule, Paint, PathBuilder, Pixmap, PremultipliedColorU8, Stroke, StrokeDash, Transform};
+use crate::geometry::Point2D;
+use crate::spatial::Bounds;
+use super::{ExportScene, ExportOptions, Layer, piece_polylines};
+use super::svg::{layer_style, LAYER_ORDER};
+use crate::palette;
+
+/// أقصى بعد للصورة بالبكسل (حماية من الذاكرة عند DPI عالٍ)
+pub const MAX_PIXELS: u32 = 16384;
+
+/// خطوط النظام المعروفة لكتابة النصوص (أول خط موجود يُستخدم)
+const FONT_CANDIDATES: [&str; 6] = [
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PngOptions {
+    pub dpi: f64,
+    /// المنطقة المرسومة بوحدات الرسم، أو None لكل المحتوى مع الهامش
+    pub region: Option<Bounds>,
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        Self { dpi: 96.0, region: None }
+    }
+}
+
+/// أول خط متاح من خطوط النظام، أو None (تُرسم الصورة بدون نصوص)
+pub fn load_font() -> Option<FontVec> {
+    FONT_CANDIDATES.iter()
+        .find_map(|path| std::fs::read(path).ok())
+        .and_then(|data| FontVec::try_from_vec(data).ok())
+}
+
+fn hex_color(hex: &str) -> (u8, u8, u8) {
+    let c = |i: usize| u8::from_str_radix(hex.get(i..i + 2).unwrap_or("00"), 16).unwrap_or(0);
+    (c(1), c(3), c(5))
+}
+
+/// كتابة نص بلون `color`؛ `at` هو خط الأساس (وسط النص إذا كان `centered`)
+fn draw_text(pixmap: &mut Pixmap, font: &FontVec, text: &str, at: Point2D, size_px: f32, centered: bool, color: &str) {
+    let ink = hex_color(color);
+    let scaled = font.as_scaled(PxScale::from(size_px));
+    let ids: Vec<_> = text.chars().map(|c| scaled.glyph_id(c)).collect();
+    let width: f32 = ids.iter().map(|id| scaled.h_advance(*id)).sum();
+    let mut x = at.x as f32 - if centered { width / 2.0 } else { 0.0 };
+    let (w, h) = (pixmap.width() as i32, pixmap.height() as i32);
+    let pixels = pixmap.pixels_mut();
+    let mut prev = None;
+    for id in ids {
+        if let Some(p) = prev {
+            x += scaled.kern(p, id);
+        }
+        let glyph = id.with_scale_and_position(size_px, point(x, at.y as f32));
+        if let Some(outlined) = font.outline_glyph(glyph) {
+            let b = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let (px, py) = (b.min.x as i32 + gx as i32, b.min.y as i32 + gy as i32);
+                if px < 0 || py < 0 || px >= w || py >= h {
+                    return;
+                }
+                // مزج اللون فوق البكسل الموجود (ألوان مضروبة مسبقاً في الشفافية)
+                let dst = &mut pixels[(py * w + px) as usize];
+                let cover = coverage.clamp(0.0, 1.0);
+                let keep = 1.0 - cover;
+                let mix = |v: u8, c: u8| (c as f32 * cover + v as f32 * keep).round() as u8;
+                let alpha = (255.0 * cover + dst.alpha() as f32 * keep).round() as u8;
+                if let Some(c) = PremultipliedColorU8::from_rgba(mix(dst.red(), ink.0), mix(dst.green(), ink.1), mix(dst.blue(), ink.2), alpha) {
+                    *dst = c;
+                }
+            });
+        }
+        x += scaled.h_advance(id);
+        prev = Some(id);
+    }
+}
+
+/// رسم المشهد على المعالج (بدون GPU) كصورة PNG بالدقة `dpi`
+/// بألوان الكانفاس وسماكات طبقات تصدير SVG؛ يعيد None إذا كانت المنطقة فارغة أو أكبر من الحد
+pub fn render_png(scene: &ExportScene, opts: &ExportOptions, png: &PngOptions) -> Option<Vec<u8>> {
+    let px_per_mm = png.dpi / 25.4;
+    let s = opts.mm_per_unit * px_per_mm;
+    let region = match png.region {
+        Some(r) => r,
+        None => {
+            let b = scene.bounds()?;
+            let m = opts.margin_mm / opts.mm_per_unit;
+            Bounds::new(Point2D::new(b.min.x - m, b.min.y - m), Point2D::new(b.max.x + m, b.max.y + m))
+        }
+    };
+    let w = ((region.max.x - region.min.x) * s).ceil();
+    let h = ((region.max.y - region.min.y) * s).ceil();
+    if w < 1.0 || h < 1.0 || w > MAX_PIXELS as f64 || h > MAX_PIXELS as f64 {
+        return None;
+    }
+    let mut pixmap = Pixmap::new(w as u32, h as u32)?;
+    pixmap.fill(Color::WHITE);
+    let px = |p: &Point2D| ((p.x - region.min.x) * s) as f32;
+    let py = |p: &Point2D| ((p.y - region.min.y) * s) as f32;
+
+    for layer in LAYER_ORDER {
+        let style = layer_style(layer);
+        let (r, g, b) = hex_color(palette::layer_color(layer));
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(r, g, b, 255);
+        paint.anti_alias = true;
+        let mut stroke = Stroke { width: (style.width_mm * px_per_mm).max(0.5) as f32, ..Stroke::default() };
+        if let Some((on, off)) = style.dash_mm {
+            stroke.dash = StrokeDash::new(vec![(on * px_per_mm) as f32, (off * px_per_mm) as f32], 0.0);
+        }
+
+        let mut pb = PathBuilder::new();
+        let mut add_poly = |poly: &[Point2D]| {
+            for (i, p) in poly.iter().enumerate() {
+                if i == 0 { pb.move_to(px(p), py(p)) } else { pb.line_to(px(p), py(p)) }
+            }
+        };
+        match layer {
+            Layer::DrawingPoints => {
+                for (_, p) in &scene.drawing.points {
+                    if let Some(dot) = PathBuilder::from_circle(px(p), py(p), (0.8 * px_per_mm) as f32) {
+                        pixmap.fill_path(&dot, &paint, FillRule::Winding, Transform::identity(), None);
+                    }
+                }
+                continue;
+            }
+            Layer::DrawingLines => scene.drawing.lines.iter().for_each(|(a, b)| add_poly(&[*a, *b])),
+            Layer::DrawingBisectors => scene.drawing.bisectors.iter().for_each(|(a, b)| add_poly(&[*a, *b])),
+            Layer::DrawingSplines => {
+                // المنحنيات ترسم كمنحنيات بيزيه حقيقية
+                for [p0, p1, p2, p3] in &scene.drawing.splines {
+                    pb.move_to(px(p0), py(p0));
+                    pb.cubic_to(px(p1), py(p1), px(p2), py(p2), px(p3), py(p3));
+                }
+            }
+            _ => {
+                for piece in &scene.pieces {
+                    for (_, poly) in piece_polylines(piece).iter().filter(|(l, _)| *l == layer) {
+                        add_poly(poly);
+                    }
+                }
+            }
+        }
+        if let Some(path) = pb.finish() {
+            pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+        }
+    }
+
+    // المسارات بدون قطعة بلون الكانفاس وبضعف سماكة خطوط الرسم
+    let mut pb = PathBuilder::new();
+    for poly in &scene.drawing.contours {
+        for (i, p) in poly.iter().enumerate() {
+            if i == 0 { pb.move_to(px(p), py(p)) } else { pb.line_to(px(p), py(p)) }
+        }
+    }
+    if let Some(path) = pb.finish() {
+        let (r, g, b) = hex_color(palette::CONTOUR);
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(r, g, b, 255);
+        paint.anti_alias = true;
+        let width = 2.0 * layer_style(Layer::DrawingLines).width_mm * px_per_mm;
+        let stroke = Stroke { width: width.max(1.0) as f32, ..Stroke::default() };
+        pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+    }
+
+    // النصوص: أسماء النقاط وملصقات القطع
+    if let Some(font) = load_font() {
+        let mm = px_per_mm;
+        for (name, p) in &scene.drawing.points {
+            let at = Point2D::new(px(p) as f64 + 1.5 * mm, py(p) as f64 - 1.5 * mm);
+            draw_text(&mut pixmap, &font, name, at, (3.0 * mm) as f32, false, palette::INK);
+        }
+        for label in scene.pieces.iter().flat_map(|p| p.labels.iter()) {
+            let size = label.font_size * s;
+            for (i, line) in label.lines.iter().enumerate() {
+                let at = Point2D::new(px(&label.at) as f64, py(&label.at) as f64 + size * 1.2 * i as f64);
+                draw_text(&mut pixmap, &font, line, at, size as f32, true, palette::INK);
+            }
+        }
+    }
+
+    pixmap.encode_png().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ألوان البكسلات غير البيضاء في الصورة
+    fn colors(png: &[u8]) -> Vec<(u8, u8, u8)> {
+        let pixmap = Pixmap::decode_png(png).unwrap();
+        pixmap.pixels().iter()
+            .map(|p| p.demultiply())
+            .map(|c| (c.red(), c.green(), c.blue()))
+            .filter(|c| *c != (255, 255, 255))
+            .collect()
+    }
+
+    #[test]
+    fn uses_canvas_palette() {
+        let mut scene = ExportScene::default();
+        scene.drawing.lines = vec![(Point2D::new(0.0, 0.0), Point2D::new(100.0, 0.0))];
+        let png = render_png(&scene, &ExportOptions::default(), &PngOptions { dpi: 600.0, region: None }).unwrap();
+        assert!(colors(&png).contains(&hex_color(palette::LINE)));
+        assert!(!colors(&png).contains(&(0, 0, 0)));
+    }
+
+    #[test]
+    fn draws_contours_without_a_piece() {
+        let mut scene = ExportScene::default();
+        let square = [(0.0, 0.0), (50.0, 0.0), (50.0, 50.0), (0.0, 50.0), (0.0, 0.0)];
+        scene.drawing.contours = vec![square.iter().map(|&(x, y)| Point2D::new(x, y)).collect()];
+        let png = render_png(&scene, &ExportOptions::default(), &PngOptions { dpi: 600.0, region: None }).unwrap();
+        assert!(colors(&png).contains(&hex_color(palette::CONTOUR)));
+    }
+
+    #[test]
+    fn region_sets_the_image_size_and_crops() {
+        let mut scene = ExportScene::default();
+        scene.drawing.lines = vec![(Point2D::new(0.0, 0.0), Point2D::new(100.0, 0.0))];
+        // 25.4 dpi = بكسل لكل مليمتر (وحدة الرسم مليمتر افتراضياً)
+        let region = Bounds::new(Point2D::new(200.0, 200.0), Point2D::new(240.0, 230.0));
+        let png = render_png(&scene, &ExportOptions::default(), &PngOptions { dpi: 25.4, region: Some(region) }).unwrap();
+        let pixmap = Pixmap::decode_png(&png).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (40, 30));
+        // الخط خارج المنطقة
+        assert!(colors(&png).is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+use std::fmt::Write;
+use crate::geometry::Point2D;
+use super::{ExportScene, ExportOptions, Layer, piece_polylines, fmt_num};
+
+/// نمط خطوط الطبقة في المخرجات (بالمليمتر)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerStyle {
+    pub stroke: &'static str,
+    pub width_mm: f64,
+    pub dash_mm: Option<(f64, f64)>,
+}
+
+pub fn layer_style(layer: Layer) -> LayerStyle {
+    let solid = |stroke, width_mm| LayerStyle { stroke, width_mm, dash_mm: None };
+    match layer {
+        Layer::DrawingPoints => solid("#000000", 0.2),
+        Layer::DrawingLines | Layer::DrawingSplines => solid("#000000", 0.3),
+        Layer::DrawingBisectors => LayerStyle { stroke: "#000000", width_mm: 0.3, dash_mm: Some((2.0, 1.0)) },
+        Layer::CutLine => solid("#000000", 0.5),
+        Layer::SeamLine => LayerStyle { stroke: "#000000", width_mm: 0.3, dash_mm: Some((3.0, 1.5)) },
+        Layer::Notches | Layer::Grainline | Layer::InternalCut | Layer::Placements => solid("#000000", 0.3),
+        Layer::InternalDraw => LayerStyle { stroke: "#555555", width_mm: 0.2, dash_mm: Some((1.0, 1.0)) },
+        Layer::Text => solid("#000000", 0.0),
+    }
+}
+
+/// ترتيب الطبقات في الملف
+pub const LAYER_ORDER: [Layer; 11] = [
+    Layer::DrawingLines,
+    Layer::DrawingSplines,
+    Layer::DrawingBisectors,
+    Layer::DrawingPoints,
+    Layer::CutLine,
+    Layer::SeamLine,
+    Layer::InternalCut,
+    Layer::InternalDraw,
+    Layer::Notches,
+    Layer::Grainline,
+    Layer::Placements,
+];
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// كتابة المشهد كملف SVG بالمقاس الحقيقي: الـ viewBox بالمليمتر والعرض والارتفاع بوحدة mm
+/// بدون الشبكة أو أي عناصر خاصة بالواجهة
+pub fn write_svg(scene: &ExportScene, opts: &ExportOptions) -> String {
+    let s = opts.mm_per_unit;
+    let mm = |p: &Point2D| Point2D::new(p.x * s, p.y * s);
+    let num = fmt_num;
+
+    let (min, max) = match scene.bounds() {
+        Some(b) => (mm(&b.min), mm(&b.max)),
+        None => (Point2D::new(0.0, 0.0), Point2D::new(0.0, 0.0)),
+    };
+    let m = opts.margin_mm;
+    let (x0, y0) = (min.x - m, min.y - m);
+    let (w, h) = (max.x - min.x + 2.0 * m, max.y - min.y + 2.0 * m);
+
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>"#).unwrap();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{}mm" height="{}mm" viewBox="{} {} {} {}">"#,
+        num(w), num(h), num(x0), num(y0), num(w), num(h)
+    ).unwrap();
+
+    let path_of = |poly: &[Point2D]| {
+        let mut d = String::new();
+        for (i, p) in poly.iter().map(mm).enumerate() {
+            write!(d, "{}{} {} ", if i == 0 { "M" } else { "L" }, num(p.x), num(p.y)).unwrap();
+        }
+        d.trim_end().to_string()
+    };
+
+    for layer in LAYER_ORDER {
+        let style = layer_style(layer);
+        let mut body = String::new();
+        match layer {
+            Layer::DrawingPoints => {
+                for (name, p) in &scene.drawing.points {
+                    let p = mm(p);
+                    writeln!(body, r#"    <circle cx="{}" cy="{}" r="0.8"/>"#, num(p.x), num(p.y)).unwrap();
+                    writeln!(
+                        body,
+                        r#"    <text x="{}" y="{}" font-size="3" stroke="none">{}</text>"#,
+                        num(p.x + 1.5), num(p.y - 1.5), escape_xml(name)
+                    ).unwrap();
+                }
+            }
+            Layer::DrawingLines => {
+                for (a, b) in &scene.drawing.lines {
+                    writeln!(body, r#"    <path d="{}"/>"#, path_of(&[*a, *b])).unwrap();
+                }
+            }
+            Layer::DrawingSplines => {
+                // المنحنيات تُكتب كمنحنيات بيزيه حقيقية وليست مقسمة
+                for c in &scene.drawing.splines {
+                    let [p0, p1, p2, p3] = [mm(&c[0]), mm(&c[1]), mm(&c[2]), mm(&c[3])];
+                    writeln!(
+                        body,
+                        r#"    <path d="M{} {} C{} {} {} {} {} {}"/>"#,
+                        num(p0.x), num(p0.y), num(p1.x), num(p1.y), num(p2.x), num(p2.y), num(p3.x), num(p3.y)
+                    ).unwrap();
+                }
+            }
+            Layer::DrawingBisectors => {
+                for (a, b) in &scene.drawing.bisectors {
+                    writeln!(body, r#"    <path d="{}"/>"#, path_of(&[*a, *b])).unwrap();
+                }
+            }
+            _ => {
+                for piece in &scene.pieces {
+                    for (_, poly) in piece_polylines(piece).iter().filter(|(l, _)| *l == layer) {
+                        writeln!(body, r#"    <path data-piece="{}" d="{}"/>"#, escape_xml(&piece.name), path_of(poly)).unwrap();
+                    }
+                }
+            }
+        }
+        if body.is_empty() {
+            continue;
+        }
+        let dash = style.dash_mm
+            .map(|(on, off)| format!(r#" stroke-dasharray="{} {}""#, num(on), num(off)))
+            .unwrap_or_default();
+        let fill = if layer == Layer::DrawingPoints { style.stroke } else { "none" };
+        writeln!(
+            out,
+            r#"  <g id="{}" fill="{}" stroke="{}" stroke-width="{}"{}>"#,
+            layer.name(), fill, style.stroke, num(style.width_mm), dash
+        ).unwrap();
+        out.push_str(&body);
+        writeln!(out, "  </g>").unwrap();
+    }
+
+    // النصوص: ملصقات القطع
+    let labels: Vec<_> = scene.pieces.iter().flat_map(|p| p.labels.iter()).collect();
+    if !labels.is_empty() {
+        writeln!(out, r##"  <g id="{}" fill="#000000" stroke="none" text-anchor="middle" font-family="sans-serif">"##, Layer::Text.name()).unwrap();
+        for label in labels {
+            let at = mm(&label.at);
+            let size = label.font_size * s;
+            write!(out, r#"    <text x="{}" y="{}" font-size="{}">"#, num(at.x), num(at.y), num(size)).unwrap();
+            for (i, line) in label.lines.iter().enumerate() {
+                write!(
+                    out,
+                    r#"<tspan x="{}" dy="{}">{}</tspan>"#,
+                    num(at.x), if i == 0 { "0".to_string() } else { num(size * 1.2) }, escape_xml(line)
+                ).unwrap();
+            }
+            writeln!(out, "</text>").unwrap();
+        }
+        writeln!(out, "  </g>").unwrap();
+    }
+
+    writeln!(out, "</svg>").unwrap();
+    out
+}
+
+/// ألوان المقاسات في تراكب المقاسات (تتكرر إذا زاد عدد المقاسات)
+pub const SIZE_COLORS: [&str; 10] = [
+    "#e6194b", "#3cb44b", "#4363d8", "#f58231", "#911eb4",
+    "#42d4f4", "#f032e6", "#9a6324", "#800000", "#000075",
+];
+
+/// تراكب المقاسات (Nest of sizes): خط القص لكل مقاس بلون مختلف فوق بعضها، مع دليل الألوان
+/// المشاريع بدون قطع تُرسم بخطوط الرسم الأساسي
+pub fn write_size_nest(scenes: &[(String, ExportScene)], opts: &ExportOptions) -> String {
+    let s = opts.mm_per_unit;
+    let num = fmt_num;
+    let outline = |scene: &ExportScene| -> Vec<Vec<Point2D>> {
+        let has_pieces = !scene.pieces.is_empty();
+        scene.polylines().into_iter()
+            .filter(|(l, _)| if has_pieces { *l == Layer::CutLine } else { matches!(l, Layer::DrawingLines | Layer::DrawingSplines) })
+            .map(|(_, p)| p.iter().map(|p| Point2D::new(p.x * s, p.y * s)).collect())
+            .collect()
+    };
+    let sets: Vec<(&String, Vec<Vec<Point2D>>)> = scenes.iter().map(|(name, scene)| (name, outline(scene))).collect();
+
+    let all: Vec<Point2D> = sets.iter().flat_map(|(_, polys)| polys.iter().flatten().copied()).collect();
+    let (min, max) = match crate::spatial::Bounds::from_points(&all) {
+        Some(b) => (b.min, b.max),
+        None => (Point2D::new(0.0, 0.0), Point2D::new(0.0, 0.0)),
+    };
+    let m = opts.margin_mm;
+    // شريط الدليل أعلى الرسم
+    let legend_h = 6.0 * sets.len() as f64 + 4.0;
+    let (x0, y0) = (min.x - m, min.y - m - legend_h);
+    let (w, h) = (max.x - min.x + 2.0 * m, max.y - min.y + 2.0 * m + legend_h);
+
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>"#).unwrap();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{}mm" height="{}mm" viewBox="{} {} {} {}">"#,
+        num(w), num(h), num(x0), num(y0), num(w), num(h)
+    ).unwrap();
+    for (i, (name, polys)) in sets.iter().enumerate() {
+        let color = SIZE_COLORS[i % SIZE_COLORS.len()];
+        writeln!(out, r#"  <g id="size-{}" fill="none" stroke="{}" stroke-width="0.3">"#, escape_xml(name), color).unwrap();
+        for poly in polys {
+            let d: Vec<String> = poly.iter().enumerate()
+                .map(|(j, p)| format!("{}{} {}", if j == 0 { "M" } else { "L" }, num(p.x), num(p.y)))
+                .collect();
+            writeln!(out, r#"    <path d="{}"/>"#, d.join(" ")).unwrap();
+        }
+        writeln!(out, "  </g>").unwrap();
+    }
+    writeln!(out, r#"  <g id="legend" font-family="sans-serif" font-size="4">"#).unwrap();
+    for (i, (name, _)) in sets.iter().enumerate() {
+        let color = SIZE_COLORS[i % SIZE_COLORS.len()];
+        let y = min.y - m - legend_h + 6.0 * (i as f64 + 1.0);
+        writeln!(out, r#"    <rect x="{}" y="{}" width="8" height="3" fill="{}"/>"#, num(x0 + m), num(y - 3.0), color).unwrap();
+        writeln!(out, r##"    <text x="{}" y="{}" fill="#000000">{}</text>"##, num(x0 + m + 10.0), num(y), escape_xml(name)).unwrap();
+    }
+    writeln!(out, "  </g>").unwrap();
+    writeln!(out, "</svg>").unwrap();
+    out
+}
@@ -0,0 +1,253 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+/// معادلتا إحداثيي نقطة (تُحسبان من جدول القياسات عند إعادة الحساب)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PointFormula {
+    pub x: String,
+    pub y: String,
+}
+
+/// خطأ في معادلة مع موضع الحرف الذي حدث عنده
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FormulaError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at {})", self.message, self.position)
+    }
+}
+
+impl std::error::Error for FormulaError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Op(char),
+}
+
+fn tokenize(expr: &str) -> Result<Vec<(usize, Token)>, FormulaError> {
+    let chars: Vec<(usize, char)> = expr.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let (pos, c) = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].1.is_ascii_digit() || chars[i].1 == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().map(|(_, c)| c).collect();
+            let v = text.parse::<f64>()
+                .map_err(|_| FormulaError { position: pos, message: format!("invalid number '{}'", text) })?;
+            tokens.push((pos, Token::Num(v)));
+        } else if c.is_alphabetic() || c == '_' || c == '#' || c == '@' {
+            // أسماء القياسات: حروف وأرقام و_ (مع # أو @ للقياسات المخصصة كما في Valentina)
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].1.is_alphanumeric() || chars[i].1 == '_') {
+                i += 1;
+            }
+            tokens.push((pos, Token::Ident(chars[start..i].iter().map(|(_, c)| c).collect())));
+        } else if "+-*/^(),".contains(c) {
+            tokens.push((pos, Token::Op(c)));
+            i += 1;
+        } else {
+            return Err(FormulaError { position: pos, message: format!("unexpected character '{}'", c) });
+        }
+    }
+    Ok(tokens)
+}
+
+/// أقصى عمق تداخل (أقواس، دوال، إشارات أحادية، أسس متتالية) قبل رفض المعادلة بدل تجاوز المكدس
+pub const MAX_DEPTH: usize = 64;
+
+/// محلل تنازلي: expr = term (+|- term)* ، term = power (*|/ power)* ، power = unary (^ power)?
+struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    end: usize,
+    /// عمق التداخل الحالي (انظر nested)
+    depth: usize,
+    vars: &'a dyn Fn(&str) -> Option<f64>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn here(&self) -> usize {
+        self.tokens.get(self.pos).map(|(p, _)| *p).unwrap_or(self.end)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, FormulaError> {
+        Err(FormulaError { position: self.here(), message: message.into() })
+    }
+
+    fn eat(&mut self, op: char) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<f64, FormulaError> {
+        let mut v = self.term()?;
+        loop {
+            if self.eat('+') { v += self.term()?; }
+            else if self.eat('-') { v -= self.term()?; }
+            else { return Ok(v); }
+        }
+    }
+
+    fn term(&mut self) -> Result<f64, FormulaError> {
+        let mut v = self.power()?;
+        loop {
+            if self.eat('*') {
+                v *= self.power()?;
+            } else if self.peek() == Some(&Token::Op('/')) {
+                let at = self.here();
+                self.pos += 1;
+                let d = self.power()?;
+                if d == 0.0 {
+                    return Err(FormulaError { position: at, message: "division by zero".to_string() });
+                }
+                v /= d;
+            } else {
+                return Ok(v);
+            }
+        }
+    }
+
+    /// كل استدعاء ذاتي يمر من هنا ليبقى عمق المكدس محدوداً
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<f64, FormulaError>) -> Result<f64, FormulaError> {
+        if self.depth >= MAX_DEPTH {
+            return self.error(format!("formula nested deeper than {}", MAX_DEPTH));
+        }
+        self.depth += 1;
+        let v = parse(self);
+        self.depth -= 1;
+        v
+    }
+
+    fn power(&mut self) -> Result<f64, FormulaError> {
+        let base = self.unary()?;
+        if self.eat('^') { Ok(base.powf(self.nested(Self::power)?)) } else { Ok(base) }
+    }
+
+    fn unary(&mut self) -> Result<f64, FormulaError> {
+        if self.eat('-') { return Ok(-self.nested(Self::unary)?); }
+        if self.eat('+') { return self.nested(Self::unary); }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<f64, FormulaError> {
+        let at = self.here();
+        match self.peek().cloned() {
+            Some(Token::Num(v)) => { self.pos += 1; Ok(v) }
+            Some(Token::Op('(')) => {
+                self.pos += 1;
+                let v = self.nested(Self::expr)?;
+                if !self.eat(')') { return self.error("expected ')'"); }
+                Ok(v)
+            }
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                if self.eat('(') {
+                    let mut args = vec![self.nested(Self::expr)?];
+                    while self.eat(',') {
+                        args.push(self.nested(Self::expr)?);
+                    }
+                    if !self.eat(')') { return self.error("expected ')'"); }
+                    call(&name, &args).ok_or(FormulaError { position: at, message: format!("unknown function or wrong arguments '{}'", name) })
+                } else {
+                    (self.vars)(&name).ok_or(FormulaError { position: at, message: format!("unknown variable '{}'", name) })
+                }
+            }
+            Some(Token::Op(c)) => self.error(format!("unexpected '{}'", c)),
+            None => self.error("unexpected end of formula"),
+        }
+    }
+}
+
+/// الدوال المتاحة (الزوايا بالدرجات كما في بقية المشروع)
+fn call(name: &str, args: &[f64]) -> Option<f64> {
+    match (name, args) {
+        ("sqrt", [a]) => Some(a.sqrt()),
+        ("abs", [a]) => Some(a.abs()),
+        ("sin", [a]) => Some(a.to_radians().sin()),
+        ("cos", [a]) => Some(a.to_radians().cos()),
+        ("tan", [a]) => Some(a.to_radians().tan()),
+        ("min", [a, rest @ ..]) => Some(rest.iter().fold(*a, |m, v| m.min(*v))),
+        ("max", [a, rest @ ..]) => Some(rest.iter().fold(*a, |m, v| m.max(*v))),
+        _ => None,
+    }
+}
+
+/// حساب معادلة حسابية؛ `vars` يعيد قيمة اسم (قياس) أو None إن لم يكن معروفاً
+pub fn eval(expr: &str, vars: &dyn Fn(&str) -> Option<f64>) -> Result<f64, FormulaError> {
+    let tokens = tokenize(expr)?;
+    let mut parser = Parser { tokens, pos: 0, end: expr.len(), depth: 0, vars };
+    let v = parser.expr()?;
+    if parser.pos < parser.tokens.len() {
+        return parser.error("unexpected trailing input");
+    }
+    if !v.is_finite() {
+        return Err(FormulaError { position: 0, message: "result is not a finite number".to_string() });
+    }
+    Ok(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_vars(_: &str) -> Option<f64> {
+        None
+    }
+
+    #[test]
+    fn evaluates_precedence_and_functions() {
+        assert_eq!(eval("1 + 2 * 3 ^ 2", &no_vars), Ok(19.0));
+        assert_eq!(eval("-(2 + 3) * max(1, 4, 2)", &no_vars), Ok(-20.0));
+        assert_eq!(eval("2 ^ 3 ^ 2", &no_vars), Ok(512.0));
+        let vars = |name: &str| (name == "waist").then_some(80.0);
+        assert_eq!(eval("waist / 4", &vars), Ok(20.0));
+    }
+
+    #[test]
+    fn nesting_up_to_the_limit_is_accepted() {
+        let depth = MAX_DEPTH - 1;
+        let expr = format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(eval(&expr, &no_vars), Ok(1.0));
+        assert_eq!(eval(&format!("{}1", "-".repeat(depth)), &no_vars), Ok(-1.0));
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_not_a_stack_overflow() {
+        for expr in [
+            format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000)),
+            format!("{}1", "-".repeat(100_000)),
+            format!("1{}", "^1".repeat(100_000)),
+            format!("{}1{}", "sqrt(".repeat(100_000), ")".repeat(100_000)),
+        ] {
+            let err = eval(&expr, &no_vars).unwrap_err();
+            assert!(err.message.contains("nested deeper"), "{}", err.message);
+        }
+    }
+
+    #[test]
+    fn sequential_operands_do_not_count_as_depth() {
+        let expr = vec!["(1)"; 1000].join(" + ");
+        assert_eq!(eval(&expr, &no_vars), Ok(1000.0));
+    }
+}
@@ -1,5 +1,38 @@
+use std::fmt;
 use serde::{Serialize, Deserialize};
 
+/// سماحية المستند (مم): المسافات الأقصر منها تُعتبر صفراً عند فحص الأدوات
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Tolerance(pub f64);
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance(0.01)
+    }
+}
+
+/// حالة هندسية منحلة لا يمكن حساب نتيجة صحيحة لها
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeometryError {
+    /// نقطتان متطابقتان فلا يوجد اتجاه بينهما
+    CoincidentPoints,
+    /// خط أو ضلع طوله صفر
+    ZeroLength,
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeometryError::CoincidentPoints => write!(f, "points coincide, direction is undefined"),
+            GeometryError::ZeroLength => write!(f, "zero-length segment"),
+        }
+    }
+}
+
+impl std::error::Error for GeometryError {}
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Point2D {
     pub x: f64,
@@ -25,6 +58,15 @@ impl Point2D {
         dy.atan2(dx).to_degrees()
     }
 
+    /// مثل `angle_to` لكن يرفض النقاط المتطابقة (ضمن `tolerance`) بدل إرجاع صفر
+    /// سماحية صفر ترفض التطابق التام فقط
+    pub fn direction_to(&self, other: &Point2D, tolerance: f64) -> Result<f64, GeometryError> {
+        if self.distance_to(other) <= tolerance {
+            return Err(GeometryError::CoincidentPoints);
+        }
+        Ok(self.angle_to(other))
+    }
+
     /// حساب نقطة جديدة تبعد مسافة معينة وبزاوية معينة
     pub fn point_at(&self, distance: f64, angle_degrees: f64) -> Self {
         let rad = angle_degrees.to_radians();
@@ -48,4 +90,120 @@ impl Point2D {
             y: origin.y + (dx * sin_a + dy * cos_a),
         }
     }
-}
\ No newline at end of file
+
+    /// أقصر مسافة من النقطة إلى القطعة المستقيمة [a, b]
+    pub fn distance_to_segment(&self, a: &Point2D, b: &Point2D) -> f64 {
+        let dx = b.x - a.x;
+        let dy = b.y - a.y;
+        let len_sq = dx * dx + dy * dy;
+        if len_sq == 0.0 {
+            return self.distance_to(a);
+        }
+        let t = (((self.x - a.x) * dx + (self.y - a.y) * dy) / len_sq).clamp(0.0, 1.0);
+        self.distance_to(&Point2D::new(a.x + t * dx, a.y + t * dy))
+    }
+
+    /// انعكاس النقطة حول خط يمر بالنقطتين a و b
+    pub fn reflect(&self, a: &Point2D, b: &Point2D) -> Self {
+        let dx = b.x - a.x;
+        let dy = b.y - a.y;
+        let len_sq = dx * dx + dy * dy;
+        if len_sq == 0.0 {
+            return *self;
+        }
+        // إسقاط النقطة على الخط ثم مضاعفة المسافة للجهة الأخرى
+        let t = ((self.x - a.x) * dx + (self.y - a.y) * dy) / len_sq;
+        let foot_x = a.x + t * dx;
+        let foot_y = a.y + t * dy;
+        Point2D {
+            x: 2.0 * foot_x - self.x,
+            y: 2.0 * foot_y - self.y,
+        }
+    }
+}
+
+/// حساب نقطة على منحنى بيزيه تكعيبي عند المعامل t (من 0 إلى 1)
+pub fn cubic_bezier_at(p0: &Point2D, p1: &Point2D, p2: &Point2D, p3: &Point2D, t: f64) -> Point2D {
+    let mt = 1.0 - t;
+    let a = mt * mt * mt;
+    let b = 3.0 * mt * mt * t;
+    let c = 3.0 * mt * t * t;
+    let d = t * t * t;
+    Point2D {
+        x: a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+        y: a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+    }
+}
+
+/// تقريب منحنى بيزيه بخط متعدد الأضلاع من `segments` قطعة
+pub fn flatten_cubic(p0: &Point2D, p1: &Point2D, p2: &Point2D, p3: &Point2D, segments: usize) -> Vec<Point2D> {
+    let n = segments.max(1);
+    (0..=n).map(|i| cubic_bezier_at(p0, p1, p2, p3, i as f64 / n as f64)).collect()
+}
+
+/// أقصر مسافة من نقطة إلى منحنى بيزيه تكعيبي
+/// بحث أولي بالعينات ثم تحسين بالبحث الثلاثي حول أقرب عينة
+pub fn distance_to_cubic(p: &Point2D, p0: &Point2D, p1: &Point2D, p2: &Point2D, p3: &Point2D) -> f64 {
+    const SAMPLES: usize = 32;
+    let dist_at = |t: f64| p.distance_to(&cubic_bezier_at(p0, p1, p2, p3, t));
+    let step = 1.0 / SAMPLES as f64;
+    let best = (0..=SAMPLES)
+        .map(|i| i as f64 * step)
+        .min_by(|a, b| dist_at(*a).total_cmp(&dist_at(*b)))
+        .unwrap_or(0.0);
+    let (mut lo, mut hi) = ((best - step).max(0.0), (best + step).min(1.0));
+    for _ in 0..30 {
+        let m1 = lo + (hi - lo) / 3.0;
+        let m2 = hi - (hi - lo) / 3.0;
+        if dist_at(m1) < dist_at(m2) { hi = m2; } else { lo = m1; }
+    }
+    dist_at((lo + hi) / 2.0)
+}
+
+/// نقطة تقاطع الخطين اللانهائيين (a1, a2) و (b1, b2)
+/// ويرجع None إذا كان الخطان متوازيين
+pub fn line_intersection(a1: &Point2D, a2: &Point2D, b1: &Point2D, b2: &Point2D) -> Option<Point2D> {
+    let (dax, day) = (a2.x - a1.x, a2.y - a1.y);
+    let (dbx, dby) = (b2.x - b1.x, b2.y - b1.y);
+    let denom = dax * dby - day * dbx;
+    if denom.abs() < 1e-12 {
+        return None;
+    }
+    let t = ((b1.x - a1.x) * dby - (b1.y - a1.y) * dbx) / denom;
+    Some(Point2D::new(a1.x + t * dax, a1.y + t * day))
+}
+
+/// طول قوس منحنى بيزيه تكعيبي بالتقسيم التكيفي (de Casteljau)
+/// يتوقف التقسيم عندما يقترب طول الوتر من طول مضلع التحكم
+pub fn cubic_length(p0: &Point2D, p1: &Point2D, p2: &Point2D, p3: &Point2D) -> f64 {
+    fn recurse(p0: Point2D, p1: Point2D, p2: Point2D, p3: Point2D, depth: u32) -> f64 {
+        let chord = p0.distance_to(&p3);
+        let net = p0.distance_to(&p1) + p1.distance_to(&p2) + p2.distance_to(&p3);
+        if net - chord < 1e-6 * net.max(1.0) || depth >= 16 {
+            return (2.0 * chord + net) / 3.0;
+        }
+        let mid = |a: Point2D, b: Point2D| Point2D::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0);
+        let (p01, p12, p23) = (mid(p0, p1), mid(p1, p2), mid(p2, p3));
+        let (p012, p123) = (mid(p01, p12), mid(p12, p23));
+        let m = mid(p012, p123);
+        recurse(p0, p01, p012, m, depth + 1) + recurse(m, p123, p23, p3, depth + 1)
+    }
+    recurse(*p0, *p1, *p2, *p3, 0)
+}
+
+/// نقطة تقاطع القطعتين [a, b] و [c, d] إن تقاطعتا فعلياً
+pub fn segment_intersection(a: &Point2D, b: &Point2D, c: &Point2D, d: &Point2D) -> Option<Point2D> {
+    let (rx, ry) = (b.x - a.x, b.y - a.y);
+    let (sx, sy) = (d.x - c.x, d.y - c.y);
+    let denom = rx * sy - ry * sx;
+    if denom.abs() < 1e-12 {
+        return None;
+    }
+    let t = ((c.x - a.x) * sy - (c.y - a.y) * sx) / denom;
+    let u = ((c.x - a.x) * ry - (c.y - a.y) * rx) / denom;
+    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+        Some(Point2D::new(a.x + t * rx, a.y + t * ry))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,78 @@
+use crate::geometry::Point2D;
+use crate::object::SelectedItem;
+use crate::spatial::{SpatialIndex, Bounds, Shape};
+
+/// نصف قطر دائرة النقطة المرسومة على الكانفاس (بوحدات الرسم)
+pub const POINT_RADIUS: f64 = 10.0;
+
+/// سماحية الالتقاط بالبكسل، تُحوّل لوحدات الرسم حسب حجم الكانفاس
+pub const HIT_TOLERANCE_PX: f64 = 6.0;
+
+/// عنصر مرشح تحت مؤشر الماوس مع بعده عنه
+#[derive(Debug, Clone, PartialEq)]
+pub struct HitCandidate {
+    pub item: SelectedItem,
+    pub distance: f64,
+}
+
+/// البحث عن كل العناصر القريبة من نقطة في إحداثيات الرسم
+/// الترتيب: النقاط أولاً ثم الخطوط والمنحنيات، والأقرب أولاً داخل كل فئة
+/// (بدلاً من ترتيب الرسم في الـ SVG الذي كان يحدد العنصر الملتقط سابقاً)
+/// المرشحون يأتون من الفهرس المكاني، ثم تُحسب المسافة الدقيقة لكل منهم فقط
+pub fn hit_test(at: &Point2D, tolerance: f64, index: &SpatialIndex) -> Vec<HitCandidate> {
+    let area = Bounds::around(at, POINT_RADIUS + tolerance);
+    let mut point_hits: Vec<HitCandidate> = Vec::new();
+    let mut curve_hits: Vec<HitCandidate> = Vec::new();
+    for candidate in index.query(&area) {
+        let distance = candidate.shape.distance_to(at);
+        match candidate.shape {
+            Shape::Point(_) if distance <= POINT_RADIUS + tolerance => {
+                point_hits.push(HitCandidate { item: candidate.item.clone(), distance });
+            }
+            Shape::Segment(..) | Shape::Cubic(_) if distance <= tolerance => {
+                curve_hits.push(HitCandidate { item: candidate.item.clone(), distance });
+            }
+            _ => {}
+        }
+    }
+
+    point_hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    curve_hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    point_hits.extend(curve_hits);
+    point_hits
+}
+
+/// أفضل مرشح فقط (للاختيار والـ Hover)
+pub fn hit_first(at: &Point2D, tolerance: f64, index: &SpatialIndex) -> Option<SelectedItem> {
+    hit_test(at, tolerance, index).into_iter().next().map(|h| h.item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::{VPoint, VLine, VBisector};
+
+    #[test]
+    fn points_rank_before_curves_including_degenerate_bisectors() {
+        let points = vec![VPoint::new(1, "A", 0.0, 0.0), VPoint::new(2, "B", 0.0, 0.0), VPoint::new(3, "C", 100.0, 0.0)];
+        let lines = vec![VLine::new(4, "AC", 1, 3)];
+        // المنصف منحل: p1 ينطبق على الرأس
+        let bisectors = vec![VBisector::new(5, "Bis", 1, 2, 3, 50.0)];
+        let index = SpatialIndex::build(&points, &lines, &[], &bisectors, 0.01);
+        let hits: Vec<SelectedItem> = hit_test(&Point2D::new(0.0, 0.0), 1.0, &index).into_iter().map(|h| h.item).collect();
+        assert_eq!(hits.len(), 4);
+        assert!(matches!(hits[0], SelectedItem::Point(1 | 2)));
+        assert!(matches!(hits[1], SelectedItem::Point(1 | 2)));
+        assert!(hits[2..].contains(&SelectedItem::Line(4)));
+        assert!(hits[2..].contains(&SelectedItem::Bisector(5)));
+    }
+
+    #[test]
+    fn curves_only_hit_within_tolerance() {
+        let points = vec![VPoint::new(1, "A", 0.0, 0.0), VPoint::new(2, "B", 100.0, 0.0)];
+        let lines = vec![VLine::new(3, "AB", 1, 2)];
+        let index = SpatialIndex::build(&points, &lines, &[], &[], 0.01);
+        assert_eq!(hit_first(&Point2D::new(50.0, 4.0), 6.0, &index), Some(SelectedItem::Line(3)));
+        assert_eq!(hit_first(&Point2D::new(50.0, 8.0), 6.0, &index), None);
+    }
+}
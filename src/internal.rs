@@ -0,0 +1,94 @@
+use crate::geometry::Point2D;
+use crate::object::VPoint;
+use serde::{Serialize, Deserialize};
+
+/// أبعاد علامة الموضع الافتراضية (بوحدات الرسم)
+pub const DEFAULT_PLACEMENT_SIZE: f64 = 20.0;
+
+/// مسار داخلي في القطعة (بنسة، موضع جيب...) مبني على مسار موجود
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InternalPath {
+    pub contour_id: u32,
+    /// يُقص على القماش (وإلا يُرسم فقط للإرشاد)
+    pub cut_on_fabric: bool,
+}
+
+/// شكل علامة الموضع
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PlacementKind {
+    #[default]
+    Cross,
+    Rectangle,
+    Circle,
+    Button,
+}
+
+impl PlacementKind {
+    pub const ALL: [PlacementKind; 4] = [
+        PlacementKind::Cross,
+        PlacementKind::Rectangle,
+        PlacementKind::Circle,
+        PlacementKind::Button,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PlacementKind::Cross => "Cross",
+            PlacementKind::Rectangle => "Rectangle",
+            PlacementKind::Circle => "Circle",
+            PlacementKind::Button => "Button",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.label() == label)
+    }
+}
+
+/// علامة موضع (زرار، ثقب، جيب) مرتبطة بنقطة من الرسم
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlacementLabel {
+    pub kind: PlacementKind,
+    pub point_id: u32,
+    pub width: f64,
+    pub height: f64,
+    pub angle: f64,
+}
+
+impl PlacementLabel {
+    pub fn new(kind: PlacementKind, point_id: u32) -> Self {
+        Self {
+            kind,
+            point_id,
+            width: DEFAULT_PLACEMENT_SIZE,
+            height: DEFAULT_PLACEMENT_SIZE,
+            angle: 0.0,
+        }
+    }
+
+    /// خطوط العلامة في إحداثيات الرسم (الأشكال المغلقة تنتهي بنقطة البداية)
+    pub fn shape(&self, points: &[VPoint]) -> Vec<Vec<Point2D>> {
+        let Some(center) = points.iter().find(|p| p.metadata.id == self.point_id).map(|p| p.coords) else {
+            return Vec::new();
+        };
+        let (hw, hh) = (self.width / 2.0, self.height / 2.0);
+        let at = |dx: f64, dy: f64| Point2D::new(center.x + dx, center.y + dy).rotate(&center, self.angle);
+        let ellipse = |rx: f64, ry: f64| -> Vec<Point2D> {
+            (0..=24).map(|i| {
+                let t = std::f64::consts::TAU * i as f64 / 24.0;
+                at(rx * t.cos(), ry * t.sin())
+            }).collect()
+        };
+        match self.kind {
+            PlacementKind::Cross => vec![vec![at(-hw, 0.0), at(hw, 0.0)], vec![at(0.0, -hh), at(0.0, hh)]],
+            PlacementKind::Rectangle => vec![vec![at(-hw, -hh), at(hw, -hh), at(hw, hh), at(-hw, hh), at(-hw, -hh)]],
+            PlacementKind::Circle => vec![ellipse(hw, hh)],
+            // دائرة الزرار مع علامة المنتصف
+            PlacementKind::Button => vec![
+                ellipse(hw, hh),
+                vec![at(-hw / 3.0, 0.0), at(hw / 3.0, 0.0)],
+                vec![at(0.0, -hh / 3.0), at(0.0, hh / 3.0)],
+            ],
+        }
+    }
+}
@@ -0,0 +1,523 @@
+use crate::geometry::{Point2D, segment_intersection};
+use crate::contour::signed_area;
+use crate::piece::PieceGeometry;
+use crate::spatial::Bounds;
+use serde::{Serialize, Deserialize};
+
+/// عرض القماش الافتراضي بالمليمتر
+pub const DEFAULT_FABRIC_WIDTH: f64 = 1500.0;
+
+/// الدورانات المسموحة للقطعة بعد محاذاة خط النسيج مع طول القماش
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RotationStep {
+    /// بدون دوران
+    Fixed,
+    /// 0 أو 180 درجة (خط النسيج يبقى موازياً لطول القماش)
+    #[default]
+    HalfTurn,
+    /// كل 90 درجة (يسمح بقص القطعة على العرض)
+    QuarterTurn,
+}
+
+impl RotationStep {
+    pub const ALL: [RotationStep; 3] = [RotationStep::Fixed, RotationStep::HalfTurn, RotationStep::QuarterTurn];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RotationStep::Fixed => "No rotation",
+            RotationStep::HalfTurn => "0° / 180°",
+            RotationStep::QuarterTurn => "90° steps",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.label() == label)
+    }
+
+    pub fn angles(&self) -> &'static [f64] {
+        match self {
+            RotationStep::Fixed => &[0.0],
+            RotationStep::HalfTurn => &[0.0, 180.0],
+            RotationStep::QuarterTurn => &[0.0, 90.0, 180.0, 270.0],
+        }
+    }
+
+    /// خطوة الدوران اليدوي، أو None إذا كان الدوران ممنوعاً
+    pub fn step(&self) -> Option<f64> {
+        match self {
+            RotationStep::Fixed => None,
+            RotationStep::HalfTurn => Some(180.0),
+            RotationStep::QuarterTurn => Some(90.0),
+        }
+    }
+}
+
+/// إعدادات التخطيط: طول القماش على المحور X وعرضه على المحور Y
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LayoutOptions {
+    pub fabric_width: f64,
+    /// أقصى طول متاح، أو None لطول غير محدود
+    pub max_length: Option<f64>,
+    /// المسافة الدنيا بين القطع
+    pub gap: f64,
+    pub rotation: RotationStep,
+    /// بذرة ترتيب المحاولات (نفس البذرة = نفس النتيجة)
+    pub seed: u64,
+    /// عدد ترتيبات القطع المجرَّبة، يُحتفظ بأقصرها
+    pub attempts: usize,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            fabric_width: DEFAULT_FABRIC_WIDTH,
+            max_length: None,
+            gap: 2.0,
+            rotation: RotationStep::default(),
+            seed: 1,
+            attempts: 8,
+        }
+    }
+}
+
+/// نسخة من قطعة موضوعة على القماش
+/// التحويل: محاذاة خط النسيج مع X، ثم القلب حول X، ثم الدوران، ثم الإزاحة
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlacedPiece {
+    pub piece_id: u32,
+    /// رقم النسخة من 0 حتى عدد القص - 1
+    pub copy: u32,
+    pub rotation: f64,
+    pub mirrored: bool,
+    pub offset: Point2D,
+}
+
+impl PlacedPiece {
+    pub fn map(&self, p: &Point2D, grain_angle: f64) -> Point2D {
+        let origin = Point2D::new(0.0, 0.0);
+        let mut q = p.rotate(&origin, -grain_angle);
+        if self.mirrored {
+            q.y = -q.y;
+        }
+        let q = q.rotate(&origin, self.rotation);
+        Point2D::new(q.x + self.offset.x, q.y + self.offset.y)
+    }
+
+    /// حدود القص بعد التحويل
+    pub fn outline(&self, geo: &PieceGeometry) -> Vec<Point2D> {
+        let grain = geo.grain_angle();
+        geo.cut_outline().iter().map(|p| self.map(p, grain)).collect()
+    }
+
+    /// كل خطوط القطعة وملصقاتها بعد التحويل (للتصدير)
+    pub fn apply(&self, geo: &PieceGeometry) -> PieceGeometry {
+        let grain = geo.grain_angle();
+        let map = |poly: &Vec<Point2D>| poly.iter().map(|p| self.map(p, grain)).collect::<Vec<_>>();
+        let map_all = |lines: &Vec<Vec<Point2D>>| lines.iter().map(map).collect::<Vec<_>>();
+        let mut placed = geo.clone();
+        placed.seam = map(&geo.seam);
+        placed.allowance = geo.allowance.as_ref().map(map);
+        placed.notches = map_all(&geo.notches);
+        placed.grainline = map_all(&geo.grainline);
+        placed.cut_paths = map_all(&geo.cut_paths);
+        placed.draw_paths = map_all(&geo.draw_paths);
+        placed.placements = map_all(&geo.placements);
+        for label in &mut placed.labels {
+            label.at = self.map(&label.at, grain);
+        }
+        placed
+    }
+
+    fn center(&self, geo: &PieceGeometry) -> Point2D {
+        Bounds::from_points(&self.outline(geo)).map_or(Point2D::new(0.0, 0.0), |b| b.center())
+    }
+
+    /// تعديل التحويل مع إبقاء مركز القطعة في مكانه
+    fn keep_center(&mut self, geo: &PieceGeometry, change: impl Fn(&mut Self)) {
+        let before = self.center(geo);
+        change(self);
+        let after = self.center(geo);
+        self.offset = Point2D::new(self.offset.x + before.x - after.x, self.offset.y + before.y - after.y);
+    }
+
+    /// دوران حول مركز القطعة
+    pub fn rotate_by(&mut self, geo: &PieceGeometry, degrees: f64) {
+        self.keep_center(geo, |p| p.rotation = (p.rotation + degrees).rem_euclid(360.0));
+    }
+
+    /// قلب القطعة حول محور خط النسيج (يمين/يسار)
+    pub fn flip(&mut self, geo: &PieceGeometry) {
+        self.keep_center(geo, |p| p.mirrored = !p.mirrored);
+    }
+}
+
+/// نتيجة التخطيط
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Layout {
+    pub fabric_width: f64,
+    pub placements: Vec<PlacedPiece>,
+    /// النسخ التي لم تتسع (قطعة، نسخة)
+    pub unplaced: Vec<(u32, u32)>,
+}
+
+impl Layout {
+    fn outlines<'a>(&'a self, pieces: &'a [PieceGeometry]) -> impl Iterator<Item = Vec<Point2D>> + 'a {
+        self.placements.iter().filter_map(|pl| {
+            pieces.iter().find(|g| g.id == pl.piece_id).map(|g| pl.outline(g))
+        })
+    }
+
+    /// القطع الموضوعة محوّلة إلى مواضعها على القماش
+    pub fn placed_pieces(&self, pieces: &[PieceGeometry]) -> Vec<PieceGeometry> {
+        self.placements.iter()
+            .filter_map(|pl| pieces.iter().find(|g| g.id == pl.piece_id).map(|g| pl.apply(g)))
+            .collect()
+    }
+
+    /// الطول المستخدم من القماش
+    pub fn length(&self, pieces: &[PieceGeometry]) -> f64 {
+        self.outlines(pieces)
+            .flat_map(|o| o.into_iter().map(|p| p.x))
+            .fold(0.0, f64::max)
+    }
+
+    /// أول نسخة تحتوي النقطة (من الأعلى رسماً، أي آخر القائمة)
+    pub fn hit(&self, pieces: &[PieceGeometry], at: &Point2D) -> Option<usize> {
+        self.placements.iter().enumerate().rev()
+            .find(|(_, pl)| {
+                pieces.iter().find(|g| g.id == pl.piece_id)
+                    .is_some_and(|g| point_in_polygon(at, &pl.outline(g)))
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// النسخ المتداخلة مع غيرها أو الخارجة عن عرض القماش
+    pub fn conflicts(&self, pieces: &[PieceGeometry]) -> Vec<usize> {
+        let outlines: Vec<Option<Vec<Point2D>>> = self.placements.iter()
+            .map(|pl| pieces.iter().find(|g| g.id == pl.piece_id).map(|g| pl.outline(g)))
+            .collect();
+        let mut bad = Vec::new();
+        for (i, a) in outlines.iter().enumerate() {
+            let Some(a) = a else { continue };
+            // قطعة بلا خط قص لا تتعارض مع شيء
+            let Some(b) = Bounds::from_points(a) else { continue };
+            let outside = b.min.x < -1e-6 || b.min.y < -1e-6 || b.max.y > self.fabric_width + 1e-6;
+            let overlaps = outlines.iter().enumerate()
+                .any(|(j, o)| j != i && o.as_ref().is_some_and(|o| polygons_overlap(a, o, 0.0)));
+            if outside || overlaps {
+                bad.push(i);
+            }
+        }
+        bad
+    }
+
+    /// نسبة مساحة القطع إلى مساحة القماش المستخدم (0..1)
+    pub fn efficiency(&self, pieces: &[PieceGeometry]) -> f64 {
+        let used = self.length(pieces) * self.fabric_width;
+        if used <= 0.0 {
+            return 0.0;
+        }
+        self.outlines(pieces).map(|o| signed_area(&o).abs()).sum::<f64>() / used
+    }
+}
+
+/// مولد أرقام شبه عشوائية صغير (xorshift64) لضمان تكرار النتائج بنفس البذرة
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// شكل نسخة بدوران معين عند الأصل، مع حدوده
+#[derive(Clone)]
+struct Variant {
+    rotation: f64,
+    poly: Vec<Point2D>,
+    bounds: Bounds,
+}
+
+struct Item {
+    piece_id: u32,
+    copy: u32,
+    mirrored: bool,
+    area: f64,
+    variants: Vec<Variant>,
+}
+
+fn point_in_polygon(p: &Point2D, poly: &[Point2D]) -> bool {
+    let mut inside = false;
+    let n = poly.len();
+    for i in 0..n {
+        let (a, b) = (&poly[i], &poly[(i + 1) % n]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// هل يتداخل المضلعان أو تقل المسافة بينهما عن `gap`
+pub fn polygons_overlap(a: &[Point2D], b: &[Point2D], gap: f64) -> bool {
+    if a.len() < 3 || b.len() < 3 {
+        return false;
+    }
+    let (Some(ba), Some(bb)) = (Bounds::from_points(a), Bounds::from_points(b)) else {
+        return false;
+    };
+    let grown = Bounds::new(
+        Point2D::new(ba.min.x - gap, ba.min.y - gap),
+        Point2D::new(ba.max.x + gap, ba.max.y + gap),
+    );
+    if !grown.intersects(&bb) {
+        return false;
+    }
+    if point_in_polygon(&a[0], b) || point_in_polygon(&b[0], a) {
+        return true;
+    }
+    let edges = |poly: &[Point2D]| (0..poly.len()).map(|i| (poly[i], poly[(i + 1) % poly.len()])).collect::<Vec<_>>();
+    let (ea, eb) = (edges(a), edges(b));
+    ea.iter().any(|(a1, a2)| eb.iter().any(|(b1, b2)| {
+        segment_intersection(a1, a2, b1, b2).is_some()
+            || (gap > 0.0 && (a1.distance_to_segment(b1, b2) < gap || b1.distance_to_segment(a1, a2) < gap))
+    }))
+}
+
+fn build_items(pieces: &[PieceGeometry], opts: &LayoutOptions) -> Vec<Item> {
+    let mut items = Vec::new();
+    for geo in pieces {
+        if geo.cut_outline().len() < 3 {
+            continue;
+        }
+        for copy in 0..geo.quantity {
+            let mirrored = geo.mirrored_pairs && copy % 2 == 1;
+            let variants = opts.rotation.angles().iter().filter_map(|&rotation| {
+                let pl = PlacedPiece { piece_id: geo.id, copy, rotation, mirrored, offset: Point2D::new(0.0, 0.0) };
+                let poly = pl.outline(geo);
+                let bounds = Bounds::from_points(&poly)?;
+                Some(Variant { rotation, poly, bounds })
+            }).collect();
+            items.push(Item { piece_id: geo.id, copy, mirrored, area: signed_area(geo.cut_outline()).abs(), variants });
+        }
+    }
+    items
+}
+
+/// نتيجة محاولة: الموضوعات وغير الموضوعات والطول المستخدم
+type Attempt = (Vec<PlacedPiece>, Vec<(u32, u32)>, f64);
+
+/// ترتيب القطع بالترتيب المعطى بطريقة "أقصى اليسار ثم الأعلى" مع محاولة الانزلاق لليسار
+fn place_all(items: &[Item], order: &[usize], opts: &LayoutOptions) -> Attempt {
+    let mut placed_polys: Vec<Vec<Point2D>> = Vec::new();
+    let mut placed_bounds: Vec<Bounds> = Vec::new();
+    let mut placements = Vec::new();
+    let mut unplaced = Vec::new();
+    let mut length: f64 = 0.0;
+
+    for &i in order {
+        let item = &items[i];
+        // أفضل موضع: أقل امتداد على طول القماش ثم الأقرب للحافة العلوية
+        let mut best: Option<(f64, f64, &Variant, Point2D)> = None;
+        for v in &item.variants {
+            let (w, h) = (v.bounds.max.x - v.bounds.min.x, v.bounds.max.y - v.bounds.min.y);
+            if h > opts.fabric_width {
+                continue;
+            }
+            let shifted = |x: f64, y: f64| -> Vec<Point2D> {
+                v.poly.iter().map(|p| Point2D::new(p.x - v.bounds.min.x + x, p.y - v.bounds.min.y + y)).collect()
+            };
+            let fits = |x: f64, y: f64| {
+                let poly = shifted(x, y);
+                !placed_polys.iter().any(|o| polygons_overlap(&poly, o, opts.gap))
+            };
+
+            let mut ys: Vec<f64> = std::iter::once(0.0)
+                .chain(placed_bounds.iter().map(|b| b.max.y + opts.gap))
+                .filter(|y| y + h <= opts.fabric_width)
+                .collect();
+            ys.sort_by(f64::total_cmp);
+            ys.dedup();
+            let mut xs: Vec<f64> = std::iter::once(0.0)
+                .chain(placed_bounds.iter().map(|b| b.max.x + opts.gap))
+                .collect();
+            xs.sort_by(f64::total_cmp);
+            xs.dedup();
+
+            for &y in &ys {
+                let Some(k) = xs.iter().position(|&x| fits(x, y)) else { continue };
+                // انزلاق لليسار بين آخر موضع فاشل وأول موضع ناجح
+                let (mut lo, mut hi) = (if k > 0 { xs[k - 1] } else { xs[k] }, xs[k]);
+                for _ in 0..8 {
+                    let mid = (lo + hi) / 2.0;
+                    if fits(mid, y) { hi = mid } else { lo = mid }
+                }
+                let x = hi;
+                if opts.max_length.is_some_and(|max| x + w > max) {
+                    continue;
+                }
+                let score = (x + w, y);
+                if best.as_ref().is_none_or(|b| score < (b.0, b.1)) {
+                    best = Some((score.0, score.1, v, Point2D::new(x, y)));
+                }
+            }
+        }
+
+        match best {
+            Some((right, _, v, at)) => {
+                let offset = Point2D::new(at.x - v.bounds.min.x, at.y - v.bounds.min.y);
+                let poly: Vec<Point2D> = v.poly.iter().map(|p| Point2D::new(p.x + offset.x, p.y + offset.y)).collect();
+                placed_bounds.push(Bounds::new(
+                    Point2D::new(v.bounds.min.x + offset.x, v.bounds.min.y + offset.y),
+                    Point2D::new(v.bounds.max.x + offset.x, v.bounds.max.y + offset.y),
+                ));
+                placed_polys.push(poly);
+                length = length.max(right);
+                placements.push(PlacedPiece {
+                    piece_id: item.piece_id,
+                    copy: item.copy,
+                    rotation: v.rotation,
+                    mirrored: item.mirrored,
+                    offset,
+                });
+            }
+            None => unplaced.push((item.piece_id, item.copy)),
+        }
+    }
+    (placements, unplaced, length)
+}
+
+/// ترتيب آلي للقطع (بعدد نسخها) على القماش لتقليل الطول المستخدم
+/// المحاولة الأولى بترتيب المساحة تنازلياً، والباقي تبديلات عشوائية منه بالبذرة المعطاة
+pub fn nest(pieces: &[PieceGeometry], opts: &LayoutOptions) -> Layout {
+    let items = build_items(pieces, opts);
+    let mut base: Vec<usize> = (0..items.len()).collect();
+    base.sort_by(|&a, &b| items[b].area.total_cmp(&items[a].area));
+
+    let mut rng = Rng::new(opts.seed);
+    let mut best: Option<Attempt> = None;
+    for attempt in 0..opts.attempts.max(1) {
+        let mut order = base.clone();
+        if attempt > 0 && order.len() > 1 {
+            for _ in 0..order.len().div_ceil(3) {
+                let (a, b) = (rng.below(order.len()), rng.below(order.len()));
+                order.swap(a, b);
+            }
+        }
+        let result = place_all(&items, &order, opts);
+        let better = best.as_ref().is_none_or(|b| (result.1.len(), result.2) < (b.1.len(), b.2));
+        if better {
+            best = Some(result);
+        }
+    }
+
+    let (placements, unplaced, _) = best.unwrap_or_default();
+    Layout { fabric_width: opts.fabric_width, placements, unplaced }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn piece(id: u32, outline: Vec<Point2D>, quantity: u32, grain: f64) -> PieceGeometry {
+        let (sin, cos) = grain.to_radians().sin_cos();
+        PieceGeometry {
+            id,
+            name: format!("P{id}"),
+            seam: outline,
+            allowance: None,
+            notches: Vec::new(),
+            grainline: vec![vec![Point2D::new(0.0, 0.0), Point2D::new(50.0 * cos, 50.0 * sin)]],
+            labels: Vec::new(),
+            cut_paths: Vec::new(),
+            draw_paths: Vec::new(),
+            placements: Vec::new(),
+            quantity,
+            mirrored_pairs: quantity > 1,
+        }
+    }
+
+    fn rect(w: f64, h: f64) -> Vec<Point2D> {
+        vec![Point2D::new(0.0, 0.0), Point2D::new(w, 0.0), Point2D::new(w, h), Point2D::new(0.0, h)]
+    }
+
+    /// خليط من المستطيلات ومثلث وشكل L بخطوط نسيج مائلة
+    fn pieces() -> Vec<PieceGeometry> {
+        vec![
+            piece(1, rect(400.0, 250.0), 2, 0.0),
+            piece(2, rect(120.0, 600.0), 1, 90.0),
+            piece(3, vec![Point2D::new(0.0, 0.0), Point2D::new(300.0, 0.0), Point2D::new(0.0, 200.0)], 3, 30.0),
+            piece(4, vec![
+                Point2D::new(0.0, 0.0), Point2D::new(250.0, 0.0), Point2D::new(250.0, 80.0),
+                Point2D::new(80.0, 80.0), Point2D::new(80.0, 300.0), Point2D::new(0.0, 300.0),
+            ], 2, 0.0),
+            piece(5, rect(60.0, 60.0), 4, 45.0),
+        ]
+    }
+
+    fn options(seed: u64) -> LayoutOptions {
+        LayoutOptions { fabric_width: 900.0, rotation: RotationStep::HalfTurn, seed, ..LayoutOptions::default() }
+    }
+
+    #[test]
+    fn same_seed_same_layout() {
+        let pieces = pieces();
+        assert_eq!(nest(&pieces, &options(7)), nest(&pieces, &options(7)));
+        assert_eq!(nest(&pieces, &options(42)), nest(&pieces, &options(42)));
+    }
+
+    #[test]
+    fn every_copy_is_placed_without_overlap() {
+        let pieces = pieces();
+        for seed in [1, 7, 42] {
+            let opts = options(seed);
+            let layout = nest(&pieces, &opts);
+            assert!(layout.unplaced.is_empty(), "seed {seed}: {:?}", layout.unplaced);
+            assert_eq!(layout.placements.len(), 12);
+            assert!(layout.conflicts(&pieces).is_empty(), "seed {seed}");
+
+            let outlines: Vec<Vec<Point2D>> = layout.placements.iter()
+                .map(|pl| pl.outline(pieces.iter().find(|g| g.id == pl.piece_id).unwrap()))
+                .collect();
+            for (i, a) in outlines.iter().enumerate() {
+                for b in &outlines[i + 1..] {
+                    assert!(!polygons_overlap(a, b, 0.0), "seed {seed}: copies overlap");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_piece_within_fabric_width() {
+        let pieces = pieces();
+        let opts = options(3);
+        let layout = nest(&pieces, &opts);
+        for pl in &layout.placements {
+            let geo = pieces.iter().find(|g| g.id == pl.piece_id).unwrap();
+            let b = Bounds::from_points(&pl.outline(geo)).unwrap();
+            assert!(b.min.y >= -1e-6 && b.max.y <= opts.fabric_width + 1e-6, "piece {} at {:?}", pl.piece_id, b);
+            assert!(b.min.x >= -1e-6);
+        }
+    }
+
+    #[test]
+    fn too_wide_piece_is_unplaced() {
+        // خط النسيج على X فالارتفاع 1000 يقع على عرض القماش
+        let pieces = vec![piece(1, rect(100.0, 1000.0), 1, 0.0), piece(2, rect(100.0, 100.0), 1, 0.0)];
+        let opts = LayoutOptions { fabric_width: 500.0, rotation: RotationStep::Fixed, ..LayoutOptions::default() };
+        let layout = nest(&pieces, &opts);
+        assert_eq!(layout.unplaced, [(1, 0)]);
+        assert_eq!(layout.placements.len(), 1);
+    }
+}
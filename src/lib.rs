@@ -0,0 +1,24 @@
+//! نواة Valentina-Oxidized: الهندسة والكائنات والقطع والتصدير بدون واجهة
+//! تستخدمها الواجهة الرسومية وأداة سطر الأوامر `valentina-cli`
+pub mod error;
+pub mod types;
+pub mod geometry;
+pub mod object;
+pub mod operation;
+pub mod spatial;
+pub mod hit_test;
+pub mod contour;
+pub mod piece;
+pub mod notch;
+pub mod annotation;
+pub mod internal;
+pub mod seam_match;
+pub mod export;
+pub mod palette;
+pub mod layout;
+pub mod formula;
+pub mod measurements;
+pub mod project;
+pub mod val;
+pub mod script;
+pub mod recovery;
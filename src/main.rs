@@ -1,19 +1,44 @@
 #![allow(non_snake_case)]
 use dioxus::prelude::*;
-mod types;
-mod geometry;
-mod object;
 mod canvas_coords;
+mod selection;
+use valentina::{geometry, object, operation, spatial, hit_test, contour, piece, notch, annotation, internal, seam_match, export, layout, palette};
 
 use object::{VPoint, VLine, VCubicBezier, VBisector, VContour, SelectedItem, EntityRef};
 use canvas_coords::{CoordMapper, SvgViewBox, AspectRatioMode};
+use operation::{VOperation, OperationKind, AxisType};
+use selection::{Selection, RubberBand};
+use geometry::{Point2D, Tolerance};
+use spatial::{SpatialIndex, IdIndex, Bounds, CurveResolution};
+use contour::{ContourReport, ContourMetrics, Orientation};
+use piece::{VPiece, CornerType, PieceGeometry};
+use notch::{NotchType, NotchAngle};
+use annotation::{PatternInfo, Grainline, TextLabel};
+use internal::{InternalPath, PlacementLabel, PlacementKind};
+use seam_match::{SeamPair, SubPath};
+use export::{ExportScene, ExportOptions, ExportContent};
+use export::dxf::{DxfOptions, SplineMode};
+use export::pdf::{PdfOptions, PaperSize};
+use export::hpgl::HpglOptions;
+use export::png::PngOptions;
+use layout::{Layout, LayoutOptions, RotationStep};
+use valentina::measurements::MeasurementTable;
+use valentina::project::{self, ProjectData};
+use valentina::error::Error;
+use valentina::recovery::{self, RecoveryStore};
+use valentina::script::{self, ScriptReport, ScriptError};
+use std::collections::HashSet;
+use std::rc::Rc;
 use serde_json::Value;
-use serde::{Serialize, Deserialize};
 use std::fs;
+use std::path::Path;
+use std::hash::{DefaultHasher, Hash, Hasher};
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum CanvasMode {
     PlacePoint,
+    // وضع الاختيار: نقر/Shift+نقر ومستطيل السحب
+    Select,
     AwaitingLineStart,
     AwaitingLineEnd { first_point_id: u32 },
     // مراحل منحنى بيزيه
@@ -27,16 +52,68 @@ pub enum CanvasMode {
     BisectorEnd { p1: u32, vertex: u32 },
     // مرحلة إنشاء الكونتور (المسار)
     ContourCreation { active_contour_id: u32 },
+    // العمليات الجماعية: اختيار العناصر ثم تحديد معاملات العملية
+    OperationSelect { items: Vec<SelectedItem> },
+    MirrorLineStart { items: Vec<SelectedItem> },
+    MirrorLineEnd { items: Vec<SelectedItem>, p1: u32 },
+    MirrorAxisOrigin { items: Vec<SelectedItem>, axis: AxisType },
+    RotationOrigin { items: Vec<SelectedItem>, angle: f64 },
+    // خط نسيج القطعة بين نقطتين يختارهما المستخدم على الكانفاس
+    GrainlineStart { piece_id: u32 },
+    GrainlineEnd { piece_id: u32, start_id: u32 },
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-pub struct ProjectData {
-    pub points: Vec<VPoint>,
-    pub lines: Vec<VLine>,
-    pub splines: Vec<VCubicBezier>,
-    pub bisectors: Vec<VBisector>,
-    pub contours: Vec<VContour>,
-    pub next_id: u32,
+/// مستوى الإشعار في منطقة الإشعارات
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NoticeLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl NoticeLevel {
+    fn class(&self) -> &'static str {
+        match self {
+            NoticeLevel::Info => "notice notice-info",
+            NoticeLevel::Warning => "notice notice-warning",
+            NoticeLevel::Error => "notice notice-error",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Notice {
+    pub id: u64,
+    pub level: NoticeLevel,
+    pub text: String,
+}
+
+/// أقصى عدد للإشعارات المعروضة (الأقدم يُحذف)
+const MAX_NOTICES: usize = 5;
+
+/// نصف قطر علامة الكائن المنحل على الكانفاس
+const ERROR_MARKER_RADIUS: f64 = 9.0;
+
+/// كتابة ملف مصدَّر مع خطأ يحمل المسار
+fn write_output(path: &Path, bytes: impl AsRef<[u8]>) -> Result<String, Error> {
+    fs::write(path, bytes).map_err(|e| Error::io(path, e))?;
+    Ok(format!("Saved {}", path.display()))
+}
+
+/// بصمة محتوى المشروع لمعرفة هل تغير منذ آخر حفظ
+fn content_hash(project: &ProjectData) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(project).unwrap_or_default().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// إضافة عنصر لقائمة العناصر المختارة لعملية جماعية أو إزالته إن كان موجوداً
+fn toggle_item(items: &mut Vec<SelectedItem>, item: SelectedItem) {
+    if let Some(pos) = items.iter().position(|i| *i == item) {
+        items.remove(pos);
+    } else {
+        items.push(item);
+    }
 }
 
 fn main() {
@@ -45,16 +122,64 @@ fn main() {
 
 #[component]
 fn App() -> Element {
-    let mut points = use_signal(|| Vec::<VPoint>::new());
-    let mut lines = use_signal(|| Vec::<VLine>::new());
-    let mut splines = use_signal(|| Vec::<VCubicBezier>::new());
-    let mut bisectors = use_signal(|| Vec::<VBisector>::new());
-    let mut contours = use_signal(|| Vec::<VContour>::new());
+    let mut points = use_signal(Vec::<VPoint>::new);
+    let mut lines = use_signal(Vec::<VLine>::new);
+    let mut splines = use_signal(Vec::<VCubicBezier>::new);
+    let mut bisectors = use_signal(Vec::<VBisector>::new);
+    let mut contours = use_signal(Vec::<VContour>::new);
+    let mut operations = use_signal(Vec::<VOperation>::new);
+    let mut pieces = use_signal(Vec::<VPiece>::new);
+    let mut pattern_info = use_signal(PatternInfo::default);
+    let mut seam_pairs = use_signal(Vec::<SeamPair>::new);
+    // جدول القياسات المحمل مع المشروع (يُحفظ كما هو)
+    let mut measurements = use_signal(MeasurementTable::default);
+    let mut doc_tolerance = use_signal(Tolerance::default);
+    // وحدة السكربت: النص وآخر نتيجة تشغيل
+    let mut script_source = use_signal(String::new);
+    let mut script_result = use_signal(|| None::<Result<ScriptReport, ScriptError>>);
+    // منطقة الإشعارات: أخطاء الحفظ والتحميل والتصدير ونتائجها
+    let mut notices = use_signal(Vec::<Notice>::new);
+    let mut notice_seq = use_signal(|| 0u64);
+    // الحفظ التلقائي: نسخة الاستعادة الموجودة عند البدء، وبصمتا آخر حفظ وآخر نسخة تلقائية
+    let recovery_store = use_signal(RecoveryStore::default_location);
+    // نسخة الجلسة السابقة تُنقل جانباً فلا يستبدلها الحفظ التلقائي قبل أن يقرر المستخدم
+    let mut recovery_offer = use_signal(|| recovery_store.read().as_ref().and_then(RecoveryStore::hold));
+    let mut saved_hash = use_signal(|| None::<u64>);
+    let mut autosaved_hash = use_signal(|| None::<u64>);
+    let mut export_content = use_signal(ExportContent::default);
+    let mut dxf_splines = use_signal(SplineMode::default);
+    let mut pdf_options = use_signal(PdfOptions::default);
+    let mut png_options = use_signal(PngOptions::default);
+    let mut layout_options = use_signal(LayoutOptions::default);
+    let mut layout = use_signal(Layout::default);
+    // عرض التخطيط بدلاً من لوحة الرسم
+    let mut layout_view = use_signal(|| false);
+    // محرر التخطيط: النسخة المختارة وآخر موضع للماوس أثناء السحب
+    let mut layout_selected = use_signal(|| None::<usize>);
+    let mut layout_drag = use_signal(|| None::<Point2D>);
+    let mut layout_elem_size = use_signal(|| (1000.0, 1000.0));
+    let mut hpgl_options = use_signal(HpglOptions::default);
     let mut mode = use_signal(|| CanvasMode::PlacePoint);
-    let mut selected_item = use_signal(|| SelectedItem::None);
+    let mut selection = use_signal(Selection::default);
+    let mut band = use_signal(|| None::<RubberBand>);
+    let mut hovered = use_signal(|| None::<SelectedItem>);
+    // مفتاح علامة الخطأ تحت المؤشر (رسالتها تُعرض كتلميح على الكانفاس)
+    let mut hovered_error = use_signal(|| None::<String>);
     let mut dragging_point_id = use_signal(|| None::<u32>);
     let mut next_id = use_signal(|| 1u32);
     let mut svg_elem_size = use_signal(|| (1000.0, 1000.0));
+    // الفهرس المكاني يُعاد بناؤه فقط عند تغير البيانات
+    let spatial_index = use_memo(move || {
+        SpatialIndex::build(&points.read(), &lines.read(), &splines.read(), &bisectors.read(), doc_tolerance.read().0)
+    });
+    // فهارس المعرفات للمسارات والقطع (للرسم وللتعديل من اللوحات)
+    let contour_index = use_memo(move || IdIndex::build(&contours.read(), |c| c.metadata.id));
+    let piece_index = use_memo(move || IdIndex::build(&pieces.read(), |p| p.metadata.id));
+    // معاملات أدوات الدوران والإزاحة
+    let mut rotation_angle = use_signal(|| 90.0f64);
+    let mut move_length = use_signal(|| 100.0f64);
+    let mut move_angle = use_signal(|| 0.0f64);
+    let mut op_suffix = use_signal(String::new);
 
     // استخدام eval للحصول على حجم الـ SVG الفعلي لضبط الإحداثيات
     use_effect(move || {
@@ -70,7 +195,7 @@ fn App() -> Element {
             if let Ok(val) = eval_instance.recv().await {
                 let val: Value = val;
                 if let Some(arr) = val.as_array() {
-                    let w = arr.get(0).and_then(|v: &Value| v.as_f64()).unwrap_or(1000.0);
+                    let w = arr.first().and_then(|v: &Value| v.as_f64()).unwrap_or(1000.0);
                     let h = arr.get(1).and_then(|v: &Value| v.as_f64()).unwrap_or(1000.0);
                     svg_elem_size.set((w, h));
                 }
@@ -78,17 +203,528 @@ fn App() -> Element {
         });
     });
 
+    // قياس لوحة التخطيط عند فتحها لتحويل إحداثيات الماوس
+    use_effect(move || {
+        if !layout_view() {
+            return;
+        }
+        let mut eval_instance = document::eval(r#"
+            const el = document.getElementById('layout-canvas');
+            if (el) {
+                const rect = el.getBoundingClientRect();
+                dioxus.send([rect.width, rect.height]);
+            }
+        "#);
+        spawn(async move {
+            if let Ok(val) = eval_instance.recv().await {
+                let val: Value = val;
+                if let Some(arr) = val.as_array() {
+                    let w = arr.first().and_then(|v: &Value| v.as_f64()).unwrap_or(1000.0);
+                    let h = arr.get(1).and_then(|v: &Value| v.as_f64()).unwrap_or(1000.0);
+                    layout_elem_size.set((w, h));
+                }
+            }
+        });
+    });
+
     // سحب البيانات من الـ Signals قبل الرسم لتجنب Deadlock
     let pts_snapshot = points.read().clone();
     let lns_snapshot = lines.read().clone();
     let spl_snapshot = splines.read().clone();
     let bis_snapshot = bisectors.read().clone();
     let cnt_snapshot = contours.read().clone();
+    let ops_snapshot = operations.read().clone();
+    let pcs_snapshot = pieces.read().clone();
+    let pattern_snapshot = pattern_info.read().clone();
+    let pairs_snapshot = seam_pairs.read().clone();
     let current_mode = mode.read().clone();
-    let current_selection = selected_item.read().clone();
+    let current_selection = selection.read().clone();
+    let current_band = *band.read();
+    let current_hover = hovered.read().clone();
+    // قراءة الفهرس عبر الحارس بدون نسخ الشجرة في كل رسم
+    let index_snapshot = spatial_index.read();
+    let layout_opts_snapshot = *layout_options.read();
+    let layout_snapshot = layout.read().clone();
+    let current_layout_view = *layout_view.read();
+    let notices_snapshot = notices.read().clone();
+    let recovery_age = recovery_offer.read().as_ref().map(|r| r.age_minutes());
+    let png_region = png_options.read().region;
+    // القطعة التي يُختار خط نسيجها على الكانفاس الآن
+    let grain_pick = match current_mode {
+        CanvasMode::GrainlineStart { piece_id } | CanvasMode::GrainlineEnd { piece_id, .. } => Some(piece_id),
+        _ => None,
+    };
+    let script_snapshot = script_result.read().clone();
+
+    // استبعاد العناصر خارج مجال الرؤية (Viewport Culling)
+    let visible: HashSet<SelectedItem> = index_snapshot
+        .query(&Bounds::new(Point2D::new(0.0, 0.0), Point2D::new(1000.0, 1000.0)))
+        .into_iter()
+        .map(|s| s.item.clone())
+        .collect();
+    // كل الكيانات الداخلة في مسارات (بدلاً من البحث في كل مسار لكل كيان)
+    let contour_members: HashSet<EntityRef> = cnt_snapshot.iter()
+        .flat_map(|c| c.entities.iter().copied())
+        .collect();
+
+    // الكائنات المنحلة ضمن سماحية المستند: تُرسم كعلامة خطأ مع تلميح بدل شكلها
+    let tolerance = doc_tolerance.read().0;
+    let coords_of = |id: u32| index_snapshot.point(&pts_snapshot, id).map(|p| p.coords);
+    let mut error_markers: Vec<(String, Point2D, String)> = Vec::new();
+    let degenerate = object::degenerate_objects(&pts_snapshot, &lns_snapshot, &spl_snapshot, &bis_snapshot, tolerance);
+    for (item, e) in &degenerate {
+        let marker = match *item {
+            SelectedItem::Line(id) => index_snapshot.lines.get(&lns_snapshot, id)
+                .and_then(|l| Some((format!("ln-{id}"), coords_of(l.start_point_id)?, format!("Line {}: {}", l.metadata.name, e)))),
+            SelectedItem::Spline(id) => index_snapshot.splines.get(&spl_snapshot, id)
+                .and_then(|s| Some((format!("spl-{id}"), coords_of(s.p1_id)?, format!("Spline {}: {}", s.metadata.name, e)))),
+            SelectedItem::Bisector(id) => index_snapshot.bisectors.get(&bis_snapshot, id)
+                .and_then(|b| Some((format!("bis-{id}"), coords_of(b.vertex_id)?, format!("Bisector {}: {}", b.metadata.name, e)))),
+            _ => None,
+        };
+        error_markers.extend(marker);
+    }
+    // العمليات المنحلة: نقاطها المشتقة لم تُحسب
+    for op in &ops_snapshot {
+        let Err(e) = op.check(&pts_snapshot, tolerance) else { continue };
+        for &(_, dst) in &op.point_map {
+            if let Some(at) = coords_of(dst) {
+                error_markers.push((format!("op-{}-{dst}", op.metadata.id), at, format!("Operation {}: {}", op.metadata.name, e)));
+            }
+        }
+    }
+    let degenerate_items: HashSet<SelectedItem> = degenerate.into_iter().map(|(item, _)| item).collect();
+    // مواضع العلامات لالتقاطها في onmousemove (الكانفاس لا يستقبل أحداث المؤشر على عناصره)
+    let error_spots: Vec<(String, Point2D)> = error_markers.iter().map(|(key, at, _)| (key.clone(), *at)).collect();
+    let current_error_hover = hovered_error.read().clone();
+
+    // ترتيب كل مسار والتحقق من اتصاله وانغلاقه
+    let contour_reports: Vec<(u32, ContourReport)> = cnt_snapshot.iter()
+        .map(|c| (c.metadata.id, contour::order_contour(c, &pts_snapshot, &lns_snapshot, &spl_snapshot, &bis_snapshot, contour::CONTOUR_TOLERANCE)))
+        .collect();
+    let contour_metrics: Vec<(u32, ContourMetrics)> = contour_reports.iter()
+        .map(|(id, r)| (*id, r.metrics()))
+        .collect();
+    // التقارير والمقاييس بنفس ترتيب المسارات فيكفيها فهرس المسارات
+    let contour_ids = contour_index.read();
+    let piece_ids = piece_index.read();
+
+    // كيانات كل مسار بالترتيب المحسوب (لاختيار طرفي أجزاء الحياكة)
+    let ordered_entities: Vec<(u32, Vec<EntityRef>)> = contour_reports.iter()
+        .map(|(id, r)| (*id, r.ordered_entities()))
+        .collect();
+    let ordered_entities = Rc::new(ordered_entities);
+
+    // هندسة القطع للتخطيط وإحصاءاته
+    let layout_pieces: Vec<PieceGeometry> = if layout_snapshot.placements.is_empty() {
+        Vec::new()
+    } else {
+        pcs_snapshot.iter()
+            .filter_map(|p| PieceGeometry::build(p, &contour_reports, &pts_snapshot, &pattern_snapshot))
+            .collect()
+    };
+    let layout_piece_ids = IdIndex::build(&layout_pieces, |g| g.id);
+    let layout_length = layout_snapshot.length(&layout_pieces);
+    let layout_efficiency = layout_snapshot.efficiency(&layout_pieces);
+    let sheet_length = layout_opts_snapshot.max_length.unwrap_or(layout_length).max(layout_length).max(100.0);
+    let sheet_margin = sheet_length.max(layout_snapshot.fabric_width) * 0.05;
+    let sheet_view = SvgViewBox {
+        min_x: -sheet_margin,
+        min_y: -sheet_margin,
+        width: sheet_length + 2.0 * sheet_margin,
+        height: layout_snapshot.fabric_width + 2.0 * sheet_margin,
+    };
+    let sheet_view_box = format!("{} {} {} {}", sheet_view.min_x, sheet_view.min_y, sheet_view.width, sheet_view.height);
+    let layout_conflicts = layout_snapshot.conflicts(&layout_pieces);
+    let selected_placement = layout_selected.read().and_then(|i| {
+        let pl = layout_snapshot.placements.get(i)?;
+        let geo = layout_piece_ids.get(&layout_pieces, pl.piece_id)?;
+        Some((i, pl.copy, geo.clone()))
+    });
+
+    // تحويل إحداثيات الماوس في لوحة التخطيط إلى مليمترات القماش
+    let to_layout = move |pixel_x: f64, pixel_y: f64| -> Point2D {
+        let (elem_w, elem_h) = *layout_elem_size.read();
+        let mapper = CoordMapper { viewbox: sheet_view, preserve_aspect_ratio: AspectRatioMode::Meet };
+        let (x, y) = mapper.to_svg_space(pixel_x, pixel_y, elem_w, elem_h);
+        Point2D::new(x, y)
+    };
+
+    // تعديل نسخة موضوعة (دوران أو قلب) بمعرفة هندسة قطعتها
+    let mut edit_placement = move |i: usize, geo: &PieceGeometry, edit: &dyn Fn(&mut layout::PlacedPiece, &PieceGeometry)| {
+        let mut lay = layout.write();
+        if let Some(pl) = lay.placements.get_mut(i)
+            && pl.piece_id == geo.id
+        {
+            edit(pl, geo);
+        }
+    };
+
+    // العناصر المختارة حالياً لعملية جماعية (تظهر مميزة على الكانفاس)
+    let pending_items: HashSet<SelectedItem> = match &current_mode {
+        CanvasMode::OperationSelect { items }
+        | CanvasMode::MirrorLineStart { items }
+        | CanvasMode::MirrorLineEnd { items, .. }
+        | CanvasMode::MirrorAxisOrigin { items, .. }
+        | CanvasMode::RotationOrigin { items, .. } => items.iter().cloned().collect(),
+        _ => HashSet::new(),
+    };
+
+    let mut notify = move |level: NoticeLevel, text: String| {
+        let id = *notice_seq.read();
+        notice_seq.set(id + 1);
+        let mut list = notices.write();
+        list.push(Notice { id, level, text });
+        let excess = list.len().saturating_sub(MAX_NOTICES);
+        list.drain(..excess);
+    };
+    // نتيجة عملية ملف: رسالة نجاح أو خطأ
+    let mut notify_result = move |result: Result<String, Error>| match result {
+        Ok(text) => notify(NoticeLevel::Info, text),
+        Err(e) => notify(NoticeLevel::Error, e.to_string()),
+    };
+
+    // إنشاء عملية جماعية على العناصر المختارة ثم العودة لمرحلة الاختيار
+    let mut create_operation = move |kind: OperationKind, items: Vec<SelectedItem>| {
+        let op_id = *next_id.read();
+        let mut nid = op_id + 1;
+        // اللاحقة الافتراضية مثل Valentina: a1, a2...
+        let suffix = match op_suffix.read().trim() {
+            "" => format!("a{}", operations.read().len() + 1),
+            s => s.to_string(),
+        };
+        let op = VOperation::new(op_id, &format!("{}{}", kind.name_prefix(), op_id), kind, &suffix);
+        let created = operation::apply_operation(
+            op, &items, &mut nid,
+            &mut points.write(), &mut lines.write(), &mut splines.write(), &mut bisectors.write(),
+            doc_tolerance.read().0,
+        );
+        match created {
+            Ok(op) => {
+                operations.write().push(op);
+                next_id.set(nid);
+            }
+            Err(e) => notify(NoticeLevel::Error, format!("Operation not created: {}", e)),
+        }
+        mode.set(CanvasMode::OperationSelect { items: Vec::new() });
+    };
+
+    // نقرة عادية تستبدل الاختيار، ونقرة مع Shift تضيف العنصر أو تزيله
+    let mut select_item = move |item: SelectedItem, additive: bool| {
+        if additive {
+            selection.write().toggle(item);
+        } else {
+            selection.write().set_single(item);
+        }
+    };
+
+    // حذف مجموعة عناصر دفعة واحدة (النقاط تُحذف مع كل ما يعتمد عليها،
+    // والخطوط والمنحنيات والمنصفات تُزال مراجعها من المسارات والقطع وأزواج الحياكة)
+    let mut delete_items = move |items: Vec<SelectedItem>| {
+        let mut gone: Vec<EntityRef> = Vec::new();
+        for item in items {
+            match item {
+                SelectedItem::Point(id) => {
+                    // حذف النقطة مع كل النسخ المشتقة منها عبر العمليات
+                    let removed = operation::cascade_delete(&mut operations.write(), id);
+                    points.write().retain(|p| !removed.contains(&p.metadata.id));
+                    lines.write().retain(|l| {
+                        let keep = !removed.contains(&l.start_point_id) && !removed.contains(&l.end_point_id);
+                        if !keep { gone.push(EntityRef::Line(l.metadata.id)); }
+                        keep
+                    });
+                    splines.write().retain(|s| {
+                        let keep = ![s.p1_id, s.p2_id, s.p3_id, s.p4_id].iter().any(|p| removed.contains(p));
+                        if !keep { gone.push(EntityRef::Spline(s.metadata.id)); }
+                        keep
+                    });
+                    bisectors.write().retain(|b| {
+                        let keep = ![b.p1_id, b.vertex_id, b.p3_id].iter().any(|p| removed.contains(p));
+                        if !keep { gone.push(EntityRef::Bisector(b.metadata.id)); }
+                        keep
+                    });
+                    for p in pieces.write().iter_mut() {
+                        p.placements.retain(|pl| !removed.contains(&pl.point_id));
+                    }
+                }
+                SelectedItem::Line(id) => {
+                    lines.write().retain(|l| l.metadata.id != id);
+                    gone.push(EntityRef::Line(id));
+                }
+                SelectedItem::Spline(id) => {
+                    splines.write().retain(|s| s.metadata.id != id);
+                    gone.push(EntityRef::Spline(id));
+                }
+                SelectedItem::Bisector(id) => {
+                    bisectors.write().retain(|b| b.metadata.id != id);
+                    gone.push(EntityRef::Bisector(id));
+                }
+                SelectedItem::Contour(id) => {
+                    contours.write().retain(|c| c.metadata.id != id);
+                    pieces.write().retain(|p| p.contour_id != id);
+                    for p in pieces.write().iter_mut() {
+                        p.internal_paths.retain(|ip| ip.contour_id != id);
+                    }
+                    seam_pairs.write().retain(|sp| sp.first.contour_id != id && sp.second.contour_id != id);
+                }
+                SelectedItem::Piece(id) => pieces.write().retain(|p| p.metadata.id != id),
+                SelectedItem::None => {}
+            }
+        }
+        piece::detach_entities(&mut contours.write(), &mut pieces.write(), &mut seam_pairs.write(), &gone);
+        selection.write().clear();
+    };
+
+    // تعديل خصائص قطعة من لوحة الخصائص
+    let mut edit_piece = move |id: u32, edit: &dyn Fn(&mut VPiece)| {
+        if let Some(p) = piece_index.read().get_mut(&mut pieces.write(), id) {
+            edit(p);
+        }
+    };
+
+    let mut edit_contour = move |id: u32, edit: &dyn Fn(&mut VContour)| {
+        if let Some(c) = contour_index.read().get_mut(&mut contours.write(), id) {
+            edit(c);
+        }
+    };
+
+    let mut edit_pair = move |id: u32, edit: &dyn Fn(&mut SeamPair)| {
+        if let Some(p) = seam_pairs.write().iter_mut().find(|p| p.id == id) {
+            edit(p);
+        }
+    };
+
+    // تجميع المشهد المُصدَّر من البيانات الحالية
+    let build_scene = move |content: ExportContent| -> ExportScene {
+        let (pts, lns, spl, bis) = (points.read(), lines.read(), splines.read(), bisectors.read());
+        let reports: Vec<(u32, ContourReport)> = contours.read().iter()
+            .map(|c| (c.metadata.id, contour::order_contour(c, &pts, &lns, &spl, &bis, contour::CONTOUR_TOLERANCE)))
+            .collect();
+        ExportScene::build(&pts, &lns, &spl, &bis, &pieces.read(), &reports, &pattern_info.read(), content)
+    };
+
+    // المستند الحالي كملف مشروع (للحفظ وتشغيل السكربتات)
+    let current_project = move || ProjectData {
+        points: points.read().clone(),
+        lines: lines.read().clone(),
+        splines: splines.read().clone(),
+        bisectors: bisectors.read().clone(),
+        contours: contours.read().clone(),
+        operations: operations.read().clone(),
+        pieces: pieces.read().clone(),
+        pattern: pattern_info.read().clone(),
+        seam_pairs: seam_pairs.read().clone(),
+        layout_options: *layout_options.read(),
+        layout: layout.read().clone(),
+        measurements: measurements.read().clone(),
+        tolerance: *doc_tolerance.read(),
+        next_id: *next_id.read(),
+    };
+
+    // استبدال المستند الحالي بمشروع كامل (تحميل ملف أو نتيجة سكربت)
+    let mut apply_project = move |data: ProjectData| {
+        points.set(data.points);
+        lines.set(data.lines);
+        splines.set(data.splines);
+        bisectors.set(data.bisectors);
+        contours.set(data.contours);
+        operations.set(data.operations);
+        pieces.set(data.pieces);
+        pattern_info.set(data.pattern);
+        seam_pairs.set(data.seam_pairs);
+        layout_options.set(data.layout_options);
+        layout.set(data.layout);
+        measurements.set(data.measurements);
+        doc_tolerance.set(data.tolerance);
+        layout_selected.set(None);
+        next_id.set(data.next_id);
+        selection.write().clear();
+    };
+
+    // حفظ تلقائي دوري للمستند إذا تغير منذ آخر حفظ (يستمر أثناء عرض الاستعادة لأن النسخة القديمة محجوزة في ملف آخر)
+    use_future(move || async move {
+        let Some(store) = recovery_store.read().clone() else { return };
+        // المستند الفارغ عند البدء لا يحتاج استعادة
+        saved_hash.set(Some(content_hash(&current_project())));
+        loop {
+            tokio::time::sleep(recovery::AUTOSAVE_INTERVAL).await;
+            let data = current_project();
+            let hash = Some(content_hash(&data));
+            if hash == *saved_hash.read() {
+                // عاد المستند إلى حالته المحفوظة (تراجع عن التعديلات): نسخة الاستعادة لم تعد لازمة
+                if autosaved_hash.read().is_some() {
+                    match store.clear() {
+                        Ok(()) => autosaved_hash.set(None),
+                        Err(e) => notify(NoticeLevel::Warning, format!("Could not remove recovery file: {}", e)),
+                    }
+                }
+                continue;
+            }
+            if hash == *autosaved_hash.read() {
+                continue;
+            }
+            match store.save(&data) {
+                Ok(()) => autosaved_hash.set(hash),
+                Err(e) => notify(NoticeLevel::Warning, format!("Autosave failed: {}", e)),
+            }
+        }
+    });
+
+    // هندسة كل القطع بدقة تقسيم محددة للمنحنيات (مثل دقة الراسم)
+    let build_pieces = move |resolution: Option<CurveResolution>| -> Vec<PieceGeometry> {
+        let (pts, lns, spl, bis) = (points.read(), lines.read(), splines.read(), bisectors.read());
+        let reports: Vec<(u32, ContourReport)> = contours.read().iter()
+            .map(|c| (c.metadata.id, contour::order_contour(c, &pts, &lns, &spl, &bis, contour::CONTOUR_TOLERANCE)))
+            .collect();
+        pieces.read().iter()
+            .filter_map(|p| PieceGeometry::build_at(p, &reports, &pts, &pattern_info.read(), resolution))
+            .collect()
+    };
+
+    // تحويل إحداثيات الماوس (بكسل) إلى إحداثيات الرسم مع سماحية الالتقاط بوحدات الرسم
+    let to_model = move |pixel_x: f64, pixel_y: f64| -> (Point2D, f64) {
+        let (elem_w, elem_h) = *svg_elem_size.read();
+        let mapper = CoordMapper {
+            viewbox: SvgViewBox { min_x: 0.0, min_y: 0.0, width: 1000.0, height: 1000.0 },
+            preserve_aspect_ratio: AspectRatioMode::Meet,
+        };
+        let (svg_x, svg_y) = mapper.to_svg_space(pixel_x, pixel_y, elem_w, elem_h);
+        let tolerance = mapper.pixels_to_svg(hit_test::HIT_TOLERANCE_PX, elem_w, elem_h);
+        (Point2D::new(svg_x, svg_y), tolerance)
+    };
+
+    // النقر على نقطة: السلوك يعتمد على الأداة الحالية
+    let mut on_point_click = move |pid: u32, additive: bool| {
+        let current_m = mode.read().clone();
+        match current_m {
+            CanvasMode::PlacePoint => {
+                // النقاط المشتقة تُحسب من أصولها ولا تُسحب يدوياً
+                if !additive && !operation::is_derived(&operations.read(), pid) {
+                    dragging_point_id.set(Some(pid));
+                }
+                select_item(SelectedItem::Point(pid), additive);
+            }
+            CanvasMode::Select => {
+                select_item(SelectedItem::Point(pid), additive);
+            }
+            CanvasMode::AwaitingLineStart => {
+                mode.set(CanvasMode::AwaitingLineEnd { first_point_id: pid });
+            }
+            CanvasMode::AwaitingLineEnd { first_point_id } => {
+                if first_point_id != pid {
+                    let lid = *next_id.read();
+                    let line_name = format!("L{}", lid);
+                    lines.write().push(VLine::new(lid, &line_name, first_point_id, pid));
+                    next_id.set(lid + 1);
+                }
+                mode.set(CanvasMode::AwaitingLineStart);
+            }
+            CanvasMode::BezierStart => {
+                mode.set(CanvasMode::BezierControl1 { p1: pid });
+            }
+            CanvasMode::BezierControl1 { p1 } => {
+                mode.set(CanvasMode::BezierControl2 { p1, p2: pid });
+            }
+            CanvasMode::BezierControl2 { p1, p2 } => {
+                mode.set(CanvasMode::BezierEnd { p1, p2, p3: pid });
+            }
+            CanvasMode::BezierEnd { p1, p2, p3 } => {
+                if pid != p1 && pid != p2 && pid != p3 {
+                    let sid = *next_id.read();
+                    let spline_name = format!("S{}", sid);
+                    splines.write().push(VCubicBezier::new(sid, &spline_name, p1, p2, p3, pid));
+                    next_id.set(sid + 1);
+                }
+                mode.set(CanvasMode::BezierStart);
+            }
+            CanvasMode::BisectorStart => {
+                mode.set(CanvasMode::BisectorVertex { p1: pid });
+            }
+            CanvasMode::BisectorVertex { p1 } => {
+                mode.set(CanvasMode::BisectorEnd { p1, vertex: pid });
+            }
+            CanvasMode::BisectorEnd { p1, vertex } => {
+                if pid != p1 && pid != vertex {
+                    let bid = *next_id.read();
+                    let bis_name = format!("B{}", bid);
+                    bisectors.write().push(VBisector::new(bid, &bis_name, p1, vertex, pid, 150.0));
+                    next_id.set(bid + 1);
+                }
+                mode.set(CanvasMode::BisectorStart);
+            }
+            CanvasMode::ContourCreation { .. } => {
+                select_item(SelectedItem::Point(pid), false);
+            }
+            CanvasMode::OperationSelect { mut items } => {
+                toggle_item(&mut items, SelectedItem::Point(pid));
+                mode.set(CanvasMode::OperationSelect { items });
+            }
+            CanvasMode::MirrorLineStart { items } => {
+                mode.set(CanvasMode::MirrorLineEnd { items, p1: pid });
+            }
+            CanvasMode::MirrorLineEnd { items, p1 } => {
+                if pid != p1 {
+                    create_operation(OperationKind::FlippingByLine { p1_id: p1, p2_id: pid }, items);
+                }
+            }
+            CanvasMode::MirrorAxisOrigin { items, axis } => {
+                create_operation(OperationKind::FlippingByAxis { origin_id: pid, axis }, items);
+            }
+            CanvasMode::RotationOrigin { items, angle } => {
+                create_operation(OperationKind::Rotation { origin_id: pid, angle }, items);
+            }
+            CanvasMode::GrainlineStart { piece_id } => {
+                mode.set(CanvasMode::GrainlineEnd { piece_id, start_id: pid });
+            }
+            CanvasMode::GrainlineEnd { piece_id, start_id } => {
+                if pid != start_id {
+                    edit_piece(piece_id, &|p| p.grainline = Some(Grainline::Points { start_id, end_id: pid }));
+                    mode.set(CanvasMode::Select);
+                }
+            }
+        }
+    };
+
+    // النقر على خط أو منحنى أو منصف
+    let mut on_entity_click = move |item: SelectedItem, additive: bool| {
+        let entity = match item {
+            SelectedItem::Line(id) => EntityRef::Line(id),
+            SelectedItem::Spline(id) => EntityRef::Spline(id),
+            SelectedItem::Bisector(id) => EntityRef::Bisector(id),
+            _ => return,
+        };
+        let current_m = mode.read().clone();
+        match current_m {
+            CanvasMode::ContourCreation { active_contour_id } => {
+                edit_contour(active_contour_id, &|c| c.entities.push(entity));
+            }
+            CanvasMode::OperationSelect { mut items } => {
+                toggle_item(&mut items, item);
+                mode.set(CanvasMode::OperationSelect { items });
+            }
+            _ => select_item(item, additive),
+        }
+    };
+
+    // النقر على مساحة فارغة: إضافة نقطة أو بدء مستطيل الاختيار
+    let mut on_background_click = move |at: Point2D, additive: bool| {
+        match *mode.read() {
+            CanvasMode::PlacePoint => {
+                let id = *next_id.read();
+                points.write().push(VPoint::new(id, &format!("P{}", id), at.x, at.y));
+                next_id.set(id + 1);
+            }
+            CanvasMode::Select => band.set(Some(RubberBand::new(at))),
+            _ => {}
+        }
+        if !additive {
+            selection.write().clear();
+        }
+    };
 
     let mode_text = match current_mode {
         CanvasMode::PlacePoint => "Click background to add Points / Drag to move",
+        CanvasMode::Select => "Select: Shift+Click to toggle / Drag → window, drag ← crossing",
         CanvasMode::AwaitingLineStart => "Line: Select Start Point",
         CanvasMode::AwaitingLineEnd { .. } => "Line: Select End Point",
         CanvasMode::BezierStart => "Bezier: Select Start Point",
@@ -99,6 +735,13 @@ fn App() -> Element {
         CanvasMode::BisectorVertex { .. } => "Bisector: Select vertex (corner)",
         CanvasMode::BisectorEnd { .. } => "Bisector: Select third point",
         CanvasMode::ContourCreation { .. } => "Contour: Select lines/splines to group",
+        CanvasMode::OperationSelect { .. } => "Operations: Select objects, then choose mirror, rotation or move",
+        CanvasMode::MirrorLineStart { .. } => "Mirror: Select first point of the mirror line",
+        CanvasMode::MirrorLineEnd { .. } => "Mirror: Select second point of the mirror line",
+        CanvasMode::MirrorAxisOrigin { .. } => "Mirror: Select a point on the axis",
+        CanvasMode::RotationOrigin { .. } => "Rotation: Select the rotation origin point",
+        CanvasMode::GrainlineStart { .. } => "Grainline: Select the start point",
+        CanvasMode::GrainlineEnd { .. } => "Grainline: Select the end point",
     };
 
     rsx! {
@@ -109,6 +752,42 @@ fn App() -> Element {
             
             div { id: "sidebar",
                 h2 { "Valentina-Oxidized 🦀" }
+
+                if let Some(age) = recovery_age {
+                    div { class: "recovery-banner",
+                        span { "Unsaved work from {age} min ago was found. Restore it?" }
+                        div { class: "toolbar",
+                            button {
+                                onclick: move |_| {
+                                    let Some(rec) = recovery_offer.write().take() else { return };
+                                    let Some(store) = recovery_store.read().clone() else { return };
+                                    match store.load(&rec) {
+                                        Ok(data) => {
+                                            apply_project(data);
+                                            autosaved_hash.set(None);
+                                            if let Err(e) = store.discard(&rec) {
+                                                notify(NoticeLevel::Warning, format!("Could not remove the recovery file: {}", e));
+                                            }
+                                            notify(NoticeLevel::Info, "Restored unsaved work. Save it to keep it.".to_string());
+                                        }
+                                        Err(e) => notify(NoticeLevel::Error, format!("Could not restore: {}", e)),
+                                    }
+                                },
+                                "♻ Restore"
+                            }
+                            button {
+                                onclick: move |_| {
+                                    let Some(rec) = recovery_offer.write().take() else { return };
+                                    if let Some(store) = recovery_store.read().as_ref()
+                                        && let Err(e) = store.discard(&rec) {
+                                        notify(NoticeLevel::Warning, format!("Could not remove the recovery file: {}", e));
+                                    }
+                                },
+                                "🗑 Discard"
+                            }
+                        }
+                    }
+                }
                 
                 div { class: "toolbar",
                     button {
@@ -116,6 +795,11 @@ fn App() -> Element {
                         onclick: move |_| mode.set(CanvasMode::PlacePoint),
                         "📍 Pt"
                     }
+                    button {
+                        class: if matches!(current_mode, CanvasMode::Select) { "active" } else { "" },
+                        onclick: move |_| mode.set(CanvasMode::Select),
+                        "⬚ Sel"
+                    }
                     button {
                         class: if matches!(current_mode, CanvasMode::AwaitingLineStart | CanvasMode::AwaitingLineEnd { .. }) { "active" } else { "" },
                         onclick: move |_| mode.set(CanvasMode::AwaitingLineStart),
@@ -142,117 +826,797 @@ fn App() -> Element {
                         },
                         "🧩 Path"
                     }
+                    button {
+                        class: if matches!(current_mode, CanvasMode::OperationSelect { .. } | CanvasMode::MirrorLineStart { .. } | CanvasMode::MirrorLineEnd { .. } | CanvasMode::MirrorAxisOrigin { .. } | CanvasMode::RotationOrigin { .. }) { "active" } else { "" },
+                        onclick: move |_| mode.set(CanvasMode::OperationSelect { items: Vec::new() }),
+                        "🔁 Ops"
+                    }
                 }
 
                 p { class: "mode-hint", "{mode_text}" }
-                
-                div { class: "info-box",
-                    h3 { "Selection" }
-                    match current_selection {
-                        SelectedItem::None => rsx! { p { "Nothing selected" } },
-                        SelectedItem::Point(id) => rsx! { 
-                            div {
-                                p { "Selected Point: P{id}" }
-                                button { 
-                                    class: "delete-btn",
-                                    onclick: move |_| {
-                                        points.write().retain(|p| p.metadata.id != id);
-                                        lines.write().retain(|l| l.start_point_id != id && l.end_point_id != id);
-                                        splines.write().retain(|s| s.p1_id != id && s.p2_id != id && s.p3_id != id && s.p4_id != id);
-                                        bisectors.write().retain(|b| b.p1_id != id && b.vertex_id != id && b.p3_id != id);
-                                        selected_item.set(SelectedItem::None);
+
+                if let CanvasMode::OperationSelect { items } = current_mode.clone() {
+                    div { class: "info-box",
+                        h3 { "Operations" }
+                        p { class: "stats", "Objects: {items.len()}" }
+                        label { "Suffix " }
+                        input {
+                            r#type: "text",
+                            placeholder: "a{ops_snapshot.len() + 1}",
+                            value: "{op_suffix}",
+                            oninput: move |evt| op_suffix.set(evt.value()),
+                        }
+                        if !items.is_empty() {
+                            h3 { "Mirror" }
+                            div { class: "toolbar",
+                                button {
+                                    onclick: {
+                                        let items = items.clone();
+                                        move |_| mode.set(CanvasMode::MirrorLineStart { items: items.clone() })
                                     },
-                                    "🗑 Delete"
+                                    "By line"
+                                }
+                                button {
+                                    onclick: {
+                                        let items = items.clone();
+                                        move |_| mode.set(CanvasMode::MirrorAxisOrigin { items: items.clone(), axis: AxisType::Horizontal })
+                                    },
+                                    "↔ Axis"
+                                }
+                                button {
+                                    onclick: {
+                                        let items = items.clone();
+                                        move |_| mode.set(CanvasMode::MirrorAxisOrigin { items: items.clone(), axis: AxisType::Vertical })
+                                    },
+                                    "↕ Axis"
                                 }
                             }
-                        },
-                        SelectedItem::Line(id) => rsx! { 
-                            div {
-                                p { "Selected Line: L{id}" }
-                                button { 
-                                    class: "delete-btn",
-                                    onclick: move |_| {
-                                        lines.write().retain(|l| l.metadata.id != id);
-                                        selected_item.set(SelectedItem::None);
+                            h3 { "Rotate" }
+                            label { "Angle° " }
+                            input {
+                                r#type: "number",
+                                value: "{rotation_angle}",
+                                oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() { rotation_angle.set(v) },
+                            }
+                            div { class: "toolbar",
+                                button {
+                                    onclick: {
+                                        let items = items.clone();
+                                        move |_| mode.set(CanvasMode::RotationOrigin { items: items.clone(), angle: *rotation_angle.read() })
                                     },
-                                    "🗑 Delete"
+                                    "⟳ Pick origin"
                                 }
                             }
-                        },
-                        SelectedItem::Spline(id) => rsx! { 
-                            div {
-                                p { "Selected Spline: S{id}" }
-                                button { 
-                                    class: "delete-btn",
+                            h3 { "Move" }
+                            label { "Length " }
+                            input {
+                                r#type: "number",
+                                value: "{move_length}",
+                                oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() { move_length.set(v) },
+                            }
+                            label { " Angle° " }
+                            input {
+                                r#type: "number",
+                                value: "{move_angle}",
+                                oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() { move_angle.set(v) },
+                            }
+                            div { class: "toolbar",
+                                button {
                                     onclick: move |_| {
-                                        splines.write().retain(|s| s.metadata.id != id);
-                                        selected_item.set(SelectedItem::None);
+                                        let kind = OperationKind::Move { length: *move_length.read(), angle: *move_angle.read() };
+                                        create_operation(kind, items.clone());
                                     },
-                                    "🗑 Delete"
+                                    "➜ Apply move"
+                                }
+                            }
+                        }
+                    }
+                }
+                
+                div { class: "info-box",
+                    h3 { "Selection" }
+                    div { class: "toolbar",
+                        button {
+                            onclick: move |_| {
+                                let all = selection::all_items(&points.read(), &lines.read(), &splines.read(), &bisectors.read(), &contours.read(), &pieces.read());
+                                selection.write().replace(all);
+                            },
+                            "All"
+                        }
+                        button {
+                            onclick: move |_| {
+                                let all = selection::all_items(&points.read(), &lines.read(), &splines.read(), &bisectors.read(), &contours.read(), &pieces.read());
+                                let inverted = all.into_iter().filter(|i| !selection.read().contains(i)).collect();
+                                selection.write().replace(inverted);
+                            },
+                            "Invert"
+                        }
+                        button {
+                            onclick: move |_| selection.write().clear(),
+                            "None"
+                        }
+                    }
+                    if current_selection.is_empty() {
+                        p { "Nothing selected" }
+                    } else if let Some(single) = current_selection.single() {
+                        match single {
+                            SelectedItem::None => rsx! { p { "Nothing selected" } },
+                            SelectedItem::Point(id) => rsx! { 
+                                div {
+                                    p { "Selected Point: P{id}" }
+                                    if let Some(op) = ops_snapshot.iter().find(|op| op.point_map.iter().any(|(_, d)| *d == id)) {
+                                        p { class: "stats", "Derived by {op.metadata.name}" }
+                                    }
+                                    button { 
+                                        class: "delete-btn",
+                                        onclick: move |_| delete_items(vec![SelectedItem::Point(id)]),
+                                        "🗑 Delete"
+                                    }
+                                }
+                            },
+                            SelectedItem::Line(id) => rsx! { 
+                                div {
+                                    p { "Selected Line: L{id}" }
+                                    button { 
+                                        class: "delete-btn",
+                                        onclick: move |_| delete_items(vec![SelectedItem::Line(id)]),
+                                        "🗑 Delete"
+                                    }
+                                }
+                            },
+                            SelectedItem::Spline(id) => rsx! { 
+                                div {
+                                    p { "Selected Spline: S{id}" }
+                                    button { 
+                                        class: "delete-btn",
+                                        onclick: move |_| delete_items(vec![SelectedItem::Spline(id)]),
+                                        "🗑 Delete"
+                                    }
+                                }
+                            },
+                            SelectedItem::Bisector(id) => rsx! { 
+                                div {
+                                    p { "Selected Bisector: B{id}" }
+                                    button { 
+                                        class: "delete-btn",
+                                        onclick: move |_| delete_items(vec![SelectedItem::Bisector(id)]),
+                                        "🗑 Delete"
+                                    }
+                                }
+                            },
+                            SelectedItem::Contour(id) => rsx! { 
+                                div {
+                                    p { "Selected Path: {id}" }
+                                    if let Some(c) = contour_ids.get(&cnt_snapshot, id) {
+                                        p { class: "stats", "Entities: {c.entities.len()}" }
+                                    }
+                                    if let Some((_, m)) = contour_ids.get(&contour_metrics, id) {
+                                        ul { class: "stats",
+                                            li { "Perimeter: {m.perimeter:.2}" }
+                                            if m.area > 0.0 {
+                                                li { "Area: {m.area:.2}" }
+                                            }
+                                            if let Some(c) = m.centroid {
+                                                li { "Centroid: ({c.x:.1}, {c.y:.1})" }
+                                            }
+                                            if !m.self_intersections.is_empty() {
+                                                li { style: "color: #e74c3c;", "⚠ Self-intersections: {m.self_intersections.len()}" }
+                                            }
+                                        }
+                                    }
+                                    if let Some((_, report)) = contour_ids.get(&contour_reports, id) {
+                                        ul { class: "stats",
+                                            li { if report.closed { "Closed: yes" } else { "Closed: no" } }
+                                            li {
+                                                match report.orientation {
+                                                    Orientation::Clockwise => "Orientation: CW",
+                                                    Orientation::CounterClockwise => "Orientation: CCW",
+                                                    Orientation::Degenerate => "Orientation: —",
+                                                }
+                                            }
+                                            for gap in report.gaps.iter() {
+                                                li { style: "color: #e74c3c;", "Gap: {gap.distance:.2} at ({gap.at.x:.1}, {gap.at.y:.1})" }
+                                            }
+                                            if !report.missing.is_empty() {
+                                                li { style: "color: #e74c3c;", "Missing entities: {report.missing.len()}" }
+                                            }
+                                        }
+                                        button {
+                                            class: "action-btn",
+                                            onclick: {
+                                                let ordered = report.ordered_entities();
+                                                move |_| {
+                                                    // حفظ الترتيب المحسوب بدلاً من ترتيب النقر
+                                                    edit_contour(id, &|c| c.entities = ordered.clone());
+                                                }
+                                            },
+                                            "⇄ Auto-order"
+                                        }
+                                        if report.closed {
+                                            button {
+                                                class: "action-btn",
+                                                onclick: move |_| {
+                                                    let pid = *next_id.read();
+                                                    pieces.write().push(VPiece::new(pid, &format!("Piece{}", pid), id, piece::DEFAULT_SEAM_ALLOWANCE));
+                                                    next_id.set(pid + 1);
+                                                    selection.write().set_single(SelectedItem::Piece(pid));
+                                                },
+                                                "✂ Make piece"
+                                            }
+                                        }
+                                    }
+                                    button { 
+                                        class: "delete-btn",
+                                        onclick: move |_| delete_items(vec![SelectedItem::Contour(id)]),
+                                        "🗑 Delete Path"
+                                    }
+                                }
+                            },
+                            SelectedItem::Piece(id) => rsx! {
+                                div {
+                                    if let Some(pc) = piece_ids.get(&pcs_snapshot, id) {
+                                        p { "Selected Piece: {pc.metadata.name}" }
+                                        label { "Seam allowance " }
+                                        input {
+                                            r#type: "number",
+                                            value: "{pc.seam_allowance}",
+                                            oninput: move |evt| {
+                                                if let Ok(v) = evt.value().parse::<f64>() {
+                                                    edit_piece(id, &|p| p.seam_allowance = v.max(0.0));
+                                                }
+                                            },
+                                        }
+                                        label { " Corners " }
+                                        select {
+                                            onchange: move |evt| {
+                                                if let Some(c) = CornerType::from_label(&evt.value()) {
+                                                    edit_piece(id, &|p| p.corner = c);
+                                                }
+                                            },
+                                            for c in CornerType::ALL {
+                                                option { value: "{c.label()}", selected: pc.corner == c, "{c.label()}" }
+                                            }
+                                        }
+                                        div {
+                                            label { "Cut " }
+                                            input {
+                                                r#type: "number",
+                                                style: "width: 50px;",
+                                                value: "{pc.cut_quantity}",
+                                                oninput: move |evt| {
+                                                    if let Ok(q) = evt.value().parse::<u32>() {
+                                                        edit_piece(id, &|p| p.cut_quantity = q.max(1));
+                                                    }
+                                                },
+                                            }
+                                            label { " Material " }
+                                            input {
+                                                style: "width: 90px;",
+                                                value: "{pc.material}",
+                                                oninput: move |evt| {
+                                                    let material = evt.value();
+                                                    edit_piece(id, &|p| p.material = material.clone());
+                                                },
+                                            }
+                                        }
+                                        label {
+                                            input {
+                                                r#type: "checkbox",
+                                                checked: pc.mirrored_pairs,
+                                                onchange: move |evt| {
+                                                    let mirrored = evt.checked();
+                                                    edit_piece(id, &|p| p.mirrored_pairs = mirrored);
+                                                },
+                                            }
+                                            " Mirrored pairs"
+                                        }
+                                        h4 { "Grainline" }
+                                        select {
+                                            onchange: move |evt| {
+                                                let grainline = match evt.value().as_str() {
+                                                    "center" => Some(Grainline::Centered { offset: Point2D::new(0.0, 0.0), length: 100.0, angle: 90.0 }),
+                                                    _ => None,
+                                                };
+                                                edit_piece(id, &|p| p.grainline = grainline.clone());
+                                                // بين نقطتين: يبقى خط النسيج فارغاً حتى يختار المستخدم النقطتين
+                                                if evt.value() == "points" {
+                                                    mode.set(CanvasMode::GrainlineStart { piece_id: id });
+                                                } else if matches!(*mode.read(), CanvasMode::GrainlineStart { piece_id } | CanvasMode::GrainlineEnd { piece_id, .. } if piece_id == id) {
+                                                    mode.set(CanvasMode::Select);
+                                                }
+                                            },
+                                            option { value: "none", selected: pc.grainline.is_none() && grain_pick != Some(id), "None" }
+                                            option { value: "center", selected: matches!(pc.grainline, Some(Grainline::Centered { .. })), "Center + length + angle" }
+                                            option { value: "points", selected: matches!(pc.grainline, Some(Grainline::Points { .. })) || grain_pick == Some(id), "Two points" }
+                                        }
+                                        if grain_pick == Some(id) {
+                                            div { class: "stats", "Click the start and end points on the canvas" }
+                                        }
+                                        match pc.grainline.clone() {
+                                            Some(Grainline::Centered { offset, length, angle }) => rsx! {
+                                                div { class: "stats",
+                                                    label { "Length " }
+                                                    input {
+                                                        r#type: "number", style: "width: 60px;", value: "{length}",
+                                                        oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() {
+                                                            edit_piece(id, &|p| p.grainline = Some(Grainline::Centered { offset, length: v, angle }));
+                                                        },
+                                                    }
+                                                    label { " Angle° " }
+                                                    input {
+                                                        r#type: "number", style: "width: 60px;", value: "{angle}",
+                                                        oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() {
+                                                            edit_piece(id, &|p| p.grainline = Some(Grainline::Centered { offset, length, angle: v }));
+                                                        },
+                                                    }
+                                                }
+                                            },
+                                            Some(Grainline::Points { start_id, end_id }) => rsx! {
+                                                div { class: "stats",
+                                                    select {
+                                                        onchange: move |evt| if let Ok(v) = evt.value().parse::<u32>() {
+                                                            edit_piece(id, &|p| p.grainline = Some(Grainline::Points { start_id: v, end_id }));
+                                                        },
+                                                        for pt in pts_snapshot.iter() {
+                                                            option { value: "{pt.metadata.id}", selected: pt.metadata.id == start_id, "{pt.metadata.name}" }
+                                                        }
+                                                    }
+                                                    " → "
+                                                    select {
+                                                        onchange: move |evt| if let Ok(v) = evt.value().parse::<u32>() {
+                                                            edit_piece(id, &|p| p.grainline = Some(Grainline::Points { start_id, end_id: v }));
+                                                        },
+                                                        for pt in pts_snapshot.iter() {
+                                                            option { value: "{pt.metadata.id}", selected: pt.metadata.id == end_id, "{pt.metadata.name}" }
+                                                        }
+                                                    }
+                                                    button {
+                                                        onclick: move |_| mode.set(CanvasMode::GrainlineStart { piece_id: id }),
+                                                        "Pick"
+                                                    }
+                                                }
+                                            },
+                                            None => rsx! {},
+                                        }
+                                        h4 { "Internal paths" }
+                                        for (i, ip) in pc.internal_paths.iter().enumerate() {
+                                            div { key: "ip-{i}", class: "stats",
+                                                {
+                                                    let name = contour_ids.get(&cnt_snapshot, ip.contour_id).map(|c| c.metadata.name.clone()).unwrap_or_default();
+                                                    rsx! { "{name} " }
+                                                }
+                                                label {
+                                                    input {
+                                                        r#type: "checkbox",
+                                                        checked: ip.cut_on_fabric,
+                                                        onchange: move |evt| {
+                                                            let cut = evt.checked();
+                                                            edit_piece(id, &|p| if let Some(ip) = p.internal_paths.get_mut(i) { ip.cut_on_fabric = cut; });
+                                                        },
+                                                    }
+                                                    " cut"
+                                                }
+                                                button {
+                                                    onclick: move |_| edit_piece(id, &|p| { p.internal_paths.remove(i); }),
+                                                    "✕"
+                                                }
+                                            }
+                                        }
+                                        select {
+                                            onchange: move |evt| {
+                                                if let Ok(cid) = evt.value().parse::<u32>() {
+                                                    edit_piece(id, &|p| {
+                                                        if !p.internal_paths.iter().any(|ip| ip.contour_id == cid) {
+                                                            p.internal_paths.push(InternalPath { contour_id: cid, cut_on_fabric: false });
+                                                        }
+                                                    });
+                                                }
+                                            },
+                                            option { value: "", selected: true, "+ Add path…" }
+                                            for c in cnt_snapshot.iter().filter(|c| c.metadata.id != pc.contour_id) {
+                                                option { value: "{c.metadata.id}", "{c.metadata.name}" }
+                                            }
+                                        }
+                                        h4 { "Placements" }
+                                        for (i, pl) in pc.placements.iter().enumerate() {
+                                            div { key: "pl-{i}", class: "stats",
+                                                select {
+                                                    onchange: move |evt| if let Some(k) = PlacementKind::from_label(&evt.value()) {
+                                                        edit_piece(id, &|p| if let Some(pl) = p.placements.get_mut(i) { pl.kind = k; });
+                                                    },
+                                                    for k in PlacementKind::ALL {
+                                                        option { value: "{k.label()}", selected: pl.kind == k, "{k.label()}" }
+                                                    }
+                                                }
+                                                select {
+                                                    onchange: move |evt| if let Ok(v) = evt.value().parse::<u32>() {
+                                                        edit_piece(id, &|p| if let Some(pl) = p.placements.get_mut(i) { pl.point_id = v; });
+                                                    },
+                                                    for pt in pts_snapshot.iter() {
+                                                        option { value: "{pt.metadata.id}", selected: pt.metadata.id == pl.point_id, "{pt.metadata.name}" }
+                                                    }
+                                                }
+                                                input {
+                                                    r#type: "number", style: "width: 45px;", value: "{pl.width}",
+                                                    oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() {
+                                                        edit_piece(id, &|p| if let Some(pl) = p.placements.get_mut(i) { pl.width = v.max(0.0); });
+                                                    },
+                                                }
+                                                input {
+                                                    r#type: "number", style: "width: 45px;", value: "{pl.height}",
+                                                    oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() {
+                                                        edit_piece(id, &|p| if let Some(pl) = p.placements.get_mut(i) { pl.height = v.max(0.0); });
+                                                    },
+                                                }
+                                                input {
+                                                    r#type: "number", style: "width: 45px;", value: "{pl.angle}",
+                                                    oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() {
+                                                        edit_piece(id, &|p| if let Some(pl) = p.placements.get_mut(i) { pl.angle = v; });
+                                                    },
+                                                }
+                                                button {
+                                                    onclick: move |_| edit_piece(id, &|p| { p.placements.remove(i); }),
+                                                    "✕"
+                                                }
+                                            }
+                                        }
+                                        if let Some(first) = pts_snapshot.first().map(|p| p.metadata.id) {
+                                            button {
+                                                onclick: move |_| edit_piece(id, &|p| p.placements.push(PlacementLabel::new(PlacementKind::default(), first))),
+                                                "+ Placement"
+                                            }
+                                        }
+                                        h4 { "Labels" }
+                                        for (title, is_pattern, label) in [("Piece label", false, pc.piece_label.clone()), ("Pattern label", true, pc.pattern_label.clone())] {
+                                            div { class: "stats",
+                                                label {
+                                                    input {
+                                                        r#type: "checkbox",
+                                                        checked: label.is_some(),
+                                                        onchange: move |evt| {
+                                                            let enabled = evt.checked();
+                                                            edit_piece(id, &|p| {
+                                                                let (template, offset) = if is_pattern {
+                                                                    (annotation::DEFAULT_PATTERN_LABEL, Point2D::new(0.0, 40.0))
+                                                                } else {
+                                                                    (annotation::DEFAULT_PIECE_LABEL, Point2D::new(0.0, -40.0))
+                                                                };
+                                                                let slot = if is_pattern { &mut p.pattern_label } else { &mut p.piece_label };
+                                                                *slot = enabled.then(|| TextLabel::new(template, offset));
+                                                            });
+                                                        },
+                                                    }
+                                                    " {title}"
+                                                }
+                                                if let Some(l) = label.as_ref() {
+                                                    textarea {
+                                                        rows: "2",
+                                                        style: "width: 100%;",
+                                                        value: "{l.template}",
+                                                        oninput: move |evt| {
+                                                            let template = evt.value();
+                                                            edit_piece(id, &|p| {
+                                                                let slot = if is_pattern { &mut p.pattern_label } else { &mut p.piece_label };
+                                                                if let Some(l) = slot {
+                                                                    l.template = template.clone();
+                                                                }
+                                                            });
+                                                        },
+                                                    }
+                                                    label { "X " }
+                                                    input {
+                                                        r#type: "number", style: "width: 55px;", value: "{l.offset.x}",
+                                                        oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() {
+                                                            edit_piece(id, &|p| {
+                                                                let slot = if is_pattern { &mut p.pattern_label } else { &mut p.piece_label };
+                                                                if let Some(l) = slot { l.offset.x = v; }
+                                                            });
+                                                        },
+                                                    }
+                                                    label { " Y " }
+                                                    input {
+                                                        r#type: "number", style: "width: 55px;", value: "{l.offset.y}",
+                                                        oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() {
+                                                            edit_piece(id, &|p| {
+                                                                let slot = if is_pattern { &mut p.pattern_label } else { &mut p.piece_label };
+                                                                if let Some(l) = slot { l.offset.y = v; }
+                                                            });
+                                                        },
+                                                    }
+                                                    label { " Font " }
+                                                    input {
+                                                        r#type: "number", style: "width: 45px;", value: "{l.font_size}",
+                                                        oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() {
+                                                            edit_piece(id, &|p| {
+                                                                let slot = if is_pattern { &mut p.pattern_label } else { &mut p.piece_label };
+                                                                if let Some(l) = slot { l.font_size = v.max(1.0); }
+                                                            });
+                                                        },
+                                                    }
+                                                }
+                                            }
+                                        }
+                                        if let Some((_, report)) = contour_ids.get(&contour_reports, pc.contour_id) {
+                                            if !report.closed {
+                                                p { class: "stats", style: "color: #e74c3c;", "Path is not closed: no seam allowance" }
+                                            }
+                                            // إعدادات كل عقدة (بداية كل قطعة في المسار المرتب)
+                                            h4 { "Nodes" }
+                                            for seg in report.segments.iter() {
+                                                {
+                                                    let entity = seg.entity;
+                                                    let node = pc.node(entity).cloned();
+                                                    let width = node.as_ref().and_then(|n| n.width).map(|w| w.to_string()).unwrap_or_default();
+                                                    let corner = node.as_ref().and_then(|n| n.corner);
+                                                    let notch = pc.notch(entity).cloned();
+                                                    rsx! {
+                                                        div { key: "node-{entity.label()}", class: "stats",
+                                                            label { "{entity.label()} " }
+                                                            input {
+                                                                r#type: "number",
+                                                                style: "width: 60px;",
+                                                                placeholder: "{pc.seam_allowance}",
+                                                                value: "{width}",
+                                                                oninput: move |evt| {
+                                                                    let w = evt.value().parse::<f64>().ok().map(|v| v.max(0.0));
+                                                                    edit_piece(id, &|p| p.set_node(entity, w, corner));
+                                                                },
+                                                            }
+                                                            select {
+                                                                onchange: move |evt| {
+                                                                    let c = CornerType::from_label(&evt.value());
+                                                                    edit_piece(id, &|p| {
+                                                                        let w = p.node(entity).and_then(|n| n.width);
+                                                                        p.set_node(entity, w, c);
+                                                                    });
+                                                                },
+                                                                option { value: "", selected: corner.is_none(), "Default" }
+                                                                for c in CornerType::ALL {
+                                                                    option { value: "{c.label()}", selected: corner == Some(c), "{c.label()}" }
+                                                                }
+                                                            }
+                                                            select {
+                                                                onchange: move |evt| {
+                                                                    let kind = NotchType::from_label(&evt.value());
+                                                                    edit_piece(id, &|p| p.set_notch(entity, kind));
+                                                                },
+                                                                option { value: "", selected: notch.is_none(), "No notch" }
+                                                                for k in NotchType::ALL {
+                                                                    option { value: "{k.label()}", selected: notch.as_ref().is_some_and(|n| n.kind == k), "{k.label()}" }
+                                                                }
+                                                            }
+                                                            if let Some(nt) = notch.as_ref() {
+                                                                select {
+                                                                    onchange: move |evt| {
+                                                                        if let Some(a) = NotchAngle::from_label(&evt.value()) {
+                                                                            edit_piece(id, &|p| if let Some(n) = p.notch_mut(entity) { n.angle = a });
+                                                                        }
+                                                                    },
+                                                                    for a in NotchAngle::ALL {
+                                                                        option { value: "{a.label()}", selected: nt.angle == a, "{a.label()}" }
+                                                                    }
+                                                                }
+                                                                input {
+                                                                    r#type: "number",
+                                                                    style: "width: 60px;",
+                                                                    value: "{nt.length}",
+                                                                    oninput: move |evt| {
+                                                                        if let Ok(v) = evt.value().parse::<f64>() {
+                                                                            edit_piece(id, &|p| if let Some(n) = p.notch_mut(entity) { n.length = v.max(0.0) });
+                                                                        }
+                                                                    },
+                                                                }
+                                                            }
+                                                        }
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                    button {
+                                        class: "delete-btn",
+                                        onclick: move |_| delete_items(vec![SelectedItem::Piece(id)]),
+                                        "🗑 Delete Piece"
+                                    }
+                                }
+                            },
+                        }
+                    } else {
+                        {
+                            let (n_pts, n_lns, n_spl, n_bis, n_cnt, n_pcs) = current_selection.counts();
+                            let items = current_selection.items().to_vec();
+                            rsx! {
+                                div {
+                                    p { "Selected: {current_selection.len()} objects" }
+                                    ul { class: "stats",
+                                        li { "Points: {n_pts}" }
+                                        li { "Lines: {n_lns + n_bis}" }
+                                        li { "Splines: {n_spl}" }
+                                        li { "Paths: {n_cnt}" }
+                                        li { "Pieces: {n_pcs}" }
+                                    }
+                                    div { class: "toolbar",
+                                        button {
+                                            onclick: {
+                                                let items = items.clone();
+                                                move |_| mode.set(CanvasMode::OperationSelect { items: items.clone() })
+                                            },
+                                            "🔁 Transform"
+                                        }
+                                        button {
+                                            onclick: {
+                                                let items = items.clone();
+                                                move |_| {
+                                                    // تجميع الخطوط والمنحنيات المختارة في مسار جديد
+                                                    let cid = *next_id.read();
+                                                    let mut contour = VContour::new(cid, &format!("Path{}", cid));
+                                                    contour.entities = items.iter().filter_map(|i| match *i {
+                                                        SelectedItem::Line(id) => Some(EntityRef::Line(id)),
+                                                        SelectedItem::Spline(id) => Some(EntityRef::Spline(id)),
+                                                        SelectedItem::Bisector(id) => Some(EntityRef::Bisector(id)),
+                                                        _ => None,
+                                                    }).collect();
+                                                    if !contour.entities.is_empty() {
+                                                        contours.write().push(contour);
+                                                        next_id.set(cid + 1);
+                                                    }
+                                                }
+                                            },
+                                            "🧩 Make path"
+                                        }
+                                    }
+                                    button {
+                                        class: "delete-btn",
+                                        onclick: move |_| delete_items(items.clone()),
+                                        "🗑 Delete all"
+                                    }
                                 }
                             }
-                        },
-                        SelectedItem::Bisector(id) => rsx! { 
-                            div {
-                                p { "Selected Bisector: B{id}" }
-                                button { 
-                                    class: "delete-btn",
-                                    onclick: move |_| {
-                                        bisectors.write().retain(|b| b.metadata.id != id);
-                                        selected_item.set(SelectedItem::None);
-                                    },
-                                    "🗑 Delete"
+                        }
+                    }
+                }
+
+                div { class: "control-box",
+                    h3 { "Seam matching" }
+                    for pair in pairs_snapshot.iter() {
+                        {
+                            let pair_id = pair.id;
+                            let result = pair.compare(&contour_reports);
+                            rsx! {
+                                div { key: "pair-{pair_id}", class: "stats",
+                                    input {
+                                        style: "width: 120px;",
+                                        value: "{pair.name}",
+                                        oninput: move |evt| {
+                                            let name = evt.value();
+                                            edit_pair(pair_id, &|p| p.name = name.clone());
+                                        },
+                                    }
+                                    button {
+                                        onclick: move |_| { seam_pairs.write().retain(|p| p.id != pair_id); },
+                                        "✕"
+                                    }
+                                    for (is_second, sub_path) in [(false, pair.first.clone()), (true, pair.second.clone())] {
+                                        {
+                                            let entities = contour_ids.get(&ordered_entities, sub_path.contour_id)
+                                                .map(|(_, e)| e.clone())
+                                                .unwrap_or_default();
+                                            rsx! {
+                                                div {
+                                                    select {
+                                                        onchange: {
+                                                            let ordered_entities = ordered_entities.clone();
+                                                            move |evt: Event<FormData>| {
+                                                                let Ok(cid) = evt.value().parse::<u32>() else { return };
+                                                                let Some((_, e)) = contour_index.read().get(&ordered_entities, cid) else { return };
+                                                                let (Some(from), Some(to)) = (e.first().copied(), e.last().copied()) else { return };
+                                                                let whole = SubPath { contour_id: cid, from, to };
+                                                                edit_pair(pair_id, &|p| if is_second { p.second = whole.clone() } else { p.first = whole.clone() });
+                                                            }
+                                                        },
+                                                        for c in cnt_snapshot.iter() {
+                                                            option { value: "{c.metadata.id}", selected: c.metadata.id == sub_path.contour_id, "{c.metadata.name}" }
+                                                        }
+                                                    }
+                                                    for to_end in [false, true] {
+                                                        select {
+                                                            onchange: {
+                                                                let entities = entities.clone();
+                                                                move |evt: Event<FormData>| {
+                                                                    let Some(e) = entities.iter().copied().find(|e| e.label() == evt.value()) else { return };
+                                                                    edit_pair(pair_id, &|p| {
+                                                                        let side = if is_second { &mut p.second } else { &mut p.first };
+                                                                        if to_end { side.to = e } else { side.from = e }
+                                                                    });
+                                                                }
+                                                            },
+                                                            for e in entities.iter() {
+                                                                option {
+                                                                    value: "{e.label()}",
+                                                                    selected: *e == if to_end { sub_path.to } else { sub_path.from },
+                                                                    "{e.label()}"
+                                                                }
+                                                            }
+                                                        }
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                    if let Some(r) = result {
+                                        p {
+                                            style: if r.difference.abs() > contour::CONTOUR_TOLERANCE { "color: #f39c12;" } else { "color: #2ecc71;" },
+                                            "{r.first_length:.2} / {r.second_length:.2} — Δ {r.difference:+.2} ({r.ease_percent:+.1}%)"
+                                        }
+                                    } else {
+                                        p { style: "color: #e74c3c;", "Invalid sub-path" }
+                                    }
                                 }
                             }
-                        },
-                        SelectedItem::Contour(id) => rsx! { 
-                            div {
-                                p { "Selected Path: {id}" }
-                                if let Some(c) = cnt_snapshot.iter().find(|c| c.metadata.id == id) {
-                                    p { class: "stats", "Entities: {c.entities.len()}" }
-                                }
-                                button { 
-                                    class: "delete-btn",
+                        }
+                    }
+                    if let [(a, ra), (b, rb), ..] = contour_reports.as_slice() {
+                        {
+                            let first = SubPath::whole(*a, ra);
+                            let second = SubPath::whole(*b, rb);
+                            rsx! {
+                                button {
                                     onclick: move |_| {
-                                        contours.write().retain(|c| c.metadata.id != id);
-                                        selected_item.set(SelectedItem::None);
+                                        if let (Some(first), Some(second)) = (first.clone(), second.clone()) {
+                                            let id = *next_id.read();
+                                            seam_pairs.write().push(SeamPair { id, name: format!("Seam{}", id), first, second });
+                                            next_id.set(id + 1);
+                                        }
                                     },
-                                    "🗑 Delete Path"
+                                    "+ Seam pair"
                                 }
                             }
-                        },
+                        }
                     }
                 }
 
                 div { class: "control-box",
                     h3 { "Project" }
+                    label { "Pattern " }
+                    input {
+                        value: "{pattern_snapshot.name}",
+                        oninput: move |evt| pattern_info.write().name = evt.value(),
+                    }
+                    label { " Size " }
+                    input {
+                        style: "width: 60px;",
+                        value: "{pattern_snapshot.size}",
+                        oninput: move |evt| pattern_info.write().size = evt.value(),
+                    }
+                    label { " Tolerance (mm) " }
+                    input {
+                        r#type: "number", step: "0.01", min: "0",
+                        style: "width: 60px;",
+                        title: "Distances below this are treated as zero when checking tools for degenerate input",
+                        value: "{tolerance}",
+                        oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() && v >= 0.0 { doc_tolerance.set(Tolerance(v)) },
+                    }
                     button { 
                         class: "action-btn",
                         onclick: move |_| {
-                            let pts = points.read().clone();
-                            let lns = lines.read().clone();
-                            let spls = splines.read().clone();
-                            let bis = bisectors.read().clone();
-                            let cnts = contours.read().clone();
-                            let nid = *next_id.read();
-                            
+                            let data = current_project();
                             spawn(async move {
                                 if let Some(path) = rfd::AsyncFileDialog::new()
                                     .set_file_name("project.json")
                                     .add_filter("JSON", &["json"])
                                     .save_file()
                                     .await {
-                                        let data = ProjectData {
-                                            points: pts,
-                                            lines: lns,
-                                            splines: spls,
-                                            bisectors: bis,
-                                            contours: cnts,
-                                            next_id: nid,
-                                        };
-                                        if let Ok(json) = serde_json::to_string_pretty(&data) {
-                                            let _ = fs::write(path.path(), json);
+                                        let result = data.save(path.path());
+                                        if result.is_ok() {
+                                            saved_hash.set(Some(content_hash(&data)));
+                                            autosaved_hash.set(None);
+                                            // نسخة الحفظ التلقائي لم تعد لازمة (النسخة المحجوزة لجلسة سابقة تبقى حتى يُقرر مصيرها)
+                                            if let Some(store) = recovery_store.read().as_ref()
+                                                && let Err(e) = store.clear() {
+                                                notify(NoticeLevel::Warning, format!("Could not remove the recovery file: {}", e));
+                                            }
                                         }
+                                        notify_result(result.map(|_| format!("Saved {}", path.path().display())));
                                 }
                             });
                         },
@@ -262,21 +1626,25 @@ fn App() -> Element {
                         class: "action-btn",
                         onclick: move |_| {
                             spawn(async move {
-                                if let Some(path) = rfd::AsyncFileDialog::new()
-                                    .add_filter("JSON", &["json"])
+                                let Some(path) = rfd::AsyncFileDialog::new()
+                                    .add_filter("Project", &["json", "val"])
                                     .pick_file()
-                                    .await {
-                                        if let Ok(json) = fs::read_to_string(path.path()) {
-                                            if let Ok(data) = serde_json::from_str::<ProjectData>(&json) {
-                                                points.set(data.points);
-                                                lines.set(data.lines);
-                                                splines.set(data.splines);
-                                                bisectors.set(data.bisectors);
-                                                contours.set(data.contours);
-                                                next_id.set(data.next_id);
-                                                selected_item.set(SelectedItem::None);
-                                            }
+                                    .await else { return };
+                                match ProjectData::load(path.path()) {
+                                    Ok((data, warnings)) => {
+                                        for w in warnings {
+                                            notify(NoticeLevel::Warning, w);
                                         }
+                                        // المشروع يُفتح حتى مع أخطاء الفحص ليتمكن المستخدم من إصلاحها
+                                        if let Some(e) = project::validation_error(&data.validate()) {
+                                            notify(NoticeLevel::Warning, format!("Loaded with problems: {}", e));
+                                        }
+                                        saved_hash.set(Some(content_hash(&data)));
+                                        autosaved_hash.set(None);
+                                        apply_project(data);
+                                        notify(NoticeLevel::Info, format!("Loaded {}", path.path().display()));
+                                    }
+                                    Err(e) => notify(NoticeLevel::Error, e.to_string()),
                                 }
                             });
                         },
@@ -284,36 +1652,553 @@ fn App() -> Element {
                     }
                 }
 
+                div { class: "control-box",
+                    h3 { "Script" }
+                    textarea {
+                        class: "script-editor",
+                        rows: "8",
+                        spellcheck: "false",
+                        placeholder: "let a = point(\"A\", 0, 0);\nlet b = polar(\"B\", a, m(\"bust\") / 4, 0);\nline(a, b);",
+                        value: "{script_source}",
+                        oninput: move |evt| script_source.set(evt.value()),
+                    }
+                    button {
+                        class: "action-btn",
+                        onclick: move |_| {
+                            // السكربت يعمل على نسخة ولا تُطبق النتيجة إلا عند النجاح
+                            let mut data = current_project();
+                            let values = data.measurements.values(None, None);
+                            let result = script::run_script(&mut data, &script_source.read(), &values);
+                            if result.is_ok() {
+                                apply_project(data);
+                            }
+                            script_result.set(Some(result));
+                        },
+                        "▶ Run"
+                    }
+                    if let Some(Ok(report)) = &script_snapshot {
+                        div { class: "script-output",
+                            for line in report.log.iter() {
+                                div { "{line}" }
+                            }
+                            div { "Created {report.created.len()} object(s)" }
+                        }
+                    }
+                    if let Some(Err(e)) = &script_snapshot {
+                        div { class: "script-output script-error", "{e}" }
+                    }
+                }
+
+                div { class: "control-box",
+                    h3 { "Export" }
+                    select {
+                        onchange: move |evt| if let Some(c) = ExportContent::from_label(&evt.value()) { export_content.set(c) },
+                        for c in ExportContent::ALL {
+                            option { value: "{c.label()}", selected: *export_content.read() == c, "{c.label()}" }
+                        }
+                    }
+                    div { class: "toolbar",
+                        button {
+                            onclick: move |_| {
+                                let opts = ExportOptions { content: *export_content.read(), ..ExportOptions::default() };
+                                let svg = export::svg::write_svg(&build_scene(opts.content), &opts);
+                                spawn(async move {
+                                    if let Some(path) = rfd::AsyncFileDialog::new()
+                                        .set_file_name("pattern.svg")
+                                        .add_filter("SVG", &["svg"])
+                                        .save_file()
+                                        .await {
+                                            notify_result(write_output(path.path(), svg));
+                                    }
+                                });
+                            },
+                            "🖼 SVG"
+                        }
+                        button {
+                            onclick: move |_| {
+                                let opts = ExportOptions { content: *export_content.read(), ..ExportOptions::default() };
+                                let dxf_opts = DxfOptions { spline_mode: *dxf_splines.read(), ..DxfOptions::default() };
+                                let dxf = export::dxf::write_dxf(&build_scene(opts.content), &opts, &dxf_opts);
+                                spawn(async move {
+                                    if let Some(path) = rfd::AsyncFileDialog::new()
+                                        .set_file_name("pattern.dxf")
+                                        .add_filter("DXF (AAMA)", &["dxf"])
+                                        .save_file()
+                                        .await {
+                                            notify_result(write_output(path.path(), dxf));
+                                    }
+                                });
+                            },
+                            "📐 DXF"
+                        }
+                        button {
+                            onclick: move |_| {
+                                let opts = ExportOptions { content: *export_content.read(), ..ExportOptions::default() };
+                                let pdf = export::pdf::write_pdf(&build_scene(opts.content), &opts, &pdf_options.read());
+                                spawn(async move {
+                                    if let Some(path) = rfd::AsyncFileDialog::new()
+                                        .set_file_name("pattern.pdf")
+                                        .add_filter("PDF", &["pdf"])
+                                        .save_file()
+                                        .await {
+                                            notify_result(write_output(path.path(), pdf));
+                                    }
+                                });
+                            },
+                            "🖨 PDF"
+                        }
+                        button {
+                            onclick: move |_| {
+                                let opts = ExportOptions { content: *export_content.read(), ..ExportOptions::default() };
+                                let Some(png) = export::png::render_png(&build_scene(opts.content), &opts, &png_options.read()) else {
+                                    notify(NoticeLevel::Warning, "Nothing to render, or the image is too large for this DPI".to_string());
+                                    return;
+                                };
+                                spawn(async move {
+                                    if let Some(path) = rfd::AsyncFileDialog::new()
+                                        .set_file_name("pattern.png")
+                                        .add_filter("PNG", &["png"])
+                                        .save_file()
+                                        .await {
+                                            notify_result(write_output(path.path(), png));
+                                    }
+                                });
+                            },
+                            "🖼 PNG"
+                        }
+                    }
+                    label { "DXF curves: " }
+                    select {
+                        onchange: move |evt| if let Some(m) = SplineMode::from_label(&evt.value()) { dxf_splines.set(m) },
+                        for m in SplineMode::ALL {
+                            option { value: "{m.label()}", selected: *dxf_splines.read() == m, "{m.label()}" }
+                        }
+                    }
+                    div {
+                        label {
+                            input {
+                                r#type: "checkbox",
+                                checked: pdf_options.read().tiled,
+                                onchange: move |evt| pdf_options.write().tiled = evt.checked(),
+                            }
+                            " Tile PDF on "
+                        }
+                        select {
+                            onchange: move |evt| if let Some(p) = PaperSize::from_label(&evt.value()) { pdf_options.write().paper = p },
+                            for p in PaperSize::ALL {
+                                option { value: "{p.label()}", selected: pdf_options.read().paper == p, "{p.label()}" }
+                            }
+                        }
+                    }
+                    div {
+                        label { "Overlap (mm): " }
+                        input {
+                            r#type: "number",
+                            min: "0",
+                            max: "{pdf_options.read().max_overlap_mm()}",
+                            value: "{pdf_options.read().overlap_mm}",
+                            // التداخل يجب أن يبقى أصغر من المساحة المطبوعة للصفحة
+                            oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() && v >= 0.0 && v <= pdf_options.read().max_overlap_mm() {
+                                pdf_options.write().overlap_mm = v;
+                            },
+                        }
+                    }
+                    div {
+                        label { "PNG DPI: " }
+                        input {
+                            r#type: "number",
+                            min: "1",
+                            style: "width: 60px;",
+                            value: "{png_options.read().dpi}",
+                            oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() && v > 0.0 {
+                                png_options.write().dpi = v;
+                            },
+                        }
+                        label {
+                            input {
+                                r#type: "checkbox",
+                                checked: png_region.is_some(),
+                                // المنطقة تبدأ بمساحة الكانفاس المعروضة ثم تُعدّل من الحقول
+                                onchange: move |evt| png_options.write().region = evt.checked()
+                                    .then(|| Bounds::new(Point2D::new(0.0, 0.0), Point2D::new(1000.0, 1000.0))),
+                            }
+                            " Region only"
+                        }
+                    }
+                    if let Some(r) = png_region {
+                        div {
+                            label { "Region x0, y0, x1, y1: " }
+                            for (i, v) in [r.min.x, r.min.y, r.max.x, r.max.y].into_iter().enumerate() {
+                                input {
+                                    key: "{i}",
+                                    r#type: "number",
+                                    style: "width: 60px;",
+                                    value: "{v}",
+                                    oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>()
+                                        && let Some(r) = png_options.write().region.as_mut() {
+                                        match i {
+                                            0 => r.min.x = v,
+                                            1 => r.min.y = v,
+                                            2 => r.max.x = v,
+                                            _ => r.max.y = v,
+                                        }
+                                    },
+                                }
+                            }
+                        }
+                    }
+                }
+
+                div { class: "control-box",
+                    h3 { "Layout" }
+                    div {
+                        label { "Fabric width (mm): " }
+                        input {
+                            r#type: "number",
+                            style: "width: 70px;",
+                            value: "{layout_opts_snapshot.fabric_width}",
+                            oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() && v > 0.0 {
+                                layout_options.write().fabric_width = v;
+                            },
+                        }
+                    }
+                    div {
+                        label { "Max length (0 = unlimited): " }
+                        input {
+                            r#type: "number",
+                            style: "width: 70px;",
+                            value: "{layout_opts_snapshot.max_length.unwrap_or(0.0)}",
+                            oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() {
+                                layout_options.write().max_length = if v > 0.0 { Some(v) } else { None };
+                            },
+                        }
+                    }
+                    div {
+                        label { "Gap: " }
+                        input {
+                            r#type: "number",
+                            style: "width: 50px;",
+                            value: "{layout_opts_snapshot.gap}",
+                            oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() && v >= 0.0 {
+                                layout_options.write().gap = v;
+                            },
+                        }
+                        label { " Rotation: " }
+                        select {
+                            onchange: move |evt| if let Some(r) = RotationStep::from_label(&evt.value()) { layout_options.write().rotation = r },
+                            for r in RotationStep::ALL {
+                                option { value: "{r.label()}", selected: layout_opts_snapshot.rotation == r, "{r.label()}" }
+                            }
+                        }
+                    }
+                    div {
+                        label { "Seed: " }
+                        input {
+                            r#type: "number",
+                            style: "width: 60px;",
+                            value: "{layout_opts_snapshot.seed}",
+                            oninput: move |evt| if let Ok(v) = evt.value().parse::<u64>() {
+                                layout_options.write().seed = v;
+                            },
+                        }
+                        label { " Attempts: " }
+                        input {
+                            r#type: "number",
+                            style: "width: 50px;",
+                            value: "{layout_opts_snapshot.attempts}",
+                            oninput: move |evt| if let Ok(v) = evt.value().parse::<usize>() {
+                                layout_options.write().attempts = v.max(1);
+                            },
+                        }
+                    }
+                    div { class: "toolbar",
+                        button {
+                            onclick: move |_| {
+                                let geos = build_scene(ExportContent::PiecesOnly).pieces;
+                                let result = layout::nest(&geos, &layout_options.read());
+                                layout.set(result);
+                                layout_selected.set(None);
+                                layout_view.set(true);
+                            },
+                            "▦ Nest"
+                        }
+                        button {
+                            onclick: move |_| { let v = *layout_view.read(); layout_view.set(!v); },
+                            if current_layout_view { "✏ Drafting" } else { "▦ Layout view" }
+                        }
+                    }
+                    div {
+                        label { "Plotter units/mm: " }
+                        input {
+                            r#type: "number",
+                            style: "width: 50px;",
+                            value: "{hpgl_options.read().units_per_mm}",
+                            oninput: move |evt| if let Ok(v) = evt.value().parse::<f64>() && v > 0.0 {
+                                hpgl_options.write().units_per_mm = v;
+                            },
+                        }
+                    }
+                    div {
+                        label { "Pens: cut " }
+                        input {
+                            r#type: "number", min: "1", style: "width: 35px;",
+                            value: "{hpgl_options.read().cut_pen}",
+                            oninput: move |evt| if let Ok(v) = evt.value().parse::<u8>() && v >= 1 { hpgl_options.write().cut_pen = v },
+                        }
+                        label { " seam " }
+                        input {
+                            r#type: "number", min: "1", style: "width: 35px;",
+                            value: "{hpgl_options.read().seam_pen}",
+                            oninput: move |evt| if let Ok(v) = evt.value().parse::<u8>() && v >= 1 { hpgl_options.write().seam_pen = v },
+                        }
+                        label { " marks " }
+                        input {
+                            r#type: "number", min: "1", style: "width: 35px;",
+                            value: "{hpgl_options.read().mark_pen}",
+                            oninput: move |evt| if let Ok(v) = evt.value().parse::<u8>() && v >= 1 { hpgl_options.write().mark_pen = v },
+                        }
+                        label { " text " }
+                        input {
+                            r#type: "number", min: "1", style: "width: 35px;",
+                            value: "{hpgl_options.read().text_pen}",
+                            oninput: move |evt| if let Ok(v) = evt.value().parse::<u8>() && v >= 1 { hpgl_options.write().text_pen = v },
+                        }
+                    }
+                    if !layout_snapshot.placements.is_empty() {
+                        div { class: "toolbar",
+                            button {
+                                onclick: move |_| {
+                                    let opts = ExportOptions::default();
+                                    let hp = *hpgl_options.read();
+                                    let geos = build_pieces(Some(CurveResolution::Tolerance(hp.curve_tolerance(&opts))));
+                                    let lay = layout.read().clone();
+                                    let plt = match export::hpgl::write_hpgl(&lay.placed_pieces(&geos), lay.fabric_width, &opts, &hp) {
+                                        Ok(plt) => plt,
+                                        Err(e) => {
+                                            notify(NoticeLevel::Error, format!("HPGL export failed: {}", e));
+                                            return;
+                                        }
+                                    };
+                                    spawn(async move {
+                                        if let Some(path) = rfd::AsyncFileDialog::new()
+                                            .set_file_name("layout.plt")
+                                            .add_filter("HPGL", &["plt", "hpgl"])
+                                            .save_file()
+                                            .await {
+                                                notify_result(write_output(path.path(), plt));
+                                        }
+                                    });
+                                },
+                                "🖊 HPGL"
+                            }
+                        }
+                    }
+                    if !layout_snapshot.placements.is_empty() {
+                        p { class: "stats",
+                            "Length: {layout_length:.1} mm · Efficiency: {layout_efficiency * 100.0:.1}%"
+                        }
+                    }
+                    if !layout_snapshot.unplaced.is_empty() {
+                        p { style: "color: #e74c3c; font-size: 0.85em;",
+                            "{layout_snapshot.unplaced.len()} piece copies did not fit on the fabric"
+                        }
+                    }
+                }
+
                 h3 { "Entities" }
                 ul {
                     li { "Points: {pts_snapshot.len()}" }
                     li { "Lines: {lns_snapshot.len() + bis_snapshot.len()}" }
                     li { "Splines: {spl_snapshot.len()}" }
                     li { "Paths: {cnt_snapshot.len()}" }
+                    li { "Operations: {ops_snapshot.len()}" }
+                    li { "Pieces: {pcs_snapshot.len()}" }
+                }
+                if !pcs_snapshot.is_empty() {
+                    h3 { "Pieces" }
+                    ul {
+                        for pc in pcs_snapshot.iter() {
+                            {
+                                let pid = pc.metadata.id;
+                                let is_selected = current_selection.contains(&SelectedItem::Piece(pid));
+                                rsx! {
+                                    li {
+                                        key: "pc-item-{pid}",
+                                        style: if is_selected { "cursor: pointer; color: #f1c40f;" } else { "cursor: pointer;" },
+                                        onclick: move |evt| select_item(SelectedItem::Piece(pid), evt.modifiers().shift()),
+                                        "{pc.metadata.name}"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                if !cnt_snapshot.is_empty() {
+                    h3 { "Paths" }
+                    ul {
+                        for c in cnt_snapshot.iter() {
+                            {
+                                let cid = c.metadata.id;
+                                let is_selected = current_selection.contains(&SelectedItem::Contour(cid));
+                                rsx! {
+                                    li {
+                                        key: "cnt-item-{cid}",
+                                        style: if is_selected { "cursor: pointer; color: #f1c40f;" } else { "cursor: pointer;" },
+                                        onclick: move |evt| select_item(SelectedItem::Contour(cid), evt.modifiers().shift()),
+                                        "{c.metadata.name}"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            // محرر التخطيط: سحب القطع وتدويرها وقلبها مع كشف التداخل
+            if current_layout_view {
+                div { id: "layout-view",
+                    onmousedown: {
+                        let geos = layout_pieces.clone();
+                        move |evt: Event<MouseData>| {
+                            let coords = evt.element_coordinates();
+                            let at = to_layout(coords.x, coords.y);
+                            let hit = layout.read().hit(&geos, &at);
+                            layout_selected.set(hit);
+                            layout_drag.set(hit.map(|_| at));
+                        }
+                    },
+                    onmousemove: move |evt| {
+                        let (Some(last), Some(i)) = (*layout_drag.read(), *layout_selected.read()) else { return };
+                        let coords = evt.element_coordinates();
+                        let at = to_layout(coords.x, coords.y);
+                        if let Some(pl) = layout.write().placements.get_mut(i) {
+                            pl.offset = Point2D::new(pl.offset.x + at.x - last.x, pl.offset.y + at.y - last.y);
+                        }
+                        layout_drag.set(Some(at));
+                    },
+                    onmouseup: move |_| layout_drag.set(None),
+
+                    div { class: "layout-toolbar",
+                        onmousedown: move |evt| evt.stop_propagation(),
+                        span { "Utilization: {layout_efficiency * 100.0:.1}% · Length: {layout_length:.1} mm" }
+                        if !layout_conflicts.is_empty() {
+                            span { style: "color: #e74c3c; margin-left: 10px;",
+                                "⚠ {layout_conflicts.len()} overlapping or off fabric"
+                            }
+                        }
+                        if let Some((i, copy, selected_geo)) = selected_placement.clone() {
+                            span { style: "margin-left: 10px;", "{selected_geo.name} #{copy + 1}" }
+                            if let Some(step) = layout_opts_snapshot.rotation.step() {
+                                button {
+                                    onclick: {
+                                        let selected_geo = selected_geo.clone();
+                                        move |_| edit_placement(i, &selected_geo, &|pl, geo| pl.rotate_by(geo, step))
+                                    },
+                                    "⟳ Rotate {step}°"
+                                }
+                            }
+                            button {
+                                onclick: {
+                                    move |_| edit_placement(i, &selected_geo, &|pl, geo| pl.flip(geo))
+                                },
+                                "⇋ Flip"
+                            }
+                        }
+                    }
+
+                    svg {
+                        id: "layout-canvas",
+                        width: "100%", height: "100%",
+                        view_box: "{sheet_view_box}",
+                        preserve_aspect_ratio: "xMidYMid meet",
+                        rect {
+                            x: "0", y: "0",
+                            width: "{sheet_length}", height: "{layout_snapshot.fabric_width}",
+                            fill: "white", stroke: "#7f8c8d", stroke_width: "2",
+                        }
+                        for (i, pl) in layout_snapshot.placements.iter().enumerate() {
+                            {
+                                let Some(geo) = layout_piece_ids.get(&layout_pieces, pl.piece_id) else {
+                                    return rsx! { "" };
+                                };
+                                let outline = pl.outline(geo);
+                                let center = Bounds::from_points(&outline).map_or(Point2D::new(0.0, 0.0), |b| b.center());
+                                let d = piece::polygon_path(&outline);
+                                let name = if pl.mirrored { format!("{} (M)", geo.name) } else { geo.name.clone() };
+                                let class = if layout_selected() == Some(i) { "selected" } else { "" };
+                                let fill = if layout_conflicts.contains(&i) { "rgba(231, 76, 60, 0.4)" } else { "rgba(52, 152, 219, 0.25)" };
+                                rsx! {
+                                    g { key: "lay-{i}",
+                                        path { class: "{class}", d: "{d}", fill: "{fill}", stroke: palette::INK, stroke_width: "2" }
+                                        text {
+                                            x: "{center.x}", y: "{center.y}",
+                                            text_anchor: "middle", font_size: "24", fill: palette::INK,
+                                            "{name}"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
                 }
             }
 
             div { 
                 id: "viewport",
+                style: if current_layout_view { "display: none;" } else if current_hover.is_some() && current_band.is_none() { "cursor: pointer;" } else if current_error_hover.is_some() { "cursor: help;" } else { "" },
+                onmousedown: move |evt| {
+                    let coords = evt.element_coordinates();
+                    let (at, tolerance) = to_model(coords.x, coords.y);
+                    let additive = evt.modifiers().shift();
+                    let hit = hit_test::hit_first(&at, tolerance, &spatial_index.read());
+                    match hit {
+                        Some(SelectedItem::Point(pid)) => on_point_click(pid, additive),
+                        Some(item) => on_entity_click(item, additive),
+                        None => on_background_click(at, additive),
+                    }
+                },
                 onmousemove: move |evt| {
+                    let coords = evt.element_coordinates();
+                    let (at, tolerance) = to_model(coords.x, coords.y);
+                    if let Some(b) = band.write().as_mut() {
+                        b.end = at;
+                    }
                     if let Some(pid) = *dragging_point_id.read() {
-                        let coords = evt.element_coordinates();
-                        let (elem_w, elem_h) = *svg_elem_size.read();
-                        let mapper = CoordMapper {
-                            viewbox: SvgViewBox { min_x: 0.0, min_y: 0.0, width: 1000.0, height: 1000.0 },
-                            preserve_aspect_ratio: AspectRatioMode::Meet,
-                        };
-                        let (svg_x, svg_y) = mapper.to_svg_space(coords.x, coords.y, elem_w, elem_h);
-                        
+                        // الموقع من الفهرس قبل قفل النقاط للكتابة (الترتيب لا يتغير أثناء السحب)
+                        let slot = spatial_index.peek().points.position(pid);
                         let mut points_lock = points.write();
-                        if let Some(p) = points_lock.iter_mut().find(|p| p.metadata.id == pid) {
-                            p.coords.x = svg_x;
-                            p.coords.y = svg_y;
+                        if let Some(p) = slot.and_then(|i| points_lock.get_mut(i)).filter(|p| p.metadata.id == pid) {
+                            p.coords = at;
                         }
+                        // تحديث النسخ المشتقة لتتبع أصولها
+                        operation::recalculate(&operations.read(), &mut points_lock, doc_tolerance.read().0);
+                        return;
+                    }
+                    // تحديث العنصر تحت المؤشر فقط عند تغيره لتجنب إعادة الرسم مع كل حركة
+                    let hit = hit_test::hit_first(&at, tolerance, &spatial_index.read());
+                    if *hovered.peek() != hit {
+                        hovered.set(hit);
+                    }
+                    let reach = ERROR_MARKER_RADIUS.max(tolerance);
+                    let error_hit = error_spots.iter()
+                        .find(|(_, spot)| spot.distance_to(&at) <= reach)
+                        .map(|(key, _)| key.clone());
+                    if *hovered_error.peek() != error_hit {
+                        hovered_error.set(error_hit);
                     }
                 },
-                onmouseup: move |_| {
+                onmouseup: move |evt| {
                     dragging_point_id.set(None);
+                    // إنهاء مستطيل الاختيار
+                    let finished = band.write().take();
+                    if let Some(b) = finished {
+                        let picked = selection::band_select(&b, &spatial_index.read());
+                        if evt.modifiers().shift() {
+                            selection.write().extend(picked);
+                        } else {
+                            selection.write().replace(picked);
+                        }
+                    }
                 },
                 svg {
                     id: "main-canvas",
@@ -328,36 +2213,102 @@ fn App() -> Element {
 
                     rect {
                         width: "100%", height: "100%", fill: "url(#grid)",
-                        onmousedown: move |evt| {
-                            if *mode.read() == CanvasMode::PlacePoint {
-                                let coords = evt.element_coordinates();
-                                let (elem_w, elem_h) = *svg_elem_size.read();
-                                let mapper = CoordMapper {
-                                    viewbox: SvgViewBox { min_x: 0.0, min_y: 0.0, width: 1000.0, height: 1000.0 },
-                                    preserve_aspect_ratio: AspectRatioMode::Meet,
-                                };
-                                let (svg_x, svg_y) = mapper.to_svg_space(coords.x, coords.y, elem_w, elem_h);
-                                
-                                let id = *next_id.read();
-                                points.write().push(VPoint::new(id, &format!("P{}", id), svg_x, svg_y));
-                                next_id.set(id + 1);
+                    }
+
+                    // رسم القطع: هامش الخياطة متقطع حول خط الخياطة
+                    for pc in pcs_snapshot.iter() {
+                        {
+                            let pid = pc.metadata.id;
+                            let class = if current_selection.contains(&SelectedItem::Piece(pid)) { "selected" } else { "" };
+                            let report = contour_ids.get(&contour_reports, pc.contour_id).map(|(_, r)| r);
+                            let seam_d = report.map(|r| r.to_svg_path()).unwrap_or_default();
+                            let Some(geo) = PieceGeometry::build(pc, &contour_reports, &pts_snapshot, &pattern_snapshot) else {
+                                return rsx! { "" };
+                            };
+                            let allowance_d = geo.allowance.as_deref().map(piece::polygon_path).unwrap_or_default();
+                            let notches_d = piece::polylines_path(&geo.notches);
+                            let grain_d = piece::polylines_path(&geo.grainline);
+                            let cut_d = piece::polylines_path(&geo.cut_paths);
+                            let draw_d = piece::polylines_path(&geo.draw_paths);
+                            let placements_d = piece::polylines_path(&geo.placements);
+                            rsx! {
+                                g { key: "pc-{pid}",
+                                    path {
+                                        class: "{class}",
+                                        d: "{allowance_d}",
+                                        fill: "rgba(189, 195, 199, 0.35)",
+                                        stroke: palette::ALLOWANCE,
+                                        stroke_width: "2",
+                                        stroke_dasharray: "8,4",
+                                    }
+                                    path {
+                                        d: "{seam_d}",
+                                        fill: "rgba(255, 255, 255, 0.6)",
+                                        stroke: palette::INK,
+                                        stroke_width: "2",
+                                    }
+                                    path {
+                                        d: "{notches_d}",
+                                        fill: "none",
+                                        stroke: palette::NOTCH,
+                                        stroke_width: "2",
+                                    }
+                                    path {
+                                        d: "{grain_d}",
+                                        fill: "none",
+                                        stroke: palette::INK,
+                                        stroke_width: "2",
+                                    }
+                                    path {
+                                        d: "{cut_d}",
+                                        fill: "none",
+                                        stroke: palette::INK,
+                                        stroke_width: "2",
+                                    }
+                                    path {
+                                        d: "{draw_d}",
+                                        fill: "none",
+                                        stroke: palette::ALLOWANCE,
+                                        stroke_width: "1.5",
+                                        stroke_dasharray: "4,3",
+                                    }
+                                    path {
+                                        d: "{placements_d}",
+                                        fill: "none",
+                                        stroke: palette::PLACEMENT,
+                                        stroke_width: "1.5",
+                                    }
+                                    for (i, label) in geo.labels.iter().enumerate() {
+                                        text {
+                                            key: "pc-lbl-{pid}-{i}",
+                                            x: "{label.at.x}", y: "{label.at.y}",
+                                            font_size: "{label.font_size}",
+                                            text_anchor: "middle",
+                                            fill: palette::INK,
+                                            for (j, line) in label.lines.iter().enumerate() {
+                                                tspan { x: "{label.at.x}", dy: if j == 0 { "0" } else { "1.2em" }, "{line}" }
+                                            }
+                                        }
+                                    }
+                                }
                             }
-                            selected_item.set(SelectedItem::None);
                         }
                     }
 
                     // رسم المنحنيات (Splines)
-                    for spline in spl_snapshot.iter() {
+                    for spline in spl_snapshot.iter().filter(|s| visible.contains(&SelectedItem::Spline(s.metadata.id)) && !degenerate_items.contains(&SelectedItem::Spline(s.metadata.id))) {
                         {
                             let sid = spline.metadata.id;
-                            let is_selected = matches!(current_selection, SelectedItem::Spline(id) if id == sid);
+                            let is_selected = current_selection.contains(&SelectedItem::Spline(sid))
+                                || pending_items.contains(&SelectedItem::Spline(sid));
+                            let class = if is_selected { "selected" } else if current_hover == Some(SelectedItem::Spline(sid)) { "hovered" } else { "" };
                             // تمييز إذا كان جزء من كونتور
-                            let is_in_contour = cnt_snapshot.iter().any(|c| c.entities.contains(&EntityRef::Spline(sid)));
+                            let is_in_contour = contour_members.contains(&EntityRef::Spline(sid));
                             
-                            let p1 = pts_snapshot.iter().find(|p| p.metadata.id == spline.p1_id);
-                            let p2 = pts_snapshot.iter().find(|p| p.metadata.id == spline.p2_id);
-                            let p3 = pts_snapshot.iter().find(|p| p.metadata.id == spline.p3_id);
-                            let p4 = pts_snapshot.iter().find(|p| p.metadata.id == spline.p4_id);
+                            let p1 = index_snapshot.point(&pts_snapshot, spline.p1_id);
+                            let p2 = index_snapshot.point(&pts_snapshot, spline.p2_id);
+                            let p3 = index_snapshot.point(&pts_snapshot, spline.p3_id);
+                            let p4 = index_snapshot.point(&pts_snapshot, spline.p4_id);
                             
                             if let (Some(s), Some(c1), Some(c2), Some(e)) = (p1, p2, p3, p4) {
                                 let d_path = format!("M {} {} C {} {}, {} {}, {} {}", 
@@ -365,21 +2316,11 @@ fn App() -> Element {
                                 rsx! {
                                     path { 
                                         key: "spl-{sid}",
-                                        class: if is_selected { "selected" } else { "" },
+                                        class: "{class}",
                                         d: "{d_path}",
-                                        stroke: if is_in_contour { "#f39c12" } else { "#2ecc71" }, 
+                                        stroke: if is_in_contour { palette::CONTOUR } else { palette::SPLINE }, 
                                         stroke_width: if is_in_contour { "5" } else { "3" },
                                         fill: "none",
-                                        onmousedown: move |evt| {
-                                            evt.stop_propagation();
-                                            if let CanvasMode::ContourCreation { active_contour_id } = *mode.read() {
-                                                if let Some(c) = contours.write().iter_mut().find(|c| c.metadata.id == active_contour_id) {
-                                                    c.entities.push(EntityRef::Spline(sid));
-                                                }
-                                            } else {
-                                                selected_item.set(SelectedItem::Spline(sid));
-                                            }
-                                        }
                                     }
                                 }
                             } else { rsx! { "" } }
@@ -387,34 +2328,26 @@ fn App() -> Element {
                     }
 
                     // رسم الخطوط العادية
-                    for line in lns_snapshot.iter() {
+                    for line in lns_snapshot.iter().filter(|l| visible.contains(&SelectedItem::Line(l.metadata.id)) && !degenerate_items.contains(&SelectedItem::Line(l.metadata.id))) {
                         {
                             let lid = line.metadata.id;
-                            let is_selected = matches!(current_selection, SelectedItem::Line(id) if id == lid);
-                            let is_in_contour = cnt_snapshot.iter().any(|c| c.entities.contains(&EntityRef::Line(lid)));
+                            let is_selected = current_selection.contains(&SelectedItem::Line(lid))
+                                || pending_items.contains(&SelectedItem::Line(lid));
+                            let class = if is_selected { "selected" } else if current_hover == Some(SelectedItem::Line(lid)) { "hovered" } else { "" };
+                            let is_in_contour = contour_members.contains(&EntityRef::Line(lid));
 
-                            let p1 = pts_snapshot.iter().find(|p| p.metadata.id == line.start_point_id);
-                            let p2 = pts_snapshot.iter().find(|p| p.metadata.id == line.end_point_id);
+                            let p1 = index_snapshot.point(&pts_snapshot, line.start_point_id);
+                            let p2 = index_snapshot.point(&pts_snapshot, line.end_point_id);
                             
                             if let (Some(start), Some(end)) = (p1, p2) {
                                 rsx! {
                                     line { 
                                         key: "ln-{lid}",
-                                        class: if is_selected { "selected" } else { "" },
+                                        class: "{class}",
                                         x1: "{start.x()}", y1: "{start.y()}", 
                                         x2: "{end.x()}", y2: "{end.y()}", 
-                                        stroke: if is_in_contour { "#f39c12" } else { "#3498db" }, 
+                                        stroke: if is_in_contour { palette::CONTOUR } else { palette::LINE }, 
                                         stroke_width: if is_in_contour { "5" } else { "3" },
-                                        onmousedown: move |evt| {
-                                            evt.stop_propagation();
-                                            if let CanvasMode::ContourCreation { active_contour_id } = *mode.read() {
-                                                if let Some(c) = contours.write().iter_mut().find(|c| c.metadata.id == active_contour_id) {
-                                                    c.entities.push(EntityRef::Line(lid));
-                                                }
-                                            } else {
-                                                selected_item.set(SelectedItem::Line(lid));
-                                            }
-                                        }
                                     }
                                 }
                             } else { rsx! { "" } }
@@ -422,34 +2355,26 @@ fn App() -> Element {
                     }
 
                     // رسم المنصفات (Bisectors)
-                    for bis in bis_snapshot.iter() {
+                    for bis in bis_snapshot.iter().filter(|b| visible.contains(&SelectedItem::Bisector(b.metadata.id)) && !degenerate_items.contains(&SelectedItem::Bisector(b.metadata.id))) {
                         {
                             let bid = bis.metadata.id;
-                            let is_selected = matches!(current_selection, SelectedItem::Bisector(id) if id == bid);
-                            let p1 = pts_snapshot.iter().find(|p| p.metadata.id == bis.p1_id);
-                            let vertex = pts_snapshot.iter().find(|p| p.metadata.id == bis.vertex_id);
-                            let p3 = pts_snapshot.iter().find(|p| p.metadata.id == bis.p3_id);
+                            let is_selected = current_selection.contains(&SelectedItem::Bisector(bid))
+                                || pending_items.contains(&SelectedItem::Bisector(bid));
+                            let class = if is_selected { "selected" } else if current_hover == Some(SelectedItem::Bisector(bid)) { "hovered" } else { "" };
+                            let p1 = index_snapshot.point(&pts_snapshot, bis.p1_id);
+                            let vertex = index_snapshot.point(&pts_snapshot, bis.vertex_id);
+                            let p3 = index_snapshot.point(&pts_snapshot, bis.p3_id);
                             
-                            if let (Some(p1), Some(v), Some(p3)) = (p1, vertex, p3) {
-                                let end_coords = bis.calculate_end_point(p1, v, p3);
+                            if let (Some(p1), Some(v), Some(p3)) = (p1, vertex, p3)
+                                && let Ok(end_coords) = bis.calculate_end_point(p1, v, p3, tolerance) {
                                 rsx! {
                                     line { 
                                         key: "bis-{bid}",
-                                        class: if is_selected { "selected" } else { "" },
+                                        class: "{class}",
                                         x1: "{v.x()}", y1: "{v.y()}", 
                                         x2: "{end_coords.x}", y2: "{end_coords.y}", 
-                                        stroke: "#9b59b6", stroke_width: "2",
+                                        stroke: palette::BISECTOR, stroke_width: "2",
                                         stroke_dasharray: "5,5",
-                                        onmousedown: move |evt| {
-                                            evt.stop_propagation();
-                                            if let CanvasMode::ContourCreation { active_contour_id } = *mode.read() {
-                                                if let Some(c) = contours.write().iter_mut().find(|c| c.metadata.id == active_contour_id) {
-                                                    c.entities.push(EntityRef::Bisector(bid));
-                                                }
-                                            } else {
-                                                selected_item.set(SelectedItem::Bisector(bid));
-                                            }
-                                        }
                                     }
                                 }
                             } else { rsx! { "" } }
@@ -457,12 +2382,14 @@ fn App() -> Element {
                     }
 
                     // رسم النقاط
-                    for p in pts_snapshot.iter() {
+                    for p in pts_snapshot.iter().filter(|p| visible.contains(&SelectedItem::Point(p.metadata.id))) {
                         {
                             let pid = p.metadata.id;
                             let px = p.x();
                             let py = p.y();
-                            let is_selected = matches!(current_selection, SelectedItem::Point(id) if id == pid);
+                            let is_selected = current_selection.contains(&SelectedItem::Point(pid))
+                                || pending_items.contains(&SelectedItem::Point(pid));
+                            let class = if is_selected { "selected" } else if current_hover == Some(SelectedItem::Point(pid)) { "hovered" } else { "" };
                             
                             let is_active = match &current_mode {
                                 CanvasMode::AwaitingLineEnd { first_point_id } => *first_point_id == pid,
@@ -471,89 +2398,30 @@ fn App() -> Element {
                                 CanvasMode::BezierEnd { p1, p2, p3 } => *p1 == pid || *p2 == pid || *p3 == pid,
                                 CanvasMode::BisectorVertex { p1 } => *p1 == pid,
                                 CanvasMode::BisectorEnd { p1, vertex } => *p1 == pid || *vertex == pid,
+                                CanvasMode::MirrorLineEnd { p1, .. } => *p1 == pid,
                                 _ => false,
                             } || is_selected;
                             
-                            let fill_color = if is_active { "#f1c40f" } 
-                                             else if is_selected { "#f1c40f" }
-                                             else if !matches!(current_mode, CanvasMode::PlacePoint) { "#e67e22" }
-                                             else { "#e74c3c" };
+                            let fill_color = if is_active { palette::POINT_ACTIVE }
+                                             else if !matches!(current_mode, CanvasMode::PlacePoint) { palette::POINT_PICKABLE }
+                                             else { palette::POINT };
 
                             rsx! {
                                 g {
                                     key: "pt-group-{pid}",
                                     circle { 
                                         key: "pt-{pid}",
-                                        class: if is_selected { "selected" } else { "" },
-                                        cx: "{px}", cy: "{py}", r: "10", 
+                                        class: "{class}",
+                                        cx: "{px}", cy: "{py}", r: "{hit_test::POINT_RADIUS}", 
                                         fill: "{fill_color}",
                                         stroke: if is_active || is_selected { "white" } else { "none" },
                                         stroke_width: "2",
-                                        style: "cursor: grab; pointer-events: all;",
-                                        onmousedown: move |evt| {
-                                            evt.stop_propagation();
-                                            let current_m = mode.read().clone();
-                                            match current_m {
-                                                CanvasMode::PlacePoint => {
-                                                    dragging_point_id.set(Some(pid));
-                                                    selected_item.set(SelectedItem::Point(pid));
-                                                }
-                                                CanvasMode::AwaitingLineStart => {
-                                                    mode.set(CanvasMode::AwaitingLineEnd { first_point_id: pid });
-                                                }
-                                                CanvasMode::AwaitingLineEnd { first_point_id } => {
-                                                    if first_point_id != pid {
-                                                        let lid = *next_id.read();
-                                                        let line_name = format!("L{}", lid);
-                                                        lines.write().push(VLine::new(lid, &line_name, first_point_id, pid));
-                                                        next_id.set(lid + 1);
-                                                    }
-                                                    mode.set(CanvasMode::AwaitingLineStart);
-                                                }
-                                                CanvasMode::BezierStart => {
-                                                    mode.set(CanvasMode::BezierControl1 { p1: pid });
-                                                }
-                                                CanvasMode::BezierControl1 { p1 } => {
-                                                    mode.set(CanvasMode::BezierControl2 { p1, p2: pid });
-                                                }
-                                                CanvasMode::BezierControl2 { p1, p2 } => {
-                                                    mode.set(CanvasMode::BezierEnd { p1, p2, p3: pid });
-                                                }
-                                                CanvasMode::BezierEnd { p1, p2, p3 } => {
-                                                    if pid != p1 && pid != p2 && pid != p3 {
-                                                        let sid = *next_id.read();
-                                                        let spline_name = format!("S{}", sid);
-                                                        splines.write().push(VCubicBezier::new(sid, &spline_name, p1, p2, p3, pid));
-                                                        next_id.set(sid + 1);
-                                                    }
-                                                    mode.set(CanvasMode::BezierStart);
-                                                }
-                                                CanvasMode::BisectorStart => {
-                                                    mode.set(CanvasMode::BisectorVertex { p1: pid });
-                                                }
-                                                CanvasMode::BisectorVertex { p1 } => {
-                                                    mode.set(CanvasMode::BisectorEnd { p1, vertex: pid });
-                                                }
-                                                CanvasMode::BisectorEnd { p1, vertex } => {
-                                                    if pid != p1 && pid != vertex {
-                                                        let bid = *next_id.read();
-                                                        let bis_name = format!("B{}", bid);
-                                                        bisectors.write().push(VBisector::new(bid, &bis_name, p1, vertex, pid, 150.0));
-                                                        next_id.set(bid + 1);
-                                                    }
-                                                    mode.set(CanvasMode::BisectorStart);
-                                                }
-                                                CanvasMode::ContourCreation { .. } => {
-                                                    selected_item.set(SelectedItem::Point(pid));
-                                                }
-                                            }
-                                        }
                                     }
                                     text {
                                         key: "lbl-{pid}",
                                         x: "{px + 15.0}",
                                         y: "{py - 15.0}",
-                                        fill: "#2c3e50",
+                                        fill: palette::INK,
                                         font_size: "18",
                                         font_weight: "bold",
                                         style: "pointer-events: none; user-select: none;",
@@ -563,6 +2431,84 @@ fn App() -> Element {
                             }
                         }
                     }
+
+                    // المسار المرتب للمسارات المختارة مع تمييز الفجوات
+                    for (cid, report) in contour_reports.iter().filter(|(cid, _)| current_selection.contains(&SelectedItem::Contour(*cid))) {
+                        path {
+                            key: "cnt-outline-{cid}",
+                            d: "{report.to_svg_path()}",
+                            fill: if report.closed { "rgba(243, 156, 18, 0.15)" } else { "none" },
+                            stroke: "#d35400",
+                            stroke_width: "2",
+                        }
+                        for (i, gap) in report.gaps.iter().enumerate() {
+                            circle {
+                                key: "cnt-gap-{cid}-{i}",
+                                cx: "{gap.at.x}", cy: "{gap.at.y}", r: "8",
+                                fill: "none", stroke: "#e74c3c", stroke_width: "3",
+                            }
+                        }
+                    }
+
+                    // تمييز نقاط التقاطع الذاتي في كل المسارات
+                    for (cid, m) in contour_metrics.iter() {
+                        for (i, p) in m.self_intersections.iter().enumerate() {
+                            path {
+                                key: "cnt-x-{cid}-{i}",
+                                d: "M {p.x - 8.0} {p.y - 8.0} L {p.x + 8.0} {p.y + 8.0} M {p.x - 8.0} {p.y + 8.0} L {p.x + 8.0} {p.y - 8.0}",
+                                stroke: "#e74c3c",
+                                stroke_width: "3",
+                            }
+                        }
+                    }
+
+                    // علامات الكائنات المنحلة؛ رسالة العلامة التي تحت المؤشر تظهر بجانبها
+                    for (key, at, message) in error_markers.iter() {
+                        g { key: "err-{key}", class: "degenerate",
+                            circle { cx: "{at.x}", cy: "{at.y}", r: "{ERROR_MARKER_RADIUS}" }
+                            if current_error_hover.as_deref() == Some(key.as_str()) {
+                                text {
+                                    x: "{at.x + ERROR_MARKER_RADIUS + 6.0}",
+                                    y: "{at.y - ERROR_MARKER_RADIUS - 6.0}",
+                                    "{message}"
+                                }
+                            }
+                        }
+                    }
+
+                    // مستطيل الاختيار: أزرق متصل للـ Window وأخضر متقطع للـ Crossing
+                    if let Some(b) = current_band {
+                        {
+                            let (min, max) = b.bounds();
+                            let window = b.is_window();
+                            rsx! {
+                                rect {
+                                    x: "{min.x}", y: "{min.y}",
+                                    width: "{max.x - min.x}", height: "{max.y - min.y}",
+                                    fill: if window { "rgba(52, 152, 219, 0.15)" } else { "rgba(46, 204, 113, 0.15)" },
+                                    stroke: if window { "#3498db" } else { "#27ae60" },
+                                    stroke_width: "1",
+                                    stroke_dasharray: if window { "none" } else { "6,4" },
+                                    style: "pointer-events: none;",
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            if !notices_snapshot.is_empty() {
+                div { class: "notifications",
+                    for n in notices_snapshot {
+                        div { key: "{n.id}", class: n.level.class(),
+                            span { "{n.text}" }
+                            button {
+                                class: "notice-close",
+                                onclick: move |_| notices.write().retain(|x| x.id != n.id),
+                                "×"
+                            }
+                        }
+                    }
                 }
             }
         }
@@ -0,0 +1,152 @@
+use std::fs;
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+use crate::error::{Error, Result};
+
+/// قياس واحد: قيمته في المقاس والطول الأساسيين ومقدار تغيره
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    pub name: String,
+    pub base: f64,
+    /// التغير لكل وحدة مقاس (صفر في الجداول الفردية)
+    #[serde(default)]
+    pub size_increase: f64,
+    /// التغير لكل وحدة طول
+    #[serde(default)]
+    pub height_increase: f64,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// مقاس وطول واحد من جدول متعدد المقاسات (None = القيمة الأساسية)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Grade {
+    pub size: Option<f64>,
+    pub height: Option<f64>,
+}
+
+impl Grade {
+    /// اسم قصير يصلح لأسماء الملفات: 52-176، 52، أو base
+    pub fn label(&self) -> String {
+        let parts: Vec<String> = [self.size, self.height].iter().flatten().map(|v| crate::export::fmt_num(*v)).collect();
+        if parts.is_empty() { "base".to_string() } else { parts.join("-") }
+    }
+}
+
+/// جدول القياسات (ملف JSON خارجي): فردي إذا لم تُحدد مقاسات، ومتعدد المقاسات بخلاف ذلك
+/// كما في ملفات .vit و .vst في Valentina
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MeasurementTable {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub base_size: f64,
+    #[serde(default)]
+    pub base_height: f64,
+    /// المقاسات والأطوال المتاحة للتدريج
+    #[serde(default)]
+    pub sizes: Vec<f64>,
+    #[serde(default)]
+    pub heights: Vec<f64>,
+    #[serde(default)]
+    pub measurements: Vec<Measurement>,
+}
+
+impl MeasurementTable {
+    /// قراءة جدول قياسات من ملف JSON
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        serde_json::from_str(&text).map_err(|e| Error::parse(path.display(), format!("invalid measurement table: {}", e)))
+    }
+
+    pub fn is_multisize(&self) -> bool {
+        !self.sizes.is_empty() || !self.heights.is_empty()
+    }
+
+    /// قيم كل القياسات لمقاس وطول محددين (الأساسيان عند عدم التحديد)
+    pub fn values(&self, size: Option<f64>, height: Option<f64>) -> Vec<(String, f64)> {
+        let ds = size.map_or(0.0, |s| s - self.base_size);
+        let dh = height.map_or(0.0, |h| h - self.base_height);
+        self.measurements.iter()
+            .map(|m| (m.name.clone(), m.base + ds * m.size_increase + dh * m.height_increase))
+            .collect()
+    }
+
+    /// التحقق من أن المقاس والطول المطلوبين موجودان في الجدول
+    pub fn check_grade(&self, size: Option<f64>, height: Option<f64>) -> Result<()> {
+        let known = |list: &[f64], base: f64, v: f64| (list.is_empty() && v == base) || list.contains(&v);
+        if let Some(s) = size && !known(&self.sizes, self.base_size, s) {
+            return Err(Error::Validation(format!("size {} is not in the measurement table", s)));
+        }
+        if let Some(h) = height && !known(&self.heights, self.base_height, h) {
+            return Err(Error::Validation(format!("height {} is not in the measurement table", h)));
+        }
+        Ok(())
+    }
+
+    /// كل تركيبات المقاس والطول في الجدول (للتدريج الجماعي)
+    pub fn grades(&self) -> Vec<Grade> {
+        let sizes: Vec<Option<f64>> = if self.sizes.is_empty() { vec![None] } else { self.sizes.iter().map(|s| Some(*s)).collect() };
+        let heights: Vec<Option<f64>> = if self.heights.is_empty() { vec![None] } else { self.heights.iter().map(|h| Some(*h)).collect() };
+        sizes.iter()
+            .flat_map(|s| heights.iter().map(move |h| Grade { size: *s, height: *h }))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> MeasurementTable {
+        MeasurementTable {
+            name: "test".to_string(),
+            base_size: 48.0,
+            base_height: 176.0,
+            sizes: vec![46.0, 48.0, 50.0],
+            heights: vec![170.0, 176.0],
+            measurements: vec![
+                Measurement { name: "waist".to_string(), base: 80.0, size_increase: 2.0, height_increase: 0.0, description: String::new() },
+                Measurement { name: "back_length".to_string(), base: 45.0, size_increase: 0.25, height_increase: 0.5, description: String::new() },
+            ],
+        }
+    }
+
+    #[test]
+    fn grades_are_every_size_and_height() {
+        let labels: Vec<String> = table().grades().iter().map(Grade::label).collect();
+        assert_eq!(labels, ["46-170", "46-176", "48-170", "48-176", "50-170", "50-176"]);
+
+        let sizes_only = MeasurementTable { heights: Vec::new(), ..table() };
+        let labels: Vec<String> = sizes_only.grades().iter().map(Grade::label).collect();
+        assert_eq!(labels, ["46", "48", "50"]);
+
+        // الجدول الفردي له درجة واحدة هي القيم الأساسية
+        assert_eq!(MeasurementTable::default().grades(), [Grade { size: None, height: None }]);
+        assert_eq!(MeasurementTable::default().grades()[0].label(), "base");
+    }
+
+    #[test]
+    fn values_grow_from_the_base_grade() {
+        let t = table();
+        assert_eq!(t.values(None, None), [("waist".to_string(), 80.0), ("back_length".to_string(), 45.0)]);
+        assert_eq!(t.values(Some(50.0), Some(170.0)), [("waist".to_string(), 84.0), ("back_length".to_string(), 42.5)]);
+        assert_eq!(t.values(Some(46.0), None), [("waist".to_string(), 76.0), ("back_length".to_string(), 44.5)]);
+    }
+
+    #[test]
+    fn check_grade_rejects_unknown_sizes_and_heights() {
+        let t = table();
+        assert!(t.check_grade(Some(48.0), Some(170.0)).is_ok());
+        assert!(t.check_grade(None, None).is_ok());
+        let Err(err) = t.check_grade(Some(52.0), None) else { panic!("size 52 accepted") };
+        assert!(err.to_string().contains("size 52"));
+        let Err(err) = t.check_grade(None, Some(180.0)) else { panic!("height 180 accepted") };
+        assert!(err.to_string().contains("height 180"));
+
+        // بدون قائمة مقاسات لا يُقبل إلا المقاس الأساسي
+        let single = MeasurementTable { sizes: Vec::new(), heights: Vec::new(), ..table() };
+        assert!(single.check_grade(Some(48.0), Some(176.0)).is_ok());
+        assert!(single.check_grade(Some(50.0), None).is_err());
+    }
+}
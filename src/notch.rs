@@ -0,0 +1,168 @@
+use crate::geometry::Point2D;
+use crate::object::EntityRef;
+use crate::contour::ContourReport;
+use crate::piece::{VPiece, outward_side, direction};
+use serde::{Serialize, Deserialize};
+
+/// طول علامة التطابق الافتراضي (بوحدات الرسم)
+pub const DEFAULT_NOTCH_LENGTH: f64 = 10.0;
+
+/// شكل علامة التطابق (Passmark)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NotchType {
+    #[default]
+    OneLine,
+    TwoLines,
+    ThreeLines,
+    TMark,
+    VMark,
+    UMark,
+}
+
+/// اتجاه العلامة عند العقدة
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NotchAngle {
+    /// على منصف الزاوية بين القطعتين
+    #[default]
+    Bisector,
+    /// عمودياً على القطعة التالية
+    StraightForward,
+    /// عمودياً على القطعة السابقة
+    StraightBackward,
+}
+
+impl NotchType {
+    pub const ALL: [NotchType; 6] = [
+        NotchType::OneLine,
+        NotchType::TwoLines,
+        NotchType::ThreeLines,
+        NotchType::TMark,
+        NotchType::VMark,
+        NotchType::UMark,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            NotchType::OneLine => "One line",
+            NotchType::TwoLines => "Two lines",
+            NotchType::ThreeLines => "Three lines",
+            NotchType::TMark => "T-mark",
+            NotchType::VMark => "V-mark",
+            NotchType::UMark => "U-mark",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|n| n.label() == label)
+    }
+}
+
+impl NotchAngle {
+    pub const ALL: [NotchAngle; 3] = [NotchAngle::Bisector, NotchAngle::StraightForward, NotchAngle::StraightBackward];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            NotchAngle::Bisector => "Bisector",
+            NotchAngle::StraightForward => "Straight forward",
+            NotchAngle::StraightBackward => "Straight backward",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.label() == label)
+    }
+}
+
+/// علامة تطابق عند عقدة من حدود القطعة (بداية الكيان `entity` في المسار المرتب)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VNotch {
+    pub entity: EntityRef,
+    pub kind: NotchType,
+    pub angle: NotchAngle,
+    pub length: f64,
+}
+
+impl VNotch {
+    pub fn new(entity: EntityRef, kind: NotchType) -> Self {
+        Self { entity, kind, angle: NotchAngle::default(), length: DEFAULT_NOTCH_LENGTH }
+    }
+}
+
+/// خطوط العلامة بدءاً من `base` وداخلة في اتجاه `dir` (متجه وحدة)
+fn mark_shape(kind: NotchType, base: Point2D, dir: Point2D, length: f64) -> Vec<Vec<Point2D>> {
+    let at = |along: f64, across: f64| Point2D::new(
+        base.x + dir.x * along - dir.y * across,
+        base.y + dir.y * along + dir.x * across,
+    );
+    let gap = length / 4.0;
+    match kind {
+        NotchType::OneLine => vec![vec![at(0.0, 0.0), at(length, 0.0)]],
+        NotchType::TwoLines => [-gap / 2.0, gap / 2.0].iter()
+            .map(|&o| vec![at(0.0, o), at(length, o)])
+            .collect(),
+        NotchType::ThreeLines => [-gap, 0.0, gap].iter()
+            .map(|&o| vec![at(0.0, o), at(length, o)])
+            .collect(),
+        NotchType::TMark => vec![
+            vec![at(0.0, 0.0), at(length, 0.0)],
+            vec![at(length, -gap), at(length, gap)],
+        ],
+        NotchType::VMark => vec![vec![at(0.0, -gap), at(length, 0.0), at(0.0, gap)]],
+        NotchType::UMark => {
+            // ضلعان متوازيان ينتهيان بنصف دائرة
+            let r = gap;
+            let mut u = vec![at(0.0, -r), at(length - r, -r)];
+            for i in 1..8 {
+                let t = std::f64::consts::PI * i as f64 / 8.0;
+                u.push(at(length - r + r * t.sin(), -r * t.cos()));
+            }
+            u.push(at(length - r, r));
+            u.push(at(0.0, r));
+            vec![u]
+        }
+    }
+}
+
+/// خطوط كل علامات القطعة: مرة على خط الخياطة ومرة على حافة الهامش
+/// كل خط متعدد يُرسم ويُصدّر كما هو
+pub fn notch_marks(piece: &VPiece, report: &ContourReport) -> Vec<Vec<Point2D>> {
+    let Some(side) = outward_side(report) else { return Vec::new() };
+    if !report.closed || report.segments.is_empty() {
+        return Vec::new();
+    }
+    let n = report.segments.len();
+    let inward = |d: Point2D| Point2D::new(-side * d.y, side * d.x);
+
+    let mut marks = Vec::new();
+    for notch in &piece.notches {
+        let Some(j) = report.segments.iter().position(|s| s.entity == notch.entity) else { continue };
+        let prev = &report.segments[(j + n - 1) % n];
+        let next = &report.segments[j];
+        let prev_poly = prev.shape.polyline(16);
+        let next_poly = next.shape.polyline(16);
+        let node = next.shape.start();
+        let in_a = inward(direction(&prev_poly[prev_poly.len() - 2], &prev_poly[prev_poly.len() - 1]));
+        let in_b = inward(direction(&next_poly[0], &next_poly[1]));
+
+        let dir = match notch.angle {
+            NotchAngle::Bisector => {
+                let sum = Point2D::new(in_a.x + in_b.x, in_a.y + in_b.y);
+                if sum.distance_to(&Point2D::new(0.0, 0.0)) < 1e-9 { in_b } else { direction(&Point2D::new(0.0, 0.0), &sum) }
+            }
+            NotchAngle::StraightForward => in_b,
+            NotchAngle::StraightBackward => in_a,
+        };
+
+        // نقطة العلامة على حافة الهامش: على نفس اتجاه العلامة للخارج
+        let width = piece.width_for(next.entity);
+        let cos = dir.x * in_b.x + dir.y * in_b.y;
+        let reach = if cos > 0.1 { width / cos } else { width };
+        let sa_base = Point2D::new(node.x - dir.x * reach, node.y - dir.y * reach);
+
+        marks.extend(mark_shape(notch.kind, node, dir, notch.length));
+        if width > 0.0 {
+            marks.extend(mark_shape(notch.kind, sa_base, dir, notch.length.min(reach)));
+        }
+    }
+    marks
+}
@@ -1,8 +1,10 @@
 use crate::types::{GOType, DrawMode};
-use crate::geometry::Point2D;
+use crate::geometry::{Point2D, GeometryError};
+use crate::spatial::Shape;
+use crate::formula::PointFormula;
 use serde::{Serialize, Deserialize};
 
-#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub enum SelectedItem {
     None,
     Point(u32),
@@ -10,16 +12,56 @@ pub enum SelectedItem {
     Spline(u32),
     Bisector(u32),
     Contour(u32),
+    Piece(u32),
 }
 
 /// يمثل نوع ومعرف أي كيان هندسي يمكن أن يكون جزءاً من مسار
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum EntityRef {
     Line(u32),
     Spline(u32),
     Bisector(u32),
 }
 
+impl EntityRef {
+    /// الاسم المختصر المعروض في الواجهة
+    pub fn label(&self) -> String {
+        match self {
+            EntityRef::Line(id) => format!("L{id}"),
+            EntityRef::Spline(id) => format!("S{id}"),
+            EntityRef::Bisector(id) => format!("B{id}"),
+        }
+    }
+
+    /// الشكل الهندسي للكيان في إحداثيات الرسم
+    /// ويرجع None إذا كان الكيان أو نقاطه غير موجودة
+    pub fn shape(
+        &self,
+        points: &[VPoint],
+        lines: &[VLine],
+        splines: &[VCubicBezier],
+        bisectors: &[VBisector],
+    ) -> Option<Shape> {
+        let find = |id: u32| points.iter().find(|p| p.metadata.id == id);
+        match *self {
+            EntityRef::Line(id) => {
+                let l = lines.iter().find(|l| l.metadata.id == id)?;
+                Some(Shape::Segment(find(l.start_point_id)?.coords, find(l.end_point_id)?.coords))
+            }
+            EntityRef::Spline(id) => {
+                let s = splines.iter().find(|s| s.metadata.id == id)?;
+                let (p1, p2, p3, p4) = (find(s.p1_id)?, find(s.p2_id)?, find(s.p3_id)?, find(s.p4_id)?);
+                Some(Shape::Cubic([p1.coords, p2.coords, p3.coords, p4.coords]))
+            }
+            EntityRef::Bisector(id) => {
+                let b = bisectors.iter().find(|b| b.metadata.id == id)?;
+                let (p1, v, p3) = (find(b.p1_id)?, find(b.vertex_id)?, find(b.p3_id)?);
+                Some(Shape::Segment(v.coords, b.calculate_end_point(p1, v, p3, 0.0).ok()?))
+            }
+        }
+    }
+}
+
 /// البيانات المشتركة لكل كائنات Valentina
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct VGObject {
@@ -44,6 +86,9 @@ impl VGObject {
 pub struct VPoint {
     pub metadata: VGObject,
     pub coords: Point2D,
+    /// معادلتا الإحداثيين من القياسات (None للنقاط الحرة)
+    #[serde(default)]
+    pub formula: Option<PointFormula>,
 }
 
 impl VPoint {
@@ -51,6 +96,7 @@ impl VPoint {
         Self {
             metadata: VGObject::new(id, name, crate::types::GOType::Point),
             coords: Point2D::new(x, y),
+            formula: None,
         }
     }
     pub fn x(&self) -> f64 { self.coords.x }
@@ -76,8 +122,12 @@ impl VLine {
     pub fn length(&self, start_p: &VPoint, end_p: &VPoint) -> f64 {
         start_p.coords.distance_to(&end_p.coords)
     }
-    pub fn angle(&self, start_p: &VPoint, end_p: &VPoint) -> f64 {
-        start_p.coords.angle_to(&end_p.coords)
+    pub fn angle(&self, start_p: &VPoint, end_p: &VPoint, tolerance: f64) -> Result<f64, GeometryError> {
+        start_p.coords.direction_to(&end_p.coords, tolerance).map_err(|_| GeometryError::ZeroLength)
+    }
+    /// الخط منحل إذا كان طوله أقل من السماحية
+    pub fn check(&self, start_p: &VPoint, end_p: &VPoint, tolerance: f64) -> Result<(), GeometryError> {
+        self.angle(start_p, end_p, tolerance).map(|_| ())
     }
 }
 
@@ -100,6 +150,14 @@ impl VCubicBezier {
             p4_id: p4,
         }
     }
+
+    /// المنحنى منحل إذا انطبقت نقاطه الأربع (طوله صفر)
+    pub fn check(&self, p1: &VPoint, p2: &VPoint, p3: &VPoint, p4: &VPoint, tolerance: f64) -> Result<(), GeometryError> {
+        if [p2, p3, p4].iter().all(|p| p1.coords.distance_to(&p.coords) <= tolerance) {
+            return Err(GeometryError::ZeroLength);
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -109,6 +167,9 @@ pub struct VBisector {
     pub vertex_id: u32,
     pub p3_id: u32,
     pub length: f64,
+    /// معادلة الطول من القياسات (تحل محل `length` عند إعادة الحساب)
+    #[serde(default)]
+    pub length_formula: Option<String>,
 }
 
 impl VBisector {
@@ -119,11 +180,13 @@ impl VBisector {
             vertex_id: vertex,
             p3_id: p3,
             length,
+            length_formula: None,
         }
     }
-    pub fn calculate_end_point(&self, p1: &VPoint, vertex: &VPoint, p3: &VPoint) -> Point2D {
-        let ang1 = vertex.coords.angle_to(&p1.coords);
-        let ang2 = vertex.coords.angle_to(&p3.coords);
+    /// نهاية المنصف؛ خطأ إذا انطبقت p1 أو p3 على الرأس (ضمن `tolerance`) لأن الزاوية غير معرفة
+    pub fn calculate_end_point(&self, p1: &VPoint, vertex: &VPoint, p3: &VPoint, tolerance: f64) -> Result<Point2D, GeometryError> {
+        let ang1 = vertex.coords.direction_to(&p1.coords, tolerance)?;
+        let ang2 = vertex.coords.direction_to(&p3.coords, tolerance)?;
         let mut diff = ang2 - ang1;
         while diff < 0.0 { diff += 360.0; }
         while diff >= 360.0 { diff -= 360.0; }
@@ -133,7 +196,7 @@ impl VBisector {
         } else {
             ang1 + diff / 2.0
         };
-        vertex.coords.point_at(self.length, bisector_angle)
+        Ok(vertex.coords.point_at(self.length, bisector_angle))
     }
 }
 
@@ -152,3 +215,35 @@ impl VContour {
         }
     }
 }
+
+/// فحص الخطوط والمنحنيات والمنصفات بسماحية المستند؛ يرجع الكائنات المنحلة مع السبب
+/// (الكائنات ذات النقاط المفقودة تُترك لفحص المراجع)
+pub fn degenerate_objects(
+    points: &[VPoint],
+    lines: &[VLine],
+    splines: &[VCubicBezier],
+    bisectors: &[VBisector],
+    tolerance: f64,
+) -> Vec<(SelectedItem, GeometryError)> {
+    let find = |id: u32| points.iter().find(|p| p.metadata.id == id);
+    let mut out = Vec::new();
+    for l in lines {
+        if let (Some(a), Some(b)) = (find(l.start_point_id), find(l.end_point_id))
+            && let Err(e) = l.check(a, b, tolerance) {
+            out.push((SelectedItem::Line(l.metadata.id), e));
+        }
+    }
+    for s in splines {
+        if let (Some(p1), Some(p2), Some(p3), Some(p4)) = (find(s.p1_id), find(s.p2_id), find(s.p3_id), find(s.p4_id))
+            && let Err(e) = s.check(p1, p2, p3, p4, tolerance) {
+            out.push((SelectedItem::Spline(s.metadata.id), e));
+        }
+    }
+    for b in bisectors {
+        if let (Some(p1), Some(v), Some(p3)) = (find(b.p1_id), find(b.vertex_id), find(b.p3_id))
+            && let Err(e) = b.calculate_end_point(p1, v, p3, tolerance) {
+            out.push((SelectedItem::Bisector(b.metadata.id), e));
+        }
+    }
+    out
+}
@@ -0,0 +1,252 @@
+use crate::geometry::{Point2D, GeometryError};
+use crate::object::{VGObject, VPoint, VLine, VCubicBezier, VBisector, SelectedItem};
+use crate::types::GOType;
+use serde::{Serialize, Deserialize};
+
+/// محور الانعكاس عند استخدام نقطة واحدة فقط
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AxisType {
+    Horizontal,
+    Vertical,
+}
+
+/// نوع العملية ومعاملاتها (مراجع لنقاط موجودة وليست نسخاً منها)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OperationKind {
+    /// انعكاس حول خط يمر بنقطتين
+    FlippingByLine { p1_id: u32, p2_id: u32 },
+    /// انعكاس حول محور أفقي أو رأسي يمر بنقطة
+    FlippingByAxis { origin_id: u32, axis: AxisType },
+    /// دوران حول نقطة بزاوية (بالدرجات)
+    Rotation { origin_id: u32, angle: f64 },
+    /// إزاحة بمسافة وزاوية (بالدرجات)
+    Move { length: f64, angle: f64 },
+}
+
+impl OperationKind {
+    /// البادئة المستخدمة في تسمية العملية (Mirror12, Rotation15...)
+    pub fn name_prefix(&self) -> &'static str {
+        match self {
+            OperationKind::FlippingByLine { .. } | OperationKind::FlippingByAxis { .. } => "Mirror",
+            OperationKind::Rotation { .. } => "Rotation",
+            OperationKind::Move { .. } => "Move",
+        }
+    }
+}
+
+/// عملية جماعية تنتج نسخاً مشتقة من كائنات مصدرية
+/// النسخ تبقى مرتبطة بأصولها عبر `point_map` وتُعاد حساباتها مع كل تحريك
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VOperation {
+    pub metadata: VGObject,
+    pub kind: OperationKind,
+    pub suffix: String,
+    /// أزواج (معرف النقطة الأصلية، معرف النقطة المشتقة)
+    pub point_map: Vec<(u32, u32)>,
+}
+
+impl VOperation {
+    pub fn new(id: u32, name: &str, kind: OperationKind, suffix: &str) -> Self {
+        Self {
+            metadata: VGObject::new(id, name, GOType::Operation),
+            kind,
+            suffix: suffix.to_string(),
+            point_map: Vec::new(),
+        }
+    }
+
+    /// النقاط التي تعتمد عليها العملية نفسها (خط الانعكاس، المحور أو مركز الدوران)
+    pub fn reference_points(&self) -> Vec<u32> {
+        match self.kind {
+            OperationKind::FlippingByLine { p1_id, p2_id } => vec![p1_id, p2_id],
+            OperationKind::FlippingByAxis { origin_id, .. } => vec![origin_id],
+            OperationKind::Rotation { origin_id, .. } => vec![origin_id],
+            OperationKind::Move { .. } => Vec::new(),
+        }
+    }
+
+    /// تطبيق التحويل على إحداثيات نقطة واحدة
+    /// يرجع None إذا كانت نقاط المرجع غير موجودة
+    pub fn transform(&self, p: &Point2D, points: &[VPoint]) -> Option<Point2D> {
+        let coords_of = |id: u32| points.iter().find(|p| p.metadata.id == id).map(|p| p.coords);
+        match self.kind {
+            OperationKind::FlippingByLine { p1_id, p2_id } => {
+                let a = coords_of(p1_id)?;
+                let b = coords_of(p2_id)?;
+                Some(p.reflect(&a, &b))
+            }
+            OperationKind::FlippingByAxis { origin_id, axis } => {
+                let o = coords_of(origin_id)?;
+                let b = match axis {
+                    AxisType::Horizontal => Point2D::new(o.x + 1.0, o.y),
+                    AxisType::Vertical => Point2D::new(o.x, o.y + 1.0),
+                };
+                Some(p.reflect(&o, &b))
+            }
+            OperationKind::Rotation { origin_id, angle } => {
+                let o = coords_of(origin_id)?;
+                Some(p.rotate(&o, angle))
+            }
+            OperationKind::Move { length, angle } => Some(p.point_at(length, angle)),
+        }
+    }
+
+    /// الانعكاس حول خط نقطتاه منطبقتان غير معرف (الاتجاه مفقود)
+    pub fn check(&self, points: &[VPoint], tolerance: f64) -> Result<(), GeometryError> {
+        let coords_of = |id: u32| points.iter().find(|p| p.metadata.id == id).map(|p| p.coords);
+        if let OperationKind::FlippingByLine { p1_id, p2_id } = self.kind
+            && let (Some(a), Some(b)) = (coords_of(p1_id), coords_of(p2_id)) {
+            a.direction_to(&b, tolerance)?;
+        }
+        Ok(())
+    }
+
+    pub fn mapped(&self, src_id: u32) -> Option<u32> {
+        self.point_map.iter().find(|(s, _)| *s == src_id).map(|(_, d)| *d)
+    }
+}
+
+/// جمع معرفات النقاط التي يعتمد عليها كل عنصر مختار (بدون تكرار)
+fn collect_source_points(
+    items: &[SelectedItem],
+    lines: &[VLine],
+    splines: &[VCubicBezier],
+    bisectors: &[VBisector],
+) -> Vec<u32> {
+    let mut ids: Vec<u32> = Vec::new();
+    let mut push = |id: u32| if !ids.contains(&id) { ids.push(id) };
+    for item in items {
+        match *item {
+            SelectedItem::Point(id) => push(id),
+            SelectedItem::Line(id) => {
+                if let Some(l) = lines.iter().find(|l| l.metadata.id == id) {
+                    push(l.start_point_id);
+                    push(l.end_point_id);
+                }
+            }
+            SelectedItem::Spline(id) => {
+                if let Some(s) = splines.iter().find(|s| s.metadata.id == id) {
+                    for pid in [s.p1_id, s.p2_id, s.p3_id, s.p4_id] { push(pid); }
+                }
+            }
+            SelectedItem::Bisector(id) => {
+                if let Some(b) = bisectors.iter().find(|b| b.metadata.id == id) {
+                    for pid in [b.p1_id, b.vertex_id, b.p3_id] { push(pid); }
+                }
+            }
+            SelectedItem::Contour(_) | SelectedItem::Piece(_) | SelectedItem::None => {}
+        }
+    }
+    ids
+}
+
+/// إنشاء النسخ المشتقة لكل العناصر المختارة وإضافتها للمخازن
+/// الخطوط والمنحنيات والمنصفات المنسوخة تشير للنقاط المشتقة، فتتبع أصولها تلقائياً
+/// لا يُضاف شيء إذا كانت مراجع العملية منحلة
+#[allow(clippy::too_many_arguments)]
+pub fn apply_operation(
+    mut op: VOperation,
+    items: &[SelectedItem],
+    next_id: &mut u32,
+    points: &mut Vec<VPoint>,
+    lines: &mut Vec<VLine>,
+    splines: &mut Vec<VCubicBezier>,
+    bisectors: &mut Vec<VBisector>,
+    tolerance: f64,
+) -> Result<VOperation, GeometryError> {
+    op.check(points, tolerance)?;
+    for src_id in collect_source_points(items, lines, splines, bisectors) {
+        let Some(src) = points.iter().find(|p| p.metadata.id == src_id) else { continue };
+        let Some(coords) = op.transform(&src.coords, points) else { continue };
+        let name = format!("{}{}", src.metadata.name, op.suffix);
+        let new_id = *next_id;
+        *next_id += 1;
+        points.push(VPoint::new(new_id, &name, coords.x, coords.y));
+        op.point_map.push((src_id, new_id));
+    }
+
+    for item in items {
+        match *item {
+            SelectedItem::Line(id) => {
+                let Some(l) = lines.iter().find(|l| l.metadata.id == id).cloned() else { continue };
+                if let (Some(s), Some(e)) = (op.mapped(l.start_point_id), op.mapped(l.end_point_id)) {
+                    let name = format!("{}{}", l.metadata.name, op.suffix);
+                    lines.push(VLine::new(*next_id, &name, s, e));
+                    *next_id += 1;
+                }
+            }
+            SelectedItem::Spline(id) => {
+                let Some(sp) = splines.iter().find(|s| s.metadata.id == id).cloned() else { continue };
+                if let (Some(p1), Some(p2), Some(p3), Some(p4)) =
+                    (op.mapped(sp.p1_id), op.mapped(sp.p2_id), op.mapped(sp.p3_id), op.mapped(sp.p4_id)) {
+                    let name = format!("{}{}", sp.metadata.name, op.suffix);
+                    splines.push(VCubicBezier::new(*next_id, &name, p1, p2, p3, p4));
+                    *next_id += 1;
+                }
+            }
+            SelectedItem::Bisector(id) => {
+                let Some(b) = bisectors.iter().find(|b| b.metadata.id == id).cloned() else { continue };
+                if let (Some(p1), Some(v), Some(p3)) =
+                    (op.mapped(b.p1_id), op.mapped(b.vertex_id), op.mapped(b.p3_id)) {
+                    let name = format!("{}{}", b.metadata.name, op.suffix);
+                    bisectors.push(VBisector::new(*next_id, &name, p1, v, p3, b.length));
+                    *next_id += 1;
+                }
+            }
+            SelectedItem::Point(_) | SelectedItem::Contour(_) | SelectedItem::Piece(_) | SelectedItem::None => {}
+        }
+    }
+    Ok(op)
+}
+
+/// إعادة حساب إحداثيات كل النقاط المشتقة بترتيب إنشاء العمليات
+/// (الترتيب مهم لأن عملية قد تعكس نقاطاً ناتجة عن عملية سابقة)
+/// العمليات المنحلة تُترك نقاطها في مكانها وتُرجع مع السبب
+pub fn recalculate(ops: &[VOperation], points: &mut [VPoint], tolerance: f64) -> Vec<(u32, GeometryError)> {
+    let mut failed = Vec::new();
+    for op in ops {
+        if let Err(e) = op.check(points, tolerance) {
+            failed.push((op.metadata.id, e));
+            continue;
+        }
+        for &(src_id, dst_id) in &op.point_map {
+            let Some(src) = points.iter().find(|p| p.metadata.id == src_id) else { continue };
+            let Some(coords) = op.transform(&src.coords, points) else { continue };
+            if let Some(dst) = points.iter_mut().find(|p| p.metadata.id == dst_id) {
+                dst.coords = coords;
+            }
+        }
+    }
+    failed
+}
+
+pub fn is_derived(ops: &[VOperation], point_id: u32) -> bool {
+    ops.iter().any(|op| op.point_map.iter().any(|(_, d)| *d == point_id))
+}
+
+/// الحذف المتتالي للعمليات: يرجع كل النقاط التي يجب حذفها مع النقطة المحذوفة
+/// (النسخ المشتقة منها، وكل نسخ العمليات التي تعتمد عليها كمرجع)
+pub fn cascade_delete(ops: &mut Vec<VOperation>, point_id: u32) -> Vec<u32> {
+    let mut removed = vec![point_id];
+    let mut i = 0;
+    while i < removed.len() {
+        let id = removed[i];
+        for op in ops.iter() {
+            let derived: Vec<u32> = if op.reference_points().contains(&id) {
+                op.point_map.iter().map(|(_, d)| *d).collect()
+            } else {
+                op.point_map.iter().filter(|(s, _)| *s == id).map(|(_, d)| *d).collect()
+            };
+            for d in derived {
+                if !removed.contains(&d) { removed.push(d); }
+            }
+        }
+        i += 1;
+    }
+    ops.retain(|op| !op.reference_points().iter().any(|id| removed.contains(id)));
+    for op in ops.iter_mut() {
+        op.point_map.retain(|(s, d)| !removed.contains(s) && !removed.contains(d));
+    }
+    ops.retain(|op| !op.point_map.is_empty());
+    removed
+}
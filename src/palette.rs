@@ -0,0 +1,34 @@
+//! ألوان الكانفاس، مشتركة بين الواجهة وتصدير PNG لتطابق المعاينة ما يظهر على الشاشة
+
+use crate::export::Layer;
+
+pub const POINT: &str = "#e74c3c";
+/// النقطة المختارة أو الجاري استخدامها في أداة
+pub const POINT_ACTIVE: &str = "#f1c40f";
+/// النقاط خارج وضع وضع النقاط (قابلة للاختيار في أداة)
+pub const POINT_PICKABLE: &str = "#e67e22";
+pub const LINE: &str = "#3498db";
+pub const SPLINE: &str = "#2ecc71";
+pub const BISECTOR: &str = "#9b59b6";
+/// عناصر الرسم الداخلة في مسار
+pub const CONTOUR: &str = "#f39c12";
+/// خط الخياطة وخط النسيج والمسارات المقصوصة والنصوص
+pub const INK: &str = "#2c3e50";
+/// حد الهامش والمسارات المرسومة فقط
+pub const ALLOWANCE: &str = "#7f8c8d";
+pub const NOTCH: &str = "#c0392b";
+pub const PLACEMENT: &str = "#8e44ad";
+
+/// لون كل طبقة تصدير كما تُرسم على الكانفاس
+pub fn layer_color(layer: Layer) -> &'static str {
+    match layer {
+        Layer::DrawingPoints => POINT,
+        Layer::DrawingLines => LINE,
+        Layer::DrawingSplines => SPLINE,
+        Layer::DrawingBisectors => BISECTOR,
+        Layer::CutLine | Layer::InternalDraw => ALLOWANCE,
+        Layer::SeamLine | Layer::Grainline | Layer::InternalCut | Layer::Text => INK,
+        Layer::Notches => NOTCH,
+        Layer::Placements => PLACEMENT,
+    }
+}
@@ -0,0 +1,582 @@
+use crate::geometry::{Point2D, line_intersection};
+use crate::object::{VGObject, EntityRef};
+use crate::contour::{ContourReport, Orientation};
+use crate::notch::{VNotch, NotchType};
+use crate::annotation::{Grainline, TextLabel, PatternInfo};
+use crate::internal::{InternalPath, PlacementLabel};
+use crate::object::{VPoint, VContour};
+use crate::seam_match::SeamPair;
+use crate::notch::notch_marks;
+use crate::spatial::{Bounds, CurveResolution};
+use crate::types::GOType;
+use serde::{Serialize, Deserialize};
+
+/// عرض هامش الخياطة الافتراضي للقطع الجديدة (بوحدات الرسم)
+pub const DEFAULT_SEAM_ALLOWANCE: f64 = 10.0;
+
+/// عدد القطع المستقيمة لتقريب كل منحنى عند حساب الإزاحة
+const OFFSET_CURVE_SEGMENTS: usize = 32;
+
+/// معالجة ركن هامش الخياطة عند التقاء قطعتين (في الأركان البارزة فقط)
+/// الأركان الداخلية تُقص دائماً عند تقاطع الإزاحتين
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CornerType {
+    /// مد حافتي الهامش حتى تتقاطعا (ركن حاد)
+    #[default]
+    Intersect,
+    /// مد كل حافة بمقدار عرض الهامش ثم الوصل بينهما
+    Extend,
+    /// قص الركن عمودياً على منصف الزاوية على بعد عرض الهامش من العقدة
+    Mitre,
+    /// انعكاس هامش القطعة التالية حول خط الخياطة السابق (مثل ثني الحاشية)
+    Reverse,
+    /// قص الهامش بزاوية قائمة على القطعة التالية عند العقدة
+    Square,
+}
+
+impl CornerType {
+    pub const ALL: [CornerType; 5] = [
+        CornerType::Intersect,
+        CornerType::Extend,
+        CornerType::Mitre,
+        CornerType::Reverse,
+        CornerType::Square,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CornerType::Intersect => "Intersect",
+            CornerType::Extend => "Extend",
+            CornerType::Mitre => "Mitre",
+            CornerType::Reverse => "Reverse",
+            CornerType::Square => "Square",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.label() == label)
+    }
+}
+
+/// إعدادات خاصة بعقدة: العقدة هي بداية الكيان `entity` في المسار المرتب
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeamNode {
+    pub entity: EntityRef,
+    /// عرض الهامش للقطعة التي تبدأ من هذه العقدة (بدلاً من عرض القطعة)
+    pub width: Option<f64>,
+    /// معالجة الركن عند هذه العقدة (بدلاً من معالجة القطعة)
+    pub corner: Option<CornerType>,
+}
+
+/// قطعة باترون (Detail) مبنية على مسار مغلق
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VPiece {
+    pub metadata: VGObject,
+    pub contour_id: u32,
+    pub seam_allowance: f64,
+    #[serde(default)]
+    pub corner: CornerType,
+    #[serde(default)]
+    pub nodes: Vec<SeamNode>,
+    #[serde(default)]
+    pub notches: Vec<VNotch>,
+    #[serde(default = "default_cut_quantity")]
+    pub cut_quantity: u32,
+    #[serde(default)]
+    pub material: String,
+    #[serde(default)]
+    pub grainline: Option<Grainline>,
+    #[serde(default)]
+    pub piece_label: Option<TextLabel>,
+    #[serde(default)]
+    pub pattern_label: Option<TextLabel>,
+    #[serde(default)]
+    pub internal_paths: Vec<InternalPath>,
+    #[serde(default)]
+    pub placements: Vec<PlacementLabel>,
+    /// النسخ الزوجية تُقص معكوسة (يمين/يسار)
+    #[serde(default)]
+    pub mirrored_pairs: bool,
+}
+
+fn default_cut_quantity() -> u32 {
+    1
+}
+
+impl VPiece {
+    pub fn new(id: u32, name: &str, contour_id: u32, seam_allowance: f64) -> Self {
+        Self {
+            metadata: VGObject::new(id, name, GOType::Piece),
+            contour_id,
+            seam_allowance,
+            corner: CornerType::default(),
+            nodes: Vec::new(),
+            notches: Vec::new(),
+            cut_quantity: default_cut_quantity(),
+            material: String::from("Fabric"),
+            grainline: None,
+            piece_label: None,
+            pattern_label: None,
+            internal_paths: Vec::new(),
+            placements: Vec::new(),
+            mirrored_pairs: false,
+        }
+    }
+
+    pub fn node(&self, entity: EntityRef) -> Option<&SeamNode> {
+        self.nodes.iter().find(|n| n.entity == entity)
+    }
+
+    /// تعديل إعدادات عقدة (تُنشأ عند أول تعديل وتُحذف إذا عادت للقيم الافتراضية)
+    pub fn set_node(&mut self, entity: EntityRef, width: Option<f64>, corner: Option<CornerType>) {
+        self.nodes.retain(|n| n.entity != entity);
+        if width.is_some() || corner.is_some() {
+            self.nodes.push(SeamNode { entity, width, corner });
+        }
+    }
+
+    pub fn notch(&self, entity: EntityRef) -> Option<&VNotch> {
+        self.notches.iter().find(|n| n.entity == entity)
+    }
+
+    pub fn notch_mut(&mut self, entity: EntityRef) -> Option<&mut VNotch> {
+        self.notches.iter_mut().find(|n| n.entity == entity)
+    }
+
+    /// إضافة علامة تطابق عند العقدة أو تغيير شكلها، أو حذفها عند None
+    pub fn set_notch(&mut self, entity: EntityRef, kind: Option<NotchType>) {
+        match (kind, self.notch_mut(entity)) {
+            (Some(kind), Some(existing)) => existing.kind = kind,
+            (Some(kind), None) => self.notches.push(VNotch::new(entity, kind)),
+            (None, _) => self.notches.retain(|n| n.entity != entity),
+        }
+    }
+
+    /// عرض الهامش الفعلي للقطعة التي تبدأ بالكيان `entity`
+    pub fn width_for(&self, entity: EntityRef) -> f64 {
+        self.node(entity).and_then(|n| n.width).unwrap_or(self.seam_allowance)
+    }
+
+    fn corner_for(&self, entity: EntityRef) -> CornerType {
+        self.node(entity).and_then(|n| n.corner).unwrap_or(self.corner)
+    }
+
+    /// مركز المستطيل المحيط بخط الخياطة: مرجع مواضع خط النسيج والملصقات
+    pub fn anchor(report: &ContourReport) -> Point2D {
+        Bounds::from_points(&report.outline()).map_or(Point2D::new(0.0, 0.0), |b| b.center())
+    }
+
+    /// حدود هامش الخياطة كمضلع مغلق، أو None إذا كان المسار غير مغلق
+    pub fn seam_allowance_outline(&self, report: &ContourReport) -> Option<Vec<Point2D>> {
+        self.seam_allowance_outline_at(report, CurveResolution::Segments(OFFSET_CURVE_SEGMENTS))
+    }
+
+    pub fn seam_allowance_outline_at(&self, report: &ContourReport, resolution: CurveResolution) -> Option<Vec<Point2D>> {
+        if !report.closed || report.segments.is_empty() {
+            return None;
+        }
+        let side = outward_side(report)?;
+
+        let seam: Vec<Vec<Point2D>> = report.segments.iter()
+            .map(|s| dedup(s.shape.flatten(resolution)))
+            .collect();
+        let widths: Vec<f64> = report.segments.iter().map(|s| self.width_for(s.entity)).collect();
+        let offsets: Vec<Vec<Point2D>> = seam.iter().zip(&widths)
+            .map(|(poly, &w)| offset_polyline(poly, w * side))
+            .collect();
+
+        let n = offsets.len();
+        let mut result = Vec::new();
+        for i in 0..n {
+            let next = (i + 1) % n;
+            let a = &offsets[i];
+            let b = &offsets[next];
+            if a.len() < 2 || b.len() < 2 {
+                continue;
+            }
+            // جسم القطعة بدون طرفيها (الطرفان يحددهما الركنان)
+            result.extend_from_slice(&a[1..a.len() - 1]);
+            let corner = self.corner_for(report.segments[next].entity);
+            let node = report.segments[i].shape.end();
+            let ctx = Corner {
+                a_prev: a[a.len() - 2],
+                a_end: a[a.len() - 1],
+                b_start: b[0],
+                b_next: b[1],
+                node,
+                seam_dir: direction(&seam[i][seam[i].len() - 2], &node),
+                width: widths[i].max(widths[next]),
+                side,
+            };
+            result.extend(ctx.resolve(corner));
+        }
+        Some(dedup(result))
+    }
+}
+
+/// معامل اتجاه العمود الخارجي: العمود الأيمن (dy, -dx) مضروباً فيه يشير لخارج القطعة
+/// ويعتمد على اتجاه دوران المسار
+pub fn outward_side(report: &ContourReport) -> Option<f64> {
+    match report.orientation {
+        Orientation::Clockwise => Some(1.0),
+        Orientation::CounterClockwise => Some(-1.0),
+        Orientation::Degenerate => None,
+    }
+}
+
+pub(crate) fn dedup(mut poly: Vec<Point2D>) -> Vec<Point2D> {
+    poly.dedup_by(|a, b| a.distance_to(b) < 1e-9);
+    poly
+}
+
+pub(crate) fn direction(a: &Point2D, b: &Point2D) -> Point2D {
+    let len = a.distance_to(b);
+    if len == 0.0 {
+        return Point2D::new(0.0, 0.0);
+    }
+    Point2D::new((b.x - a.x) / len, (b.y - a.y) / len)
+}
+
+/// إزاحة خط متعدد الأضلاع بمسافة `distance` على العمود الأيمن لكل ضلع
+/// (القيمة السالبة تعني الجهة الأخرى). الرؤوس الداخلية تُزاح على متوسط عمودي الضلعين
+pub fn offset_polyline(poly: &[Point2D], distance: f64) -> Vec<Point2D> {
+    let normal = |a: &Point2D, b: &Point2D| {
+        let d = direction(a, b);
+        Point2D::new(d.y, -d.x)
+    };
+    let n = poly.len();
+    if n < 2 {
+        return poly.to_vec();
+    }
+    (0..n).map(|i| {
+        let nv = if i == 0 {
+            normal(&poly[0], &poly[1])
+        } else if i == n - 1 {
+            normal(&poly[n - 2], &poly[n - 1])
+        } else {
+            let n1 = normal(&poly[i - 1], &poly[i]);
+            let n2 = normal(&poly[i], &poly[i + 1]);
+            let avg = Point2D::new(n1.x + n2.x, n1.y + n2.y);
+            let len = (avg.x * avg.x + avg.y * avg.y).sqrt();
+            // تعويض الزاوية حتى تبقى المسافة عن الضلعين ثابتة
+            let cos_half = (n1.x * avg.x + n1.y * avg.y) / len.max(1e-12);
+            if len < 1e-12 || cos_half < 1e-3 { n1 } else {
+                Point2D::new(avg.x / len / cos_half, avg.y / len / cos_half)
+            }
+        };
+        Point2D::new(poly[i].x + nv.x * distance, poly[i].y + nv.y * distance)
+    }).collect()
+}
+
+/// بيانات الركن بين نهاية إزاحة القطعة الحالية وبداية إزاحة التالية
+struct Corner {
+    a_prev: Point2D,
+    a_end: Point2D,
+    b_start: Point2D,
+    b_next: Point2D,
+    node: Point2D,
+    seam_dir: Point2D,
+    width: f64,
+    side: f64,
+}
+
+impl Corner {
+    fn resolve(&self, corner: CornerType) -> Vec<Point2D> {
+        // استمرار مماسي: لا يوجد ركن
+        if self.a_end.distance_to(&self.b_start) < 1e-6 {
+            return vec![self.a_end];
+        }
+        let sharp = line_intersection(&self.a_prev, &self.a_end, &self.b_start, &self.b_next);
+        let da = direction(&self.a_prev, &self.a_end);
+        let db = direction(&self.b_start, &self.b_next);
+        let convex = (da.x * db.y - da.y * db.x) * self.side > 0.0;
+        if !convex {
+            return sharp.map(|p| vec![p]).unwrap_or_else(|| vec![self.a_end, self.b_start]);
+        }
+
+        let fallback = vec![self.a_end, self.b_start];
+        match corner {
+            CornerType::Intersect => sharp.map(|p| vec![p]).unwrap_or(fallback),
+            CornerType::Extend => {
+                let pa = Point2D::new(self.a_end.x + da.x * self.width, self.a_end.y + da.y * self.width);
+                let pb = Point2D::new(self.b_start.x - db.x * self.width, self.b_start.y - db.y * self.width);
+                match sharp {
+                    Some(p) if p.distance_to(&self.node) <= pa.distance_to(&self.node) => vec![p],
+                    _ => vec![pa, pb],
+                }
+            }
+            CornerType::Mitre => {
+                let ua = direction(&self.node, &self.a_end);
+                let ub = direction(&self.node, &self.b_start);
+                let bis = direction(&Point2D::new(0.0, 0.0), &Point2D::new(ua.x + ub.x, ua.y + ub.y));
+                let cut = Point2D::new(self.node.x + bis.x * self.width, self.node.y + bis.y * self.width);
+                let cut_end = Point2D::new(cut.x - bis.y, cut.y + bis.x);
+                match sharp {
+                    Some(p) if p.distance_to(&self.node) <= self.width => vec![p],
+                    _ => match (
+                        line_intersection(&self.a_prev, &self.a_end, &cut, &cut_end),
+                        line_intersection(&self.b_start, &self.b_next, &cut, &cut_end),
+                    ) {
+                        (Some(pa), Some(pb)) => vec![pa, pb],
+                        _ => fallback,
+                    },
+                }
+            }
+            CornerType::Reverse => {
+                // انعكاس حافة الهامش التالية حول امتداد خط الخياطة السابق
+                let axis_end = Point2D::new(self.node.x + self.seam_dir.x, self.node.y + self.seam_dir.y);
+                let rb1 = self.b_start.reflect(&self.node, &axis_end);
+                let rb2 = self.b_next.reflect(&self.node, &axis_end);
+                match (
+                    line_intersection(&self.a_prev, &self.a_end, &rb1, &rb2),
+                    line_intersection(&rb1, &rb2, &self.b_start, &self.b_next),
+                ) {
+                    (Some(x), Some(y)) => vec![x, y],
+                    _ => sharp.map(|p| vec![p]).unwrap_or(fallback),
+                }
+            }
+            CornerType::Square => {
+                // العمودي على القطعة التالية عند العقدة يمر ببداية إزاحتها
+                match line_intersection(&self.a_prev, &self.a_end, &self.node, &self.b_start) {
+                    Some(x) => vec![x, self.b_start],
+                    None => fallback,
+                }
+            }
+        }
+    }
+}
+
+/// مسار SVG مغلق لمضلع
+pub fn polygon_path(poly: &[Point2D]) -> String {
+    let mut d = String::new();
+    for (i, p) in poly.iter().enumerate() {
+        d.push_str(&format!("{} {} {} ", if i == 0 { "M" } else { "L" }, p.x, p.y));
+    }
+    if !poly.is_empty() {
+        d.push('Z');
+    }
+    d
+}
+
+/// مسار SVG لمجموعة خطوط متعددة مفتوحة
+pub fn polylines_path(lines: &[Vec<Point2D>]) -> String {
+    let mut d = String::new();
+    for line in lines {
+        for (i, p) in line.iter().enumerate() {
+            d.push_str(&format!("{} {} {} ", if i == 0 { "M" } else { "L" }, p.x, p.y));
+        }
+    }
+    d.trim_end().to_string()
+}
+
+/// نص ملصق بعد حساب موضعه واستبدال متغيراته
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedText {
+    pub at: Point2D,
+    pub font_size: f64,
+    pub lines: Vec<String>,
+}
+
+/// كل ما يُرسم للقطعة في إحداثيات الرسم: خط الخياطة والهامش والعلامات والملصقات
+/// والمسارات الداخلية. هذا ما يُنقل للتخطيط (Layout) ويُصدّر
+#[derive(Debug, Clone, PartialEq)]
+pub struct PieceGeometry {
+    pub id: u32,
+    pub name: String,
+    pub seam: Vec<Point2D>,
+    pub allowance: Option<Vec<Point2D>>,
+    pub notches: Vec<Vec<Point2D>>,
+    pub grainline: Vec<Vec<Point2D>>,
+    pub labels: Vec<PlacedText>,
+    /// مسارات داخلية تُقص على القماش
+    pub cut_paths: Vec<Vec<Point2D>>,
+    /// مسارات داخلية للرسم فقط
+    pub draw_paths: Vec<Vec<Point2D>>,
+    pub placements: Vec<Vec<Point2D>>,
+    pub quantity: u32,
+    pub mirrored_pairs: bool,
+}
+
+impl PieceGeometry {
+    pub fn build(
+        piece: &VPiece,
+        reports: &[(u32, ContourReport)],
+        points: &[VPoint],
+        pattern: &PatternInfo,
+    ) -> Option<Self> {
+        Self::build_at(piece, reports, points, pattern, None)
+    }
+
+    /// مثل `build` لكن بدقة تقسيم محددة للمنحنيات (مثلاً دقة الراسم)
+    pub fn build_at(
+        piece: &VPiece,
+        reports: &[(u32, ContourReport)],
+        points: &[VPoint],
+        pattern: &PatternInfo,
+        resolution: Option<CurveResolution>,
+    ) -> Option<Self> {
+        let report_of = |id: u32| reports.iter().find(|(cid, _)| *cid == id).map(|(_, r)| r);
+        let report = report_of(piece.contour_id)?;
+        let anchor = VPiece::anchor(report);
+
+        let mut cut_paths = Vec::new();
+        let mut draw_paths = Vec::new();
+        for ip in &piece.internal_paths {
+            let Some(r) = report_of(ip.contour_id) else { continue };
+            let mut poly = resolution.map_or_else(|| r.outline(), |res| r.outline_at(res));
+            if r.closed && let Some(first) = poly.first().copied() {
+                poly.push(first);
+            }
+            if ip.cut_on_fabric { cut_paths.push(poly) } else { draw_paths.push(poly) }
+        }
+
+        Some(Self {
+            id: piece.metadata.id,
+            name: piece.metadata.name.clone(),
+            seam: resolution.map_or_else(|| report.outline(), |res| report.outline_at(res)),
+            allowance: match resolution {
+                Some(res) => piece.seam_allowance_outline_at(report, res),
+                None => piece.seam_allowance_outline(report),
+            },
+            notches: notch_marks(piece, report),
+            grainline: piece.grainline.as_ref().map(|g| g.arrow(&anchor, points)).unwrap_or_default(),
+            labels: [&piece.piece_label, &piece.pattern_label].into_iter()
+                .flatten()
+                .map(|l| PlacedText { at: l.position(&anchor), font_size: l.font_size, lines: l.lines(piece, pattern) })
+                .collect(),
+            cut_paths,
+            draw_paths,
+            placements: piece.placements.iter().flat_map(|pl| pl.shape(points)).collect(),
+            quantity: piece.cut_quantity,
+            mirrored_pairs: piece.mirrored_pairs,
+        })
+    }
+
+    /// حدود القص: حافة الهامش إن وجدت وإلا خط الخياطة
+    pub fn cut_outline(&self) -> &[Point2D] {
+        self.allowance.as_deref().unwrap_or(&self.seam)
+    }
+
+    /// زاوية خط النسيج بالدرجات (0 بدون خط نسيج)
+    pub fn grain_angle(&self) -> f64 {
+        match self.grainline.first().map(Vec::as_slice) {
+            Some([a, b, ..]) => a.angle_to(b),
+            _ => 0.0,
+        }
+    }
+}
+
+/// الحذف المتتالي للكيانات: إزالة كل مرجع لها من المسارات، ومن عقد القطع وعلاماتها،
+/// وحذف أزواج الحياكة التي يبدأ أو ينتهي جزء منها عند كيان محذوف
+pub fn detach_entities(contours: &mut [VContour], pieces: &mut [VPiece], pairs: &mut Vec<SeamPair>, removed: &[EntityRef]) {
+    if removed.is_empty() {
+        return;
+    }
+    for c in contours.iter_mut() {
+        c.entities.retain(|e| !removed.contains(e));
+    }
+    for p in pieces.iter_mut() {
+        p.nodes.retain(|n| !removed.contains(&n.entity));
+        p.notches.retain(|n| !removed.contains(&n.entity));
+    }
+    pairs.retain(|pair| {
+        [&pair.first, &pair.second].iter()
+            .all(|sub| !removed.contains(&sub.from) && !removed.contains(&sub.to))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contour::{order_contour, CONTOUR_TOLERANCE};
+    use crate::object::VLine;
+    use crate::seam_match::SubPath;
+
+    #[test]
+    fn detach_entities_removes_every_reference() {
+        let mut contour = VContour::new(10, "C");
+        contour.entities = vec![EntityRef::Line(1), EntityRef::Spline(2), EntityRef::Bisector(3)];
+        let mut other = VContour::new(11, "D");
+        other.entities = vec![EntityRef::Line(4)];
+        let mut contours = vec![contour, other];
+
+        let mut piece = VPiece::new(20, "P", 10, 10.0);
+        piece.set_node(EntityRef::Spline(2), Some(5.0), None);
+        piece.set_node(EntityRef::Bisector(3), Some(5.0), None);
+        piece.set_notch(EntityRef::Spline(2), Some(NotchType::default()));
+        piece.set_notch(EntityRef::Line(1), Some(NotchType::default()));
+        let mut pieces = vec![piece];
+
+        let keep = SeamPair {
+            id: 30,
+            name: "keep".into(),
+            first: SubPath { contour_id: 10, from: EntityRef::Line(1), to: EntityRef::Bisector(3) },
+            second: SubPath { contour_id: 11, from: EntityRef::Line(4), to: EntityRef::Line(4) },
+        };
+        let drop = SeamPair { id: 31, name: "drop".into(), second: SubPath { to: EntityRef::Spline(2), ..keep.first.clone() }, ..keep.clone() };
+        let mut pairs = vec![keep, drop];
+
+        detach_entities(&mut contours, &mut pieces, &mut pairs, &[EntityRef::Spline(2)]);
+
+        assert_eq!(contours[0].entities, [EntityRef::Line(1), EntityRef::Bisector(3)]);
+        assert_eq!(contours[1].entities, [EntityRef::Line(4)]);
+        assert_eq!(pieces[0].nodes.iter().map(|n| n.entity).collect::<Vec<_>>(), [EntityRef::Bisector(3)]);
+        assert_eq!(pieces[0].notches.iter().map(|n| n.entity).collect::<Vec<_>>(), [EntityRef::Line(1)]);
+        assert_eq!(pairs.iter().map(|p| p.id).collect::<Vec<_>>(), [30]);
+    }
+
+    /// مربع 100×100 مرسوم مع عقارب الساعة على الشاشة
+    fn square() -> ContourReport {
+        let points = vec![
+            VPoint::new(1, "A", 0.0, 0.0),
+            VPoint::new(2, "B", 100.0, 0.0),
+            VPoint::new(3, "C", 100.0, 100.0),
+            VPoint::new(4, "D", 0.0, 100.0),
+        ];
+        let lines = vec![VLine::new(5, "AB", 1, 2), VLine::new(6, "BC", 2, 3), VLine::new(7, "CD", 3, 4), VLine::new(8, "DA", 4, 1)];
+        let mut contour = VContour::new(9, "C");
+        contour.entities = lines.iter().map(|l| EntityRef::Line(l.metadata.id)).collect();
+        order_contour(&contour, &points, &lines, &[], &[], CONTOUR_TOLERANCE)
+    }
+
+    fn allowance(corner: CornerType) -> Vec<(f64, f64)> {
+        let mut piece = VPiece::new(10, "P", 9, 10.0);
+        piece.corner = corner;
+        // التقريب لأربعة منازل يزيل أخطاء الفاصلة العائمة
+        let round = |v: f64| (v * 1e4).round() / 1e4 + 0.0;
+        piece.seam_allowance_outline(&square()).unwrap().iter().map(|p| (round(p.x), round(p.y))).collect()
+    }
+
+    #[test]
+    fn square_corners_intersect_extend_and_reverse_meet_at_the_sharp_corner() {
+        let sharp = [(110.0, -10.0), (110.0, 110.0), (-10.0, 110.0), (-10.0, -10.0)];
+        assert_eq!(allowance(CornerType::Intersect), sharp);
+        // امتداد الحافتين بعرض الهامش يصل إلى الركن الحاد تماماً في الزاوية القائمة
+        assert_eq!(allowance(CornerType::Extend), sharp);
+        // انعكاس الحافة التالية حول خط خياطة عمودي عليها ينطبق عليها، فيبقى الركن الحاد
+        assert_eq!(allowance(CornerType::Reverse), sharp);
+    }
+
+    #[test]
+    fn square_corners_mitre_cut_at_the_allowance_width() {
+        // القص عمودي على المنصف على بعد 10 من العقدة: 10 - (10√2 - 10) = 4.1421
+        let d = 4.1421;
+        assert_eq!(allowance(CornerType::Mitre), [
+            (100.0 + d, -10.0), (110.0, -d),
+            (110.0, 100.0 + d), (100.0 + d, 110.0),
+            (-d, 110.0), (-10.0, 100.0 + d),
+            (-10.0, -d), (-d, -10.0),
+        ]);
+    }
+
+    #[test]
+    fn square_corners_square_join_the_offset_ends() {
+        // العمودي على القطعة التالية يوازي حافة الهامش السابقة، فيوصل طرفا الإزاحتين مباشرة
+        assert_eq!(allowance(CornerType::Square), [
+            (100.0, -10.0), (110.0, 0.0),
+            (110.0, 100.0), (100.0, 110.0),
+            (0.0, 110.0), (-10.0, 100.0),
+            (-10.0, 0.0), (0.0, -10.0),
+        ]);
+    }
+}
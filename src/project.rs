@@ -0,0 +1,300 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+use crate::geometry::{Point2D, Tolerance};
+use crate::object::{self, VPoint, VLine, VCubicBezier, VBisector, VContour, SelectedItem};
+use crate::operation::{self, VOperation};
+use crate::contour::{self, ContourReport};
+use crate::piece::{VPiece, PieceGeometry};
+use crate::annotation::PatternInfo;
+use crate::seam_match::SeamPair;
+use crate::layout::{Layout, LayoutOptions};
+use crate::measurements::{MeasurementTable, Grade};
+use crate::export::{ExportScene, ExportContent};
+use crate::spatial::CurveResolution;
+use crate::formula;
+use crate::val;
+use crate::error::{Error, Result};
+use crate::types::GOType;
+
+/// ملف المشروع (JSON) كما يحفظه البرنامج وأداة سطر الأوامر
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ProjectData {
+    pub points: Vec<VPoint>,
+    pub lines: Vec<VLine>,
+    pub splines: Vec<VCubicBezier>,
+    pub bisectors: Vec<VBisector>,
+    pub contours: Vec<VContour>,
+    #[serde(default)]
+    pub operations: Vec<VOperation>,
+    #[serde(default)]
+    pub pieces: Vec<VPiece>,
+    #[serde(default)]
+    pub pattern: PatternInfo,
+    #[serde(default)]
+    pub seam_pairs: Vec<SeamPair>,
+    #[serde(default)]
+    pub layout_options: LayoutOptions,
+    #[serde(default)]
+    pub layout: Layout,
+    #[serde(default)]
+    pub measurements: MeasurementTable,
+    /// سماحية فحص الحالات المنحلة في الأدوات (مم)
+    #[serde(default)]
+    pub tolerance: Tolerance,
+    pub next_id: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// مشكلة في المشروع مرتبطة بكائن (إن وُجد)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    pub object_id: Option<u32>,
+    pub message: String,
+}
+
+impl Issue {
+    fn error(object_id: Option<u32>, message: String) -> Self {
+        Self { severity: Severity::Error, object_id, message }
+    }
+
+    fn warning(object_id: Option<u32>, message: String) -> Self {
+        Self { severity: Severity::Warning, object_id, message }
+    }
+
+    fn formula(object_id: u32, owner: String, source: formula::FormulaError) -> Self {
+        Self::error(Some(object_id), Error::Formula { owner, source }.to_string())
+    }
+}
+
+/// هل المسار ملف Valentina (.val) بدلاً من مشروع JSON
+pub fn is_val_path(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("val"))
+}
+
+impl ProjectData {
+    pub fn from_json(text: &str) -> Result<Self> {
+        let mut project: Self = serde_json::from_str(text).map_err(|e| Error::parse("project", format!("invalid project: {}", e)))?;
+        // الملفات القديمة كانت تحفظ القطع بنوع Spline والعمليات بنوع Point
+        for piece in &mut project.pieces {
+            piece.metadata.obj_type = GOType::Piece;
+        }
+        for op in &mut project.operations {
+            op.metadata.obj_type = GOType::Operation;
+        }
+        Ok(project)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::parse("project", e))
+    }
+
+    /// تحميل مشروع JSON أو .val مع تحذيرات التحويل
+    pub fn load(path: &Path) -> Result<(Self, Vec<String>)> {
+        let text = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let loaded = if is_val_path(path) {
+            val::read_val(&text)
+        } else {
+            Self::from_json(&text).map(|p| (p, Vec::new()))
+        };
+        loaded.map_err(|e| e.in_file(path))
+    }
+
+    /// حفظ المشروع بحسب امتداد الملف؛ يعيد تحذيرات ما لا تدعمه صيغة .val
+    pub fn save(&self, path: &Path) -> Result<Vec<String>> {
+        let (text, warnings) = if is_val_path(path) { val::write_val(self) } else { (self.to_json()?, Vec::new()) };
+        fs::write(path, text).map_err(|e| Error::io(path, e))?;
+        Ok(warnings)
+    }
+
+    pub fn contour_reports(&self) -> Vec<(u32, ContourReport)> {
+        self.contours.iter()
+            .map(|c| (c.metadata.id, contour::order_contour(c, &self.points, &self.lines, &self.splines, &self.bisectors, contour::CONTOUR_TOLERANCE)))
+            .collect()
+    }
+
+    pub fn export_scene(&self, content: ExportContent) -> ExportScene {
+        ExportScene::build(&self.points, &self.lines, &self.splines, &self.bisectors, &self.pieces, &self.contour_reports(), &self.pattern, content)
+    }
+
+    pub fn piece_geometries(&self, resolution: Option<CurveResolution>) -> Vec<PieceGeometry> {
+        let reports = self.contour_reports();
+        self.pieces.iter()
+            .filter_map(|p| PieceGeometry::build_at(p, &reports, &self.points, &self.pattern, resolution))
+            .collect()
+    }
+
+    /// إعادة حساب النقاط والمنصفات ذات المعادلات من قيم القياسات ثم النقاط المشتقة من العمليات
+    /// النقاط ذات المعادلات الخاطئة تبقى في مكانها وتُرجع كأخطاء
+    pub fn recalculate(&mut self, values: &[(String, f64)]) -> Vec<Issue> {
+        let vars = |name: &str| values.iter().find(|(n, _)| n == name).map(|(_, v)| *v);
+        let mut issues = Vec::new();
+        for p in &mut self.points {
+            let Some(f) = &p.formula else { continue };
+            match (formula::eval(&f.x, &vars), formula::eval(&f.y, &vars)) {
+                (Ok(x), Ok(y)) => p.coords = Point2D::new(x, y),
+                (Err(e), _) => issues.push(Issue::formula(p.metadata.id, format!("point {}: x formula", p.metadata.name), e)),
+                (_, Err(e)) => issues.push(Issue::formula(p.metadata.id, format!("point {}: y formula", p.metadata.name), e)),
+            }
+        }
+        for b in &mut self.bisectors {
+            let Some(f) = &b.length_formula else { continue };
+            match formula::eval(f, &vars) {
+                Ok(v) => b.length = v,
+                Err(e) => issues.push(Issue::formula(b.metadata.id, format!("bisector {}: length formula", b.metadata.name), e)),
+            }
+        }
+        for (id, e) in operation::recalculate(&self.operations, &mut self.points, self.tolerance.0) {
+            issues.push(Issue::error(Some(id), format!("operation {}: {}", id, e)));
+        }
+        issues
+    }
+
+    /// نسخة من المشروع لكل مقاس وطول في جدول القياسات بعد إعادة حسابها، مع أخطاء معادلاتها
+    pub fn graded(&self) -> Vec<(Grade, ProjectData, Vec<Issue>)> {
+        self.measurements.grades().into_iter()
+            .map(|grade| {
+                let mut project = self.clone();
+                let issues = project.recalculate(&self.measurements.values(grade.size, grade.height));
+                (grade, project, issues)
+            })
+            .collect()
+    }
+
+    /// فحص سلامة المشروع: المعرفات والمراجع والمسارات والمعادلات (بقيم المقاس الأساسي)
+    pub fn validate(&self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        let ids: Vec<u32> = self.points.iter().map(|p| p.metadata.id)
+            .chain(self.lines.iter().map(|l| l.metadata.id))
+            .chain(self.splines.iter().map(|s| s.metadata.id))
+            .chain(self.bisectors.iter().map(|b| b.metadata.id))
+            .chain(self.contours.iter().map(|c| c.metadata.id))
+            .chain(self.operations.iter().map(|o| o.metadata.id))
+            .chain(self.pieces.iter().map(|p| p.metadata.id))
+            .chain(self.seam_pairs.iter().map(|p| p.id))
+            .collect();
+        let mut seen = HashSet::new();
+        for id in &ids {
+            if !seen.insert(*id) {
+                issues.push(Issue::error(Some(*id), format!("duplicate id {}", id)));
+            }
+        }
+        if let Some(max) = ids.iter().max() && *max >= self.next_id {
+            issues.push(Issue::error(None, format!("next_id {} is not above the highest id {}", self.next_id, max)));
+        }
+
+        let point_ids: HashSet<u32> = self.points.iter().map(|p| p.metadata.id).collect();
+        let mut check_points = |owner: u32, kind: &str, refs: &[u32]| {
+            for r in refs.iter().filter(|r| !point_ids.contains(r)) {
+                issues.push(Issue::error(Some(owner), format!("{} {} references missing point {}", kind, owner, r)));
+            }
+        };
+        for l in &self.lines {
+            check_points(l.metadata.id, "line", &[l.start_point_id, l.end_point_id]);
+        }
+        for s in &self.splines {
+            check_points(s.metadata.id, "spline", &[s.p1_id, s.p2_id, s.p3_id, s.p4_id]);
+        }
+        for b in &self.bisectors {
+            check_points(b.metadata.id, "bisector", &[b.p1_id, b.vertex_id, b.p3_id]);
+        }
+        for op in &self.operations {
+            let mapped: Vec<u32> = op.point_map.iter().flat_map(|(s, d)| [*s, *d]).collect();
+            check_points(op.metadata.id, "operation", &[op.reference_points(), mapped].concat());
+        }
+
+        let reports = self.contour_reports();
+        for (c, (_, report)) in self.contours.iter().zip(&reports) {
+            let id = c.metadata.id;
+            for m in &report.missing {
+                issues.push(Issue::error(Some(id), format!("contour {} references missing {}", c.metadata.name, m.label())));
+            }
+            if !report.gaps.is_empty() {
+                issues.push(Issue::warning(Some(id), format!("contour {} has {} gap(s)", c.metadata.name, report.gaps.len())));
+            }
+        }
+        for piece in &self.pieces {
+            let name = &piece.metadata.name;
+            match reports.iter().find(|(id, _)| *id == piece.contour_id) {
+                None => issues.push(Issue::error(Some(piece.metadata.id), format!("piece {} references missing contour {}", name, piece.contour_id))),
+                Some((_, r)) if !r.closed => issues.push(Issue::error(Some(piece.metadata.id), format!("piece {} contour is not closed", name))),
+                Some(_) => {}
+            }
+        }
+
+        let contour_ids: HashSet<u32> = self.contours.iter().map(|c| c.metadata.id).collect();
+        for pair in &self.seam_pairs {
+            for sub in [&pair.first, &pair.second] {
+                if !contour_ids.contains(&sub.contour_id) {
+                    issues.push(Issue::error(Some(pair.id), format!("seam pair {} references missing contour {}", pair.name, sub.contour_id)));
+                }
+            }
+        }
+        for (item, e) in object::degenerate_objects(&self.points, &self.lines, &self.splines, &self.bisectors, self.tolerance.0) {
+            let (kind, id) = match item {
+                SelectedItem::Line(id) => ("line", id),
+                SelectedItem::Spline(id) => ("spline", id),
+                SelectedItem::Bisector(id) => ("bisector", id),
+                _ => continue,
+            };
+            issues.push(Issue::error(Some(id), format!("{} {}: {}", kind, id, e)));
+        }
+
+        let piece_ids: HashSet<u32> = self.pieces.iter().map(|p| p.metadata.id).collect();
+        for pl in self.layout.placements.iter().filter(|pl| !piece_ids.contains(&pl.piece_id)) {
+            issues.push(Issue::warning(Some(pl.piece_id), format!("layout places missing piece {}", pl.piece_id)));
+        }
+
+        // المعادلات تُحسب على نسخة حتى لا يتغير المشروع أثناء الفحص
+        let values = self.measurements.values(None, None);
+        issues.extend(self.clone().recalculate(&values));
+        issues
+    }
+}
+
+/// هل توجد أخطاء (وليس تحذيرات فقط)
+pub fn has_errors(issues: &[Issue]) -> bool {
+    issues.iter().any(|i| i.severity == Severity::Error)
+}
+
+/// أول أخطاء الفحص كخطأ تحقق واحد (None إذا لم توجد إلا تحذيرات)
+pub fn validation_error(issues: &[Issue]) -> Option<Error> {
+    let errors: Vec<&str> = issues.iter().filter(|i| i.severity == Severity::Error).map(|i| i.message.as_str()).collect();
+    match errors.as_slice() {
+        [] => None,
+        [only] => Some(Error::Validation(only.to_string())),
+        [first, rest @ ..] => Some(Error::Validation(format!("{} (and {} more)", first, rest.len()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operation::OperationKind;
+
+    #[test]
+    fn legacy_object_types_are_normalized_on_load() {
+        let mut project = ProjectData::default();
+        project.pieces.push(VPiece::new(1, "Front", 2, 10.0));
+        project.operations.push(VOperation::new(3, "Move3", OperationKind::Move { length: 10.0, angle: 0.0 }, "_m"));
+        let json = project.to_json().unwrap();
+        assert!(json.contains(r#""obj_type": "Piece""#) && json.contains(r#""obj_type": "Operation""#));
+
+        // ملف محفوظ قبل إضافة النوعين
+        let legacy = json
+            .replace(r#""obj_type": "Piece""#, r#""obj_type": "Spline""#)
+            .replace(r#""obj_type": "Operation""#, r#""obj_type": "Point""#);
+        let loaded = ProjectData::from_json(&legacy).unwrap();
+        assert_eq!(loaded.pieces[0].metadata.obj_type, GOType::Piece);
+        assert_eq!(loaded.operations[0].metadata.obj_type, GOType::Operation);
+    }
+}
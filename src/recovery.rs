@@ -0,0 +1,174 @@
+//! الحفظ التلقائي واستعادة العمل غير المحفوظ بعد انهيار البرنامج
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use crate::project::ProjectData;
+use crate::error::{Error, Result};
+
+/// الفترة بين نسختي حفظ تلقائي
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+const RECOVERY_FILE: &str = "recovery.json";
+/// نسخة جلسة سابقة معروضة للاستعادة، منفصلة عن ملف الحفظ التلقائي الحالي
+const HELD_FILE: &str = "recovery.held.json";
+
+/// نسخة استعادة موجودة على القرص
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recovery {
+    pub path: PathBuf,
+    pub saved_at: SystemTime,
+}
+
+impl Recovery {
+    /// عمر النسخة بالدقائق (للعرض)
+    pub fn age_minutes(&self) -> u64 {
+        self.saved_at.elapsed().map_or(0, |d| d.as_secs() / 60)
+    }
+}
+
+/// مجلد الاستعادة (ملف واحد يُستبدل في كل حفظ تلقائي، وملف محجوز لنسخة الجلسة السابقة)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveryStore {
+    pub dir: PathBuf,
+}
+
+impl RecoveryStore {
+    /// المجلد الافتراضي حسب النظام، مثل ~/.local/share/valentina-oxidized/recovery
+    pub fn default_location() -> Option<Self> {
+        dirs::data_local_dir().map(|d| Self { dir: d.join("valentina-oxidized").join("recovery") })
+    }
+
+    fn file(&self) -> PathBuf {
+        self.dir.join(RECOVERY_FILE)
+    }
+
+    /// كتابة النسخة في ملف مؤقت ثم إعادة تسميته، حتى لا يبقى ملف ناقص إذا انهار البرنامج أثناء الكتابة
+    pub fn save(&self, project: &ProjectData) -> Result<()> {
+        fs::create_dir_all(&self.dir).map_err(|e| Error::io(&self.dir, e))?;
+        let tmp = self.dir.join(format!("{}.tmp", RECOVERY_FILE));
+        fs::write(&tmp, project.to_json()?).map_err(|e| Error::io(&tmp, e))?;
+        let file = self.file();
+        fs::rename(&tmp, &file).map_err(|e| Error::io(&file, e))
+    }
+
+    pub fn find(&self) -> Option<Recovery> {
+        let path = self.file();
+        let saved_at = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        Some(Recovery { path, saved_at })
+    }
+
+    /// نقل نسخة الجلسة السابقة جانباً عند البدء لتُعرض للاستعادة
+    /// فيستمر الحفظ التلقائي في الملف الأساسي دون أن يستبدلها ما دام القرار معلقاً
+    /// إذا وُجدت نسخة محجوزة من قبل (انهيار أثناء العرض) فالأحدث تحل محلها
+    pub fn hold(&self) -> Option<Recovery> {
+        let held = self.dir.join(HELD_FILE);
+        if self.find().is_some() {
+            fs::rename(self.file(), &held).ok()?;
+        }
+        let saved_at = fs::metadata(&held).and_then(|m| m.modified()).ok()?;
+        Some(Recovery { path: held, saved_at })
+    }
+
+    pub fn load(&self, recovery: &Recovery) -> Result<ProjectData> {
+        ProjectData::load(&recovery.path).map(|(project, _)| project)
+    }
+
+    /// حذف نسخة الحفظ التلقائي الحالية بعد حفظ سليم
+    pub fn clear(&self) -> Result<()> {
+        remove(&self.file())
+    }
+
+    /// حذف نسخة معروضة للاستعادة بعد استعادتها أو رفضها
+    pub fn discard(&self, recovery: &Recovery) -> Result<()> {
+        remove(&recovery.path)
+    }
+}
+
+fn remove(file: &Path) -> Result<()> {
+    match fs::remove_file(file) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Error::io(file, e)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::VPoint;
+
+    fn store() -> (tempfile::TempDir, RecoveryStore) {
+        let tmp = tempfile::tempdir().unwrap();
+        // مجلد غير موجود بعد: save ينشئه
+        let store = RecoveryStore { dir: tmp.path().join("recovery") };
+        (tmp, store)
+    }
+
+    #[test]
+    fn save_find_load_clear_round_trip() {
+        let (_tmp, store) = store();
+        assert_eq!(store.find(), None);
+
+        let mut project = ProjectData::default();
+        project.points.push(VPoint::new(1, "A", 10.0, 20.0));
+        project.next_id = 2;
+        store.save(&project).unwrap();
+
+        let recovery = store.find().expect("recovery file written");
+        assert_eq!(recovery.path, store.dir.join(RECOVERY_FILE));
+        assert_eq!(recovery.age_minutes(), 0);
+        let loaded = store.load(&recovery).unwrap();
+        assert_eq!(loaded.to_json().unwrap(), project.to_json().unwrap());
+
+        store.clear().unwrap();
+        assert_eq!(store.find(), None);
+        // الحذف مرة ثانية ليس خطأ
+        store.clear().unwrap();
+    }
+
+    #[test]
+    fn save_replaces_previous_copy_without_leftovers() {
+        let (_tmp, store) = store();
+        let mut project = ProjectData::default();
+        store.save(&project).unwrap();
+        project.points.push(VPoint::new(1, "A", 0.0, 0.0));
+        project.next_id = 2;
+        store.save(&project).unwrap();
+
+        let loaded = store.load(&store.find().unwrap()).unwrap();
+        assert_eq!(loaded.points.len(), 1);
+        let files: Vec<_> = fs::read_dir(&store.dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(files, [RECOVERY_FILE]);
+    }
+
+    #[test]
+    fn corrupt_recovery_file_is_a_parse_error() {
+        let (_tmp, store) = store();
+        fs::create_dir_all(&store.dir).unwrap();
+        fs::write(store.dir.join(RECOVERY_FILE), "{ truncated").unwrap();
+        let Err(err) = store.load(&store.find().unwrap()) else { panic!("corrupt file loaded") };
+        assert!(err.is_file_error());
+        assert!(err.to_string().contains(RECOVERY_FILE));
+    }
+
+    #[test]
+    fn held_copy_survives_autosaves_until_discarded() {
+        let (_tmp, store) = store();
+        assert_eq!(store.hold(), None);
+
+        let mut old = ProjectData::default();
+        old.points.push(VPoint::new(1, "Old", 0.0, 0.0));
+        old.next_id = 2;
+        store.save(&old).unwrap();
+        let held = store.hold().expect("previous session held aside");
+        assert_eq!(held.path, store.dir.join(HELD_FILE));
+        assert_eq!(store.find(), None);
+
+        // الحفظ التلقائي أثناء عرض الاستعادة يكتب في ملفه دون المساس بالنسخة المحجوزة
+        store.save(&ProjectData::default()).unwrap();
+        assert_eq!(store.load(&held).unwrap().points[0].metadata.name, "Old");
+        assert!(store.find().is_some());
+
+        store.discard(&held).unwrap();
+        assert!(!held.path.exists());
+        assert!(store.find().is_some());
+    }
+}
@@ -0,0 +1,324 @@
+//! واجهة برمجية للرسم بلغة Rhai: إنشاء النقاط والخطوط والمنحنيات والمنصفات وقراءة الأطوال والقياسات
+//! السكربت يعمل على نسخة من المشروع ولا يُطبق إلا إذا انتهى بدون أخطاء
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use rhai::{Dynamic, Engine, EvalAltResult, Position, Scope};
+use rhai::module_resolvers::DummyModuleResolver;
+use serde::Serialize;
+use crate::geometry::{self, Point2D};
+use crate::object::{VPoint, VLine, VCubicBezier, VBisector};
+use crate::formula::{self, PointFormula};
+use crate::project::ProjectData;
+
+/// حدود التنفيذ (حماية من الحلقات اللانهائية والذاكرة)
+pub const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_SIZE: usize = 10_000;
+
+/// خطأ السكربت مع موضعه في النص
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScriptError {
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl ScriptError {
+    fn at(pos: Position, message: String) -> Self {
+        Self { line: pos.line(), column: pos.position(), message }
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// ناتج تشغيل ناجح: ما طبعه السكربت ومعرفات الكائنات التي أنشأها
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ScriptReport {
+    pub log: Vec<String>,
+    pub created: Vec<u32>,
+}
+
+type Fail = Box<EvalAltResult>;
+
+/// حالة المستند أثناء التشغيل
+struct Doc {
+    project: ProjectData,
+    values: Vec<(String, f64)>,
+    report: ScriptReport,
+}
+
+impl Doc {
+    fn take_id(&mut self) -> u32 {
+        let id = self.project.next_id;
+        self.project.next_id += 1;
+        self.report.created.push(id);
+        id
+    }
+
+    fn point(&self, id: i64) -> Result<&VPoint, Fail> {
+        self.project.points.iter()
+            .find(|p| i64::from(p.metadata.id) == id)
+            .ok_or_else(|| format!("no point with id {}", id).into())
+    }
+
+    fn coords(&self, id: i64) -> Result<Point2D, Fail> {
+        self.point(id).map(|p| p.coords)
+    }
+
+    fn add_point(&mut self, name: &str, at: Point2D) -> i64 {
+        let id = self.take_id();
+        self.project.points.push(VPoint::new(id, name, at.x, at.y));
+        id.into()
+    }
+}
+
+fn number(v: &Dynamic) -> Result<f64, Fail> {
+    if let Ok(f) = v.as_float() {
+        return Ok(f);
+    }
+    v.as_int().map(|i| i as f64).map_err(|t| format!("expected a number, got {}", t).into())
+}
+
+fn to_id(v: i64) -> Result<u32, Fail> {
+    u32::try_from(v).map_err(|_| format!("invalid id {}", v).into())
+}
+
+/// محرك Rhai مقيد: بدون استيراد ملفات وبحدود للعمليات والأحجام، مع دوال المستند
+fn engine(doc: &Rc<RefCell<Doc>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(64);
+    engine.set_max_expr_depths(64, 64);
+    engine.set_max_string_size(MAX_SIZE);
+    engine.set_max_array_size(MAX_SIZE);
+    engine.set_max_map_size(MAX_SIZE);
+
+    let d = doc.clone();
+    engine.on_print(move |s| d.borrow_mut().report.log.push(s.to_string()));
+    let d = doc.clone();
+    engine.on_debug(move |s, _, pos| d.borrow_mut().report.log.push(format!("[{}] {}", pos, s)));
+
+    // نقاط
+    let d = doc.clone();
+    engine.register_fn("point", move |name: &str, x: Dynamic, y: Dynamic| -> Result<i64, Fail> {
+        let at = Point2D::new(number(&x)?, number(&y)?);
+        Ok(d.borrow_mut().add_point(name, at))
+    });
+    let d = doc.clone();
+    engine.register_fn("point_formula", move |name: &str, x: &str, y: &str| -> Result<i64, Fail> {
+        let mut doc = d.borrow_mut();
+        let values = &doc.values;
+        let vars = |n: &str| values.iter().find(|(k, _)| k == n).map(|(_, v)| *v);
+        let px = formula::eval(x, &vars).map_err(|e| format!("x formula: {}", e))?;
+        let py = formula::eval(y, &vars).map_err(|e| format!("y formula: {}", e))?;
+        let id = doc.add_point(name, Point2D::new(px, py));
+        if let Some(p) = doc.project.points.last_mut() {
+            p.formula = Some(PointFormula { x: x.to_string(), y: y.to_string() });
+        }
+        Ok(id)
+    });
+    let d = doc.clone();
+    engine.register_fn("polar", move |name: &str, from: i64, length: Dynamic, angle: Dynamic| -> Result<i64, Fail> {
+        let at = d.borrow().coords(from)?.point_at(number(&length)?, number(&angle)?);
+        Ok(d.borrow_mut().add_point(name, at))
+    });
+    let d = doc.clone();
+    engine.register_fn("along", move |name: &str, from: i64, to: i64, length: Dynamic| -> Result<i64, Fail> {
+        let (a, b) = (d.borrow().coords(from)?, d.borrow().coords(to)?);
+        let angle = a.direction_to(&b, d.borrow().project.tolerance.0).map_err(|e| format!("along: {}", e))?;
+        let at = a.point_at(number(&length)?, angle);
+        Ok(d.borrow_mut().add_point(name, at))
+    });
+
+    // خطوط ومنحنيات ومنصفات: الكائن المنحل (ضمن سماحية المستند) يوقف السكربت بخطأ
+    // المعرف يُحجز بعد التحقق، فالخطأ الذي يلتقطه السكربت بـ try/catch لا يترك معرفاً وهمياً
+    let d = doc.clone();
+    engine.register_fn("line", move |a: i64, b: i64| -> Result<i64, Fail> {
+        let mut doc = d.borrow_mut();
+        let id = doc.project.next_id;
+        let l = VLine::new(id, &format!("L{}", id), to_id(a)?, to_id(b)?);
+        l.check(doc.point(a)?, doc.point(b)?, doc.project.tolerance.0).map_err(|e| format!("line: {}", e))?;
+        doc.take_id();
+        doc.project.lines.push(l);
+        Ok(id.into())
+    });
+    let d = doc.clone();
+    engine.register_fn("spline", move |p1: i64, p2: i64, p3: i64, p4: i64| -> Result<i64, Fail> {
+        let mut doc = d.borrow_mut();
+        let id = doc.project.next_id;
+        let s = VCubicBezier::new(id, &format!("S{}", id), to_id(p1)?, to_id(p2)?, to_id(p3)?, to_id(p4)?);
+        s.check(doc.point(p1)?, doc.point(p2)?, doc.point(p3)?, doc.point(p4)?, doc.project.tolerance.0)
+            .map_err(|e| format!("spline: {}", e))?;
+        doc.take_id();
+        doc.project.splines.push(s);
+        Ok(id.into())
+    });
+    let d = doc.clone();
+    engine.register_fn("bisector", move |p1: i64, vertex: i64, p3: i64, length: Dynamic| -> Result<i64, Fail> {
+        let mut doc = d.borrow_mut();
+        let length = number(&length)?;
+        let id = doc.project.next_id;
+        let b = VBisector::new(id, &format!("B{}", id), to_id(p1)?, to_id(vertex)?, to_id(p3)?, length);
+        b.calculate_end_point(doc.point(p1)?, doc.point(vertex)?, doc.point(p3)?, doc.project.tolerance.0)
+            .map_err(|e| format!("bisector: {}", e))?;
+        doc.take_id();
+        doc.project.bisectors.push(b);
+        Ok(id.into())
+    });
+
+    // استعلامات
+    let d = doc.clone();
+    engine.register_fn("x", move |id: i64| -> Result<f64, Fail> { Ok(d.borrow().coords(id)?.x) });
+    let d = doc.clone();
+    engine.register_fn("y", move |id: i64| -> Result<f64, Fail> { Ok(d.borrow().coords(id)?.y) });
+    let d = doc.clone();
+    engine.register_fn("distance", move |a: i64, b: i64| -> Result<f64, Fail> {
+        let doc = d.borrow();
+        Ok(doc.coords(a)?.distance_to(&doc.coords(b)?))
+    });
+    let d = doc.clone();
+    engine.register_fn("length", move |id: i64| -> Result<f64, Fail> {
+        let doc = d.borrow();
+        let p = &doc.project;
+        if let Some(l) = p.lines.iter().find(|l| i64::from(l.metadata.id) == id) {
+            return Ok(doc.coords(l.start_point_id.into())?.distance_to(&doc.coords(l.end_point_id.into())?));
+        }
+        if let Some(s) = p.splines.iter().find(|s| i64::from(s.metadata.id) == id) {
+            let mut c = [Point2D::new(0.0, 0.0); 4];
+            for (slot, pid) in c.iter_mut().zip([s.p1_id, s.p2_id, s.p3_id, s.p4_id]) {
+                *slot = doc.coords(pid.into())?;
+            }
+            return Ok(geometry::cubic_length(&c[0], &c[1], &c[2], &c[3]));
+        }
+        if let Some(b) = p.bisectors.iter().find(|b| i64::from(b.metadata.id) == id) {
+            return Ok(b.length);
+        }
+        Err(format!("no line, spline or bisector with id {}", id).into())
+    });
+    let d = doc.clone();
+    engine.register_fn("m", move |name: &str| -> Result<f64, Fail> {
+        d.borrow().values.iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| *v)
+            .ok_or_else(|| format!("unknown measurement '{}'", name).into())
+    });
+    engine
+}
+
+/// تشغيل سكربت على المشروع بقيم القياسات المعطاة؛ المشروع لا يتغير عند الخطأ
+pub fn run_script(project: &mut ProjectData, source: &str, values: &[(String, f64)]) -> Result<ScriptReport, ScriptError> {
+    let doc = Rc::new(RefCell::new(Doc { project: project.clone(), values: values.to_vec(), report: ScriptReport::default() }));
+    let engine = engine(&doc);
+    let ast = engine.compile(source)
+        .map_err(|e| ScriptError::at(e.position(), e.err_type().to_string()))?;
+    engine.run_ast_with_scope(&mut Scope::new(), &ast)
+        .map_err(|e| ScriptError::at(e.position(), error_message(&e)))?;
+    drop(engine);
+    let doc = Rc::try_unwrap(doc).map_err(|_| ScriptError::at(Position::NONE, "script state still in use".to_string()))?.into_inner();
+    *project = doc.project;
+    Ok(doc.report)
+}
+
+/// رسالة الخطأ بدون الموضع (يُعرض منفصلاً) ومع السبب الأصلي لأخطاء الدوال
+fn error_message(e: &EvalAltResult) -> String {
+    match e {
+        EvalAltResult::ErrorInFunctionCall(name, _, inner, _) => format!("in {}: {}", name, error_message(inner)),
+        EvalAltResult::ErrorRuntime(v, _) => v.to_string(),
+        other => {
+            let mut text = other.to_string();
+            // to_string يضيف "(line N, position M)" في النهاية
+            if let Some(i) = text.rfind(" (line ") {
+                text.truncate(i);
+            }
+            text
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> ProjectData {
+        let mut project = ProjectData::default();
+        project.points.push(VPoint::new(1, "A", 0.0, 0.0));
+        project.points.push(VPoint::new(2, "B", 100.0, 0.0));
+        project.next_id = 3;
+        project
+    }
+
+    fn run(source: &str) -> (ProjectData, Result<ScriptReport, ScriptError>) {
+        let mut project = project();
+        let result = run_script(&mut project, source, &[("waist".to_string(), 80.0)]);
+        (project, result)
+    }
+
+    fn error(source: &str) -> ScriptError {
+        let (project, result) = run(source);
+        // المشروع لا يتغير عند أي خطأ
+        assert_eq!(project.to_json().unwrap(), self::project().to_json().unwrap(), "{source}");
+        let Err(err) = result else { panic!("script succeeded: {source}") };
+        err
+    }
+
+    #[test]
+    fn creates_objects_with_fresh_ids() {
+        let (project, result) = run("let c = point(\"C\", m(\"waist\"), 50);\nline(1, c);\nprint(x(c));");
+        let report = result.unwrap();
+        assert_eq!(report.created, [3, 4]);
+        assert_eq!(report.log, ["80.0"]);
+        assert_eq!(project.next_id, 5);
+        assert_eq!(project.lines[0].end_point_id, 3);
+    }
+
+    #[test]
+    fn caught_errors_leave_no_phantom_ids() {
+        let (project, result) = run(r#"
+            try { line(1, 1); } catch (e) { print(e); }
+            try { bisector(1, 1, 2, 10); } catch (e) { print(e); }
+            try { spline(1, 1, 1, 1); } catch (e) { print(e); }
+            line(1, 2);
+        "#);
+        let report = result.unwrap();
+        assert_eq!(report.created, [3]);
+        assert_eq!(report.log.len(), 3);
+        assert_eq!(project.next_id, 4);
+        assert_eq!(project.lines.len(), 1);
+        assert!(project.splines.is_empty() && project.bisectors.is_empty());
+    }
+
+    #[test]
+    fn runtime_error_reports_line_and_column() {
+        let err = error("let c = point(\"C\", 10, 10);\n    line(c, 99);");
+        assert_eq!((err.line, err.column), (Some(2), Some(5)));
+        assert!(err.message.contains("no point with id 99"), "{}", err.message);
+        assert!(err.to_string().starts_with("line 2: "));
+    }
+
+    #[test]
+    fn compile_error_is_reported_before_running() {
+        let err = error("point(\"C\", 1, 1);\nlet = ;");
+        assert_eq!(err.line, Some(2));
+    }
+
+    #[test]
+    fn endless_loop_is_stopped() {
+        let err = error("point(\"C\", 1, 1);\nloop {}");
+        assert!(err.message.to_lowercase().contains("operations"), "{}", err.message);
+    }
+
+    #[test]
+    fn imports_are_rejected() {
+        let err = error("import \"std\" as s;\npoint(\"C\", 1, 1);");
+        assert_eq!(err.line, Some(1));
+        assert!(err.message.contains("std"), "{}", err.message);
+    }
+}
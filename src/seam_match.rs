@@ -0,0 +1,67 @@
+use crate::object::EntityRef;
+use crate::contour::ContourReport;
+use serde::{Serialize, Deserialize};
+
+/// جزء من مسار: من الكيان `from` حتى الكيان `to` (شاملاً) بترتيب المسار المرتب
+/// في المسار المغلق يلتف الجزء من النهاية للبداية إذا لزم
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubPath {
+    pub contour_id: u32,
+    pub from: EntityRef,
+    pub to: EntityRef,
+}
+
+impl SubPath {
+    /// المسار كاملاً
+    pub fn whole(contour_id: u32, report: &ContourReport) -> Option<Self> {
+        Some(Self {
+            contour_id,
+            from: report.segments.first()?.entity,
+            to: report.segments.last()?.entity,
+        })
+    }
+
+    /// طول الجزء، أو None إذا لم يعد أحد طرفيه في المسار
+    pub fn length(&self, report: &ContourReport) -> Option<f64> {
+        let n = report.segments.len();
+        let start = report.segments.iter().position(|s| s.entity == self.from)?;
+        let end = report.segments.iter().position(|s| s.entity == self.to)?;
+        if end < start && !report.closed {
+            return None;
+        }
+        let count = (end + n - start) % n + 1;
+        Some((0..count).map(|i| report.segments[(start + i) % n].shape.length()).sum())
+    }
+}
+
+/// زوج من الحياكات المتقابلة (مثل جنب الأمام والخلف أو رأس الكم وحردة الإبط)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeamPair {
+    pub id: u32,
+    pub name: String,
+    pub first: SubPath,
+    pub second: SubPath,
+}
+
+/// نتيجة المقارنة: الفرق موجب إذا كان الجزء الثاني أطول
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeamMatch {
+    pub first_length: f64,
+    pub second_length: f64,
+    pub difference: f64,
+    /// نسبة الفرق (Ease) إلى طول الجزء الأول
+    pub ease_percent: f64,
+}
+
+impl SeamPair {
+    pub fn compare(&self, reports: &[(u32, ContourReport)]) -> Option<SeamMatch> {
+        let length = |sp: &SubPath| {
+            reports.iter().find(|(id, _)| *id == sp.contour_id).and_then(|(_, r)| sp.length(r))
+        };
+        let first_length = length(&self.first)?;
+        let second_length = length(&self.second)?;
+        let difference = second_length - first_length;
+        let ease_percent = if first_length > 0.0 { difference / first_length * 100.0 } else { 0.0 };
+        Some(SeamMatch { first_length, second_length, difference, ease_percent })
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+use crate::geometry::Point2D;
+use crate::object::{VPoint, VLine, VCubicBezier, VBisector, VContour, SelectedItem};
+use crate::piece::VPiece;
+use crate::spatial::{SpatialIndex, Bounds, Shape};
+
+/// مجموعة العناصر المختارة (تحافظ على ترتيب الاختيار)
+/// مع مجموعة موازية لفحص العضوية في O(1) أثناء الرسم
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Selection {
+    items: Vec<SelectedItem>,
+    members: HashSet<SelectedItem>,
+}
+
+impl Selection {
+    pub fn items(&self) -> &[SelectedItem] {
+        &self.items
+    }
+    pub fn len(&self) -> usize { self.items.len() }
+    pub fn is_empty(&self) -> bool { self.items.is_empty() }
+    pub fn contains(&self, item: &SelectedItem) -> bool {
+        self.members.contains(item)
+    }
+
+    /// العنصر الوحيد المختار (إن كان الاختيار عنصراً واحداً فقط)
+    pub fn single(&self) -> Option<SelectedItem> {
+        match self.items.as_slice() {
+            [item] => Some(item.clone()),
+            _ => None,
+        }
+    }
+
+    /// نقرة عادية: استبدال الاختيار بالكامل بعنصر واحد
+    pub fn set_single(&mut self, item: SelectedItem) {
+        self.replace(vec![item]);
+    }
+
+    /// نقرة مع Shift: إضافة العنصر أو إزالته
+    pub fn toggle(&mut self, item: SelectedItem) {
+        if self.members.remove(&item) {
+            self.items.retain(|i| *i != item);
+        } else {
+            self.members.insert(item.clone());
+            self.items.push(item);
+        }
+    }
+
+    /// إضافة عناصر بدون تكرار
+    pub fn extend(&mut self, items: Vec<SelectedItem>) {
+        for item in items {
+            if self.members.insert(item.clone()) {
+                self.items.push(item);
+            }
+        }
+    }
+
+    pub fn replace(&mut self, items: Vec<SelectedItem>) {
+        self.clear();
+        self.extend(items);
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.members.clear();
+    }
+
+    /// عدد العناصر من كل نوع: (نقاط، خطوط، منحنيات، منصفات، مسارات، قطع)
+    pub fn counts(&self) -> (usize, usize, usize, usize, usize, usize) {
+        let mut c = (0, 0, 0, 0, 0, 0);
+        for item in &self.items {
+            match item {
+                SelectedItem::Point(_) => c.0 += 1,
+                SelectedItem::Line(_) => c.1 += 1,
+                SelectedItem::Spline(_) => c.2 += 1,
+                SelectedItem::Bisector(_) => c.3 += 1,
+                SelectedItem::Contour(_) => c.4 += 1,
+                SelectedItem::Piece(_) => c.5 += 1,
+                SelectedItem::None => {}
+            }
+        }
+        c
+    }
+}
+
+/// مستطيل الاختيار بالسحب (Rubber-band)
+/// السحب من اليسار لليمين = Window (العنصر يجب أن يكون داخل المستطيل بالكامل)
+/// السحب من اليمين لليسار = Crossing (يكفي أن يلمس العنصر المستطيل)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RubberBand {
+    pub start: Point2D,
+    pub end: Point2D,
+}
+
+impl RubberBand {
+    pub fn new(start: Point2D) -> Self {
+        Self { start, end: start }
+    }
+
+    pub fn is_window(&self) -> bool {
+        self.end.x >= self.start.x
+    }
+
+    /// (min, max) للمستطيل بعد ترتيب الأركان
+    pub fn bounds(&self) -> (Point2D, Point2D) {
+        (
+            Point2D::new(self.start.x.min(self.end.x), self.start.y.min(self.end.y)),
+            Point2D::new(self.start.x.max(self.end.x), self.start.y.max(self.end.y)),
+        )
+    }
+
+    fn contains(&self, p: &Point2D) -> bool {
+        let (min, max) = self.bounds();
+        p.x >= min.x && p.x <= max.x && p.y >= min.y && p.y <= max.y
+    }
+
+    /// هل الخط المتعدد داخل المستطيل (Window) أو يتقاطع معه (Crossing)
+    fn matches_polyline(&self, poly: &[Point2D]) -> bool {
+        if self.is_window() {
+            return poly.iter().all(|p| self.contains(p));
+        }
+        if poly.iter().any(|p| self.contains(p)) {
+            return true;
+        }
+        let (min, max) = self.bounds();
+        let corners = [
+            min,
+            Point2D::new(max.x, min.y),
+            max,
+            Point2D::new(min.x, max.y),
+        ];
+        poly.windows(2).any(|seg| {
+            (0..4).any(|i| segments_intersect(&seg[0], &seg[1], &corners[i], &corners[(i + 1) % 4]))
+        })
+    }
+}
+
+/// اختبار تقاطع قطعتين مستقيمتين (تقاطع فعلي وليس مجرد تلامس)
+fn segments_intersect(a: &Point2D, b: &Point2D, c: &Point2D, d: &Point2D) -> bool {
+    let cross = |o: &Point2D, p: &Point2D, q: &Point2D| (p.x - o.x) * (q.y - o.y) - (p.y - o.y) * (q.x - o.x);
+    let d1 = cross(c, d, a);
+    let d2 = cross(c, d, b);
+    let d3 = cross(a, b, c);
+    let d4 = cross(a, b, d);
+    ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0)) && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+}
+
+/// كل العناصر التي يلتقطها مستطيل الاختيار
+pub fn band_select(band: &RubberBand, index: &SpatialIndex) -> Vec<SelectedItem> {
+    let (min, max) = band.bounds();
+    index.query(&Bounds::new(min, max))
+        .into_iter()
+        .filter(|s| match s.shape {
+            Shape::Point(p) => band.contains(&p),
+            _ => band.matches_polyline(&s.shape.polyline(32)),
+        })
+        .map(|s| s.item.clone())
+        .collect()
+}
+
+/// كل العناصر الموجودة في المشروع
+pub fn all_items(
+    points: &[VPoint],
+    lines: &[VLine],
+    splines: &[VCubicBezier],
+    bisectors: &[VBisector],
+    contours: &[VContour],
+    pieces: &[VPiece],
+) -> Vec<SelectedItem> {
+    points.iter().map(|p| SelectedItem::Point(p.metadata.id))
+        .chain(lines.iter().map(|l| SelectedItem::Line(l.metadata.id)))
+        .chain(splines.iter().map(|s| SelectedItem::Spline(s.metadata.id)))
+        .chain(bisectors.iter().map(|b| SelectedItem::Bisector(b.metadata.id)))
+        .chain(contours.iter().map(|c| SelectedItem::Contour(c.metadata.id)))
+        .chain(pieces.iter().map(|p| SelectedItem::Piece(p.metadata.id)))
+        .collect()
+}
@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use crate::geometry::{Point2D, distance_to_cubic, flatten_cubic, cubic_length};
+use crate::object::{VPoint, VLine, VCubicBezier, VBisector, SelectedItem};
+
+/// فهرس من المعرف إلى موقع العنصر في المخزن (بحث O(1) بدلاً من iter().find)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IdIndex {
+    map: HashMap<u32, usize>,
+}
+
+impl IdIndex {
+    pub fn build<T>(items: &[T], id_of: impl Fn(&T) -> u32) -> Self {
+        Self { map: items.iter().enumerate().map(|(i, item)| (id_of(item), i)).collect() }
+    }
+
+    /// العنصر صاحب المعرف داخل نفس المخزن الذي بُني منه الفهرس
+    pub fn get<'a, T>(&self, items: &'a [T], id: u32) -> Option<&'a T> {
+        self.map.get(&id).and_then(|&i| items.get(i))
+    }
+
+    /// نسخة قابلة للتعديل من get
+    pub fn get_mut<'a, T>(&self, items: &'a mut [T], id: u32) -> Option<&'a mut T> {
+        self.map.get(&id).and_then(|&i| items.get_mut(i))
+    }
+
+    /// موقع العنصر في المخزن
+    pub fn position(&self, id: u32) -> Option<usize> {
+        self.map.get(&id).copied()
+    }
+}
+
+/// مستطيل محاذٍ للمحاور (Axis-Aligned Bounding Box)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Point2D,
+    pub max: Point2D,
+}
+
+impl Bounds {
+    pub fn new(min: Point2D, max: Point2D) -> Self {
+        Self { min, max }
+    }
+
+    pub fn around(p: &Point2D, radius: f64) -> Self {
+        Self {
+            min: Point2D::new(p.x - radius, p.y - radius),
+            max: Point2D::new(p.x + radius, p.y + radius),
+        }
+    }
+
+    /// المستطيل المحيط بالنقاط، أو None لقائمة فارغة
+    pub fn from_points(pts: &[Point2D]) -> Option<Self> {
+        let (first, rest) = pts.split_first()?;
+        Some(rest.iter().fold(Bounds::new(*first, *first), |b, p| b.include(p)))
+    }
+
+    /// توسيع المستطيل ليشمل نقطة
+    pub fn include(&self, p: &Point2D) -> Bounds {
+        Bounds::new(
+            Point2D::new(self.min.x.min(p.x), self.min.y.min(p.y)),
+            Point2D::new(self.max.x.max(p.x), self.max.y.max(p.y)),
+        )
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        self.include(&other.min).include(&other.max)
+    }
+
+    pub fn intersects(&self, other: &Bounds) -> bool {
+        self.min.x <= other.max.x && self.max.x >= other.min.x
+            && self.min.y <= other.max.y && self.max.y >= other.min.y
+    }
+
+    pub fn center(&self) -> Point2D {
+        Point2D::new((self.min.x + self.max.x) / 2.0, (self.min.y + self.max.y) / 2.0)
+    }
+}
+
+/// دقة تقسيم المنحنيات إلى خطوط
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CurveResolution {
+    /// عدد ثابت من القطع لكل منحنى
+    Segments(usize),
+    /// أقصى انحراف مسموح عن المنحنى بوحدات الرسم
+    Tolerance(f64),
+}
+
+/// الشكل الهندسي المحسوب لكل عنصر (بعد حل معرفات النقاط)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Point(Point2D),
+    Segment(Point2D, Point2D),
+    Cubic([Point2D; 4]),
+}
+
+impl Shape {
+    pub fn bounds(&self) -> Bounds {
+        match self {
+            Shape::Point(p) => Bounds::new(*p, *p),
+            Shape::Segment(a, b) => Bounds::new(*a, *a).include(b),
+            // المنحنى محصور داخل الغلاف المحدب لنقاط التحكم
+            Shape::Cubic([p0, rest @ ..]) => rest.iter().fold(Bounds::new(*p0, *p0), |b, p| b.include(p)),
+        }
+    }
+
+    pub fn distance_to(&self, at: &Point2D) -> f64 {
+        match self {
+            Shape::Point(p) => at.distance_to(p),
+            Shape::Segment(a, b) => at.distance_to_segment(a, b),
+            Shape::Cubic([p0, p1, p2, p3]) => distance_to_cubic(at, p0, p1, p2, p3),
+        }
+    }
+
+    pub fn polyline(&self, segments: usize) -> Vec<Point2D> {
+        match self {
+            Shape::Point(p) => vec![*p],
+            Shape::Segment(a, b) => vec![*a, *b],
+            Shape::Cubic([p0, p1, p2, p3]) => flatten_cubic(p0, p1, p2, p3, segments),
+        }
+    }
+
+    /// عدد القطع اللازم ليبقى الانحراف عن المنحنى ضمن الدقة المطلوبة
+    pub fn segments_for(&self, resolution: CurveResolution) -> usize {
+        match (self, resolution) {
+            (Shape::Cubic(_), CurveResolution::Segments(n)) => n.max(1),
+            (Shape::Cubic([p0, p1, p2, p3]), CurveResolution::Tolerance(tol)) => {
+                // الانحراف <= max|B''| / (8 n²) و max|B''| <= 6 max(|p0 - 2p1 + p2|, |p1 - 2p2 + p3|)
+                let second = |a: &Point2D, b: &Point2D, c: &Point2D| {
+                    Point2D::new(a.x - 2.0 * b.x + c.x, a.y - 2.0 * b.y + c.y).distance_to(&Point2D::new(0.0, 0.0))
+                };
+                let m = 6.0 * second(p0, p1, p2).max(second(p1, p2, p3));
+                ((m / (8.0 * tol.max(1e-6))).sqrt().ceil() as usize).clamp(1, 4096)
+            }
+            _ => 1,
+        }
+    }
+
+    pub fn flatten(&self, resolution: CurveResolution) -> Vec<Point2D> {
+        self.polyline(self.segments_for(resolution))
+    }
+
+    pub fn start(&self) -> Point2D {
+        match self {
+            Shape::Point(p) | Shape::Segment(p, _) => *p,
+            Shape::Cubic(c) => c[0],
+        }
+    }
+
+    pub fn end(&self) -> Point2D {
+        match self {
+            Shape::Point(p) | Shape::Segment(_, p) => *p,
+            Shape::Cubic(c) => c[3],
+        }
+    }
+
+    /// الطول الفعلي (طول القوس للمنحنيات)
+    pub fn length(&self) -> f64 {
+        match self {
+            Shape::Point(_) => 0.0,
+            Shape::Segment(a, b) => a.distance_to(b),
+            Shape::Cubic([p0, p1, p2, p3]) => cubic_length(p0, p1, p2, p3),
+        }
+    }
+
+    /// نفس الشكل بالاتجاه المعاكس
+    pub fn reversed(&self) -> Shape {
+        match self {
+            Shape::Point(p) => Shape::Point(*p),
+            Shape::Segment(a, b) => Shape::Segment(*b, *a),
+            Shape::Cubic([p0, p1, p2, p3]) => Shape::Cubic([*p3, *p2, *p1, *p0]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedShape {
+    pub item: SelectedItem,
+    pub shape: Shape,
+    pub bounds: Bounds,
+}
+
+/// أقصى عدد أبناء لكل عقدة في شجرة R
+const NODE_CAPACITY: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+enum RNode {
+    Leaf { bounds: Bounds, entries: Vec<usize> },
+    Branch { bounds: Bounds, children: Vec<RNode> },
+}
+
+impl RNode {
+    fn bounds(&self) -> &Bounds {
+        match self {
+            RNode::Leaf { bounds, .. } | RNode::Branch { bounds, .. } => bounds,
+        }
+    }
+}
+
+/// ترتيب العناصر في شرائح (Sort-Tile-Recursive): فرز بالمحور X ثم تقسيم كل شريحة بالمحور Y
+fn str_pack<T>(mut items: Vec<T>, center: impl Fn(&T) -> Point2D) -> Vec<Vec<T>> {
+    let groups = items.len().div_ceil(NODE_CAPACITY);
+    let slices = (groups as f64).sqrt().ceil().max(1.0) as usize;
+    let per_slice = slices * NODE_CAPACITY;
+
+    items.sort_by(|a, b| center(a).x.total_cmp(&center(b).x));
+    let mut result = Vec::with_capacity(groups);
+    let mut rest = items;
+    while !rest.is_empty() {
+        let tail = rest.split_off(per_slice.min(rest.len()));
+        let mut slice = std::mem::replace(&mut rest, tail);
+        slice.sort_by(|a, b| center(a).y.total_cmp(&center(b).y));
+        while !slice.is_empty() {
+            let tail = slice.split_off(NODE_CAPACITY.min(slice.len()));
+            result.push(std::mem::replace(&mut slice, tail));
+        }
+    }
+    result
+}
+
+/// فهرس مكاني (شجرة R مبنية دفعة واحدة) لكل العناصر المرسومة
+/// يُستخدم في الالتقاط (Hit-testing) وفي استبعاد ما هو خارج مجال الرؤية (Culling)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SpatialIndex {
+    pub points: IdIndex,
+    pub lines: IdIndex,
+    pub splines: IdIndex,
+    pub bisectors: IdIndex,
+    shapes: Vec<IndexedShape>,
+    root: Option<RNode>,
+}
+
+impl SpatialIndex {
+    pub fn build(
+        points: &[VPoint],
+        lines: &[VLine],
+        splines: &[VCubicBezier],
+        bisectors: &[VBisector],
+        tolerance: f64,
+    ) -> Self {
+        let point_ids = IdIndex::build(points, |p| p.metadata.id);
+        let coords = |id: u32| point_ids.get(points, id).map(|p| p.coords);
+
+        let mut shapes: Vec<IndexedShape> = Vec::with_capacity(points.len() + lines.len() + splines.len() + bisectors.len());
+        let mut push = |item: SelectedItem, shape: Shape| {
+            let bounds = shape.bounds();
+            shapes.push(IndexedShape { item, shape, bounds });
+        };
+        for p in points {
+            push(SelectedItem::Point(p.metadata.id), Shape::Point(p.coords));
+        }
+        for l in lines {
+            if let (Some(a), Some(b)) = (coords(l.start_point_id), coords(l.end_point_id)) {
+                push(SelectedItem::Line(l.metadata.id), Shape::Segment(a, b));
+            }
+        }
+        for s in splines {
+            if let (Some(p1), Some(p2), Some(p3), Some(p4)) = (coords(s.p1_id), coords(s.p2_id), coords(s.p3_id), coords(s.p4_id)) {
+                push(SelectedItem::Spline(s.metadata.id), Shape::Cubic([p1, p2, p3, p4]));
+            }
+        }
+        for b in bisectors {
+            let find = |id: u32| point_ids.get(points, id);
+            if let (Some(p1), Some(v), Some(p3)) = (find(b.p1_id), find(b.vertex_id), find(b.p3_id)) {
+                // المنصف المنحل يبقى قابلاً للاختيار عند رأسه كقطعة صفرية الطول
+                // فيُرتّب مع المنحنيات بعد النقاط ولا ينافس نقطة الرأس نفسها
+                let shape = match b.calculate_end_point(p1, v, p3, tolerance) {
+                    Ok(end) => Shape::Segment(v.coords, end),
+                    Err(_) => Shape::Segment(v.coords, v.coords),
+                };
+                push(SelectedItem::Bisector(b.metadata.id), shape);
+            }
+        }
+
+        let root = Self::build_tree(&shapes);
+        Self {
+            lines: IdIndex::build(lines, |l| l.metadata.id),
+            splines: IdIndex::build(splines, |s| s.metadata.id),
+            bisectors: IdIndex::build(bisectors, |b| b.metadata.id),
+            points: point_ids,
+            shapes,
+            root,
+        }
+    }
+
+    fn build_tree(shapes: &[IndexedShape]) -> Option<RNode> {
+        if shapes.is_empty() {
+            return None;
+        }
+        let entries: Vec<usize> = (0..shapes.len()).collect();
+        let mut level: Vec<RNode> = str_pack(entries, |&i| shapes[i].bounds.center())
+            .into_iter()
+            .map(|group| {
+                let bounds = group.iter().skip(1).fold(shapes[group[0]].bounds, |acc, &i| acc.union(&shapes[i].bounds));
+                RNode::Leaf { bounds, entries: group }
+            })
+            .collect();
+        while level.len() > 1 {
+            level = str_pack(level, |n| n.bounds().center())
+                .into_iter()
+                .map(|group| {
+                    let bounds = group.iter().skip(1).fold(*group[0].bounds(), |acc, n| acc.union(n.bounds()));
+                    RNode::Branch { bounds, children: group }
+                })
+                .collect();
+        }
+        level.pop()
+    }
+
+    /// كل العناصر التي يتقاطع مستطيلها المحيط مع المنطقة المطلوبة
+    pub fn query(&self, area: &Bounds) -> Vec<&IndexedShape> {
+        let mut result = Vec::new();
+        let mut stack: Vec<&RNode> = self.root.iter().collect();
+        while let Some(node) = stack.pop() {
+            if !node.bounds().intersects(area) {
+                continue;
+            }
+            match node {
+                RNode::Leaf { entries, .. } => {
+                    result.extend(entries.iter().map(|&i| &self.shapes[i]).filter(|s| s.bounds.intersects(area)));
+                }
+                RNode::Branch { children, .. } => stack.extend(children.iter()),
+            }
+        }
+        result
+    }
+
+    pub fn point<'a>(&self, points: &'a [VPoint], id: u32) -> Option<&'a VPoint> {
+        self.points.get(points, id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_of_no_points_is_none() {
+        assert_eq!(Bounds::from_points(&[]), None);
+        let b = Bounds::from_points(&[Point2D::new(3.0, -1.0), Point2D::new(-2.0, 4.0)]).unwrap();
+        assert_eq!(b, Bounds::new(Point2D::new(-2.0, -1.0), Point2D::new(3.0, 4.0)));
+    }
+
+    #[test]
+    fn degenerate_bisector_is_indexed_as_a_curve_at_its_vertex() {
+        // p1 ينطبق على الرأس فلا يوجد اتجاه للمنصف
+        let points = vec![VPoint::new(1, "A", 0.0, 0.0), VPoint::new(2, "B", 0.0, 0.0), VPoint::new(3, "C", 100.0, 0.0)];
+        let bisectors = vec![VBisector::new(4, "Bis", 1, 2, 3, 50.0)];
+        let index = SpatialIndex::build(&points, &[], &[], &bisectors, 0.01);
+        let shape = index.query(&Bounds::around(&Point2D::new(0.0, 0.0), 1.0))
+            .into_iter()
+            .find(|s| s.item == SelectedItem::Bisector(4))
+            .map(|s| s.shape.clone());
+        assert_eq!(shape, Some(Shape::Segment(Point2D::new(0.0, 0.0), Point2D::new(0.0, 0.0))));
+    }
+
+    #[test]
+    fn bisector_uses_the_document_tolerance() {
+        // الذراع أقصر من السماحية فيُعد المنصف منحلاً
+        let points = vec![VPoint::new(1, "A", 0.005, 0.0), VPoint::new(2, "B", 0.0, 0.0), VPoint::new(3, "C", 0.0, 100.0)];
+        let bisectors = vec![VBisector::new(4, "Bis", 1, 2, 3, 50.0)];
+        let loose = SpatialIndex::build(&points, &[], &[], &bisectors, 0.01);
+        let strict = SpatialIndex::build(&points, &[], &[], &bisectors, 0.0);
+        let bisector = |index: &SpatialIndex| index.shapes.iter().find(|s| s.item == SelectedItem::Bisector(4)).map(|s| s.shape.clone());
+        assert_eq!(bisector(&loose), Some(Shape::Segment(Point2D::new(0.0, 0.0), Point2D::new(0.0, 0.0))));
+        assert_ne!(bisector(&strict), bisector(&loose));
+    }
+}
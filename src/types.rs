@@ -9,6 +9,10 @@ pub enum GOType {
     Spline,
     Arc,
     Circle,
+    /// قطعة باترون مبنية على مسار
+    Piece,
+    /// عملية على مجموعة عناصر (انعكاس، دوران، إزاحة)
+    Operation,
 }
 
 /// Draw Mode: هل الكائن ده للحسابات فقط أم للرسم النهائي؟
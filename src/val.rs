@@ -0,0 +1,211 @@
+//! تحويل المشروع من وإلى صيغة Valentina (.val)
+//! الصيغة تحفظ أدوات الرسم فقط: النقاط الحرة والخطوط ومنحنيات بيزيه والمنصفات
+use std::fmt::Write;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, XmlVersion};
+use crate::object::{VPoint, VLine, VCubicBezier, VBisector};
+use crate::project::ProjectData;
+use crate::export::fmt_num;
+use crate::error::{Error, Result};
+
+/// إصدار صيغة .val المكتوب (أدوات الرسم المستخدمة موجودة منذ 0.6)
+pub const VAL_VERSION: &str = "0.6.0";
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// كتابة المشروع كملف .val (بالمليمتر)؛ يعيد النص مع تحذيرات لما لا تدعمه الصيغة
+pub fn write_val(project: &ProjectData) -> (String, Vec<String>) {
+    let mut warnings = Vec::new();
+    let mut out = String::new();
+    out.push_str("<?xml version='1.0' encoding='UTF-8'?>\n<pattern>\n");
+    out.push_str("    <!--Pattern created with Valentina-Oxidized-->\n");
+    writeln!(out, "    <version>{}</version>", VAL_VERSION).unwrap();
+    out.push_str("    <unit>mm</unit>\n    <description/>\n    <notes/>\n    <measurements/>\n    <increments/>\n");
+    out.push_str("    <draw name=\"Pattern piece 1\">\n        <calculation>\n");
+
+    for p in &project.points {
+        if p.formula.is_some() {
+            warnings.push(format!("point {}: formula not supported by .val single points, coordinates written", p.metadata.name));
+        }
+        writeln!(out, r#"            <point id="{}" mx="1" my="2" name="{}" type="single" x="{}" y="{}"/>"#,
+            p.metadata.id, escape(&p.metadata.name), fmt_num(p.x()), fmt_num(p.y())).unwrap();
+    }
+    for b in &project.bisectors {
+        let length = b.length_formula.clone().unwrap_or_else(|| fmt_num(b.length));
+        writeln!(out, r#"            <point firstPoint="{}" id="{}" length="{}" lineColor="black" mx="1" my="2" name="{}" secondPoint="{}" thirdPoint="{}" type="bisector" typeLine="hair"/>"#,
+            b.p1_id, b.metadata.id, escape(&length), escape(&b.metadata.name), b.vertex_id, b.p3_id).unwrap();
+    }
+    for l in &project.lines {
+        writeln!(out, r#"            <line firstPoint="{}" id="{}" lineColor="black" secondPoint="{}" typeLine="hair"/>"#,
+            l.start_point_id, l.metadata.id, l.end_point_id).unwrap();
+    }
+    for s in &project.splines {
+        writeln!(out, r#"            <spline color="black" id="{}" penStyle="hair" point1="{}" point2="{}" point3="{}" point4="{}" type="cubicBezier"/>"#,
+            s.metadata.id, s.p1_id, s.p2_id, s.p3_id, s.p4_id).unwrap();
+    }
+    out.push_str("        </calculation>\n        <modeling/>\n        <details/>\n        <groups/>\n    </draw>\n</pattern>\n");
+
+    let dropped = [
+        ("contours", project.contours.len()),
+        ("operations", project.operations.len()),
+        ("pieces", project.pieces.len()),
+        ("seam pairs", project.seam_pairs.len()),
+        ("layout placements", project.layout.placements.len()),
+        ("measurements", project.measurements.measurements.len()),
+    ];
+    for (what, n) in dropped.into_iter().filter(|(_, n)| *n > 0) {
+        warnings.push(format!("{} {} not written (not supported in .val export)", n, what));
+    }
+    (out, warnings)
+}
+
+fn attr(e: &BytesStart, key: &str) -> Option<String> {
+    e.attributes().flatten()
+        .find(|a| a.key.as_ref() == key.as_bytes())
+        .and_then(|a| a.normalized_value(XmlVersion::Implicit1_0).ok().map(|v| v.into_owned()))
+}
+
+fn attr_num(e: &BytesStart, key: &str) -> std::result::Result<f64, String> {
+    let v = attr(e, key).ok_or(format!("missing attribute '{}'", key))?;
+    v.trim().parse::<f64>().map_err(|_| format!("attribute '{}' is not a number: {}", key, v))
+}
+
+fn attr_id(e: &BytesStart, key: &str) -> std::result::Result<u32, String> {
+    let v = attr(e, key).ok_or(format!("missing attribute '{}'", key))?;
+    v.trim().parse::<u32>().map_err(|_| format!("attribute '{}' is not an id: {}", key, v))
+}
+
+/// تحويل عنصر أداة واحد إلى كائن في المشروع
+fn read_element(project: &mut ProjectData, warnings: &mut Vec<String>, e: &BytesStart, scale: f64) -> std::result::Result<(), String> {
+    let tag = e.name().as_ref().to_vec();
+    let kind = attr(e, "type").unwrap_or_default();
+    match (tag.as_slice(), kind.as_str()) {
+        (b"point", "single") => {
+            let id = attr_id(e, "id")?;
+            let name = attr(e, "name").unwrap_or_default();
+            project.points.push(VPoint::new(id, &name, attr_num(e, "x")? * scale, attr_num(e, "y")? * scale));
+        }
+        (b"point", "bisector") => {
+            let id = attr_id(e, "id")?;
+            let name = attr(e, "name").unwrap_or_default();
+            let length = attr(e, "length").ok_or("missing attribute 'length'")?;
+            let mut b = VBisector::new(id, &name, attr_id(e, "firstPoint")?, attr_id(e, "secondPoint")?, attr_id(e, "thirdPoint")?, 0.0);
+            match length.trim().parse::<f64>() {
+                Ok(v) => b.length = v * scale,
+                // طول بمعادلة: يُحسب عند إعادة الحساب بالقياسات، ونتيجته بوحدة الملف
+                Err(_) if scale == 1.0 => b.length_formula = Some(length),
+                Err(_) => b.length_formula = Some(format!("({}) * {}", length.trim(), fmt_num(scale))),
+            }
+            project.bisectors.push(b);
+        }
+        (b"line", _) => {
+            let id = attr_id(e, "id")?;
+            project.lines.push(VLine::new(id, &format!("Line_{}", id), attr_id(e, "firstPoint")?, attr_id(e, "secondPoint")?));
+        }
+        (b"spline", "cubicBezier") => {
+            let id = attr_id(e, "id")?;
+            project.splines.push(VCubicBezier::new(id, &format!("Spline_{}", id),
+                attr_id(e, "point1")?, attr_id(e, "point2")?, attr_id(e, "point3")?, attr_id(e, "point4")?));
+        }
+        (b"point" | b"spline" | b"arc" | b"elArc" | b"operation", _) => {
+            warnings.push(format!("unsupported {} tool '{}' skipped", String::from_utf8_lossy(&tag), kind));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// قراءة ملف .val: الأدوات غير المدعومة تُتجاهل مع تحذير، والإحداثيات تُحوَّل إلى المليمتر
+pub fn read_val(xml: &str) -> Result<(ProjectData, Vec<String>)> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut project = ProjectData::default();
+    let mut warnings = Vec::new();
+    // الوحدة الافتراضية في Valentina هي السنتيمتر
+    let mut scale = 10.0;
+    let mut in_unit = false;
+
+    loop {
+        let event = reader.read_event().map_err(|e| Error::parse(".val", format!("XML error at byte {}: {}", reader.error_position(), e)))?;
+        match event {
+            Event::Eof => break,
+            Event::Start(e) if e.name().as_ref() == b"unit" => in_unit = true,
+            Event::End(e) if e.name().as_ref() == b"unit" => in_unit = false,
+            Event::Text(t) if in_unit => {
+                let unit = t.decode().map_err(|e| Error::parse(".val", e))?;
+                scale = match unit.trim() {
+                    "mm" => 1.0,
+                    "cm" => 10.0,
+                    "inch" => 25.4,
+                    other => return Err(Error::parse(".val", format!("unknown unit '{}'", other))),
+                };
+            }
+            Event::Empty(e) | Event::Start(e) => {
+                let at = reader.buffer_position();
+                read_element(&mut project, &mut warnings, &e, scale).map_err(|err| Error::parse(".val", format!("at byte {}: {}", at, err)))?;
+            }
+            _ => {}
+        }
+    }
+
+    let max_id = project.points.iter().map(|p| p.metadata.id)
+        .chain(project.lines.iter().map(|l| l.metadata.id))
+        .chain(project.splines.iter().map(|s| s.metadata.id))
+        .chain(project.bisectors.iter().map(|b| b.metadata.id))
+        .max()
+        .unwrap_or(0);
+    project.next_id = max_id + 1;
+    Ok((project, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formula;
+
+    fn bisector_file(unit: &str, length: &str) -> String {
+        format!(r#"<?xml version='1.0' encoding='UTF-8'?>
+<pattern>
+    <unit>{unit}</unit>
+    <draw name="Pattern piece 1">
+        <calculation>
+            <point id="1" name="A" type="single" x="0" y="0"/>
+            <point id="2" name="B" type="single" x="1" y="0"/>
+            <point id="3" name="C" type="single" x="0" y="1"/>
+            <point firstPoint="2" id="4" length="{length}" name="D" secondPoint="1" thirdPoint="3" type="bisector"/>
+        </calculation>
+    </draw>
+</pattern>"#)
+    }
+
+    fn bisector_length(unit: &str, length: &str) -> (f64, Option<f64>) {
+        let (project, _) = read_val(&bisector_file(unit, length)).unwrap();
+        let b = &project.bisectors[0];
+        let vars = |name: &str| (name == "waist").then_some(8.0);
+        (b.length, b.length_formula.as_deref().map(|f| formula::eval(f, &vars).unwrap()))
+    }
+
+    #[test]
+    fn numeric_lengths_are_scaled_to_mm() {
+        assert_eq!(bisector_length("cm", "3").0, 30.0);
+        assert_eq!(bisector_length("mm", "3").0, 3.0);
+        assert_eq!(bisector_length("inch", "2").0, 50.8);
+    }
+
+    #[test]
+    fn formula_lengths_are_scaled_to_mm() {
+        assert_eq!(bisector_length("cm", "waist / 4 + 1").1, Some(30.0));
+        assert_eq!(bisector_length("mm", "waist / 4 + 1").1, Some(3.0));
+        assert_eq!(bisector_length("inch", "waist/8").1, Some(25.4));
+    }
+
+    #[test]
+    fn scaled_formula_survives_mm_round_trip() {
+        let (project, _) = read_val(&bisector_file("cm", "waist/4")).unwrap();
+        let (xml, _) = write_val(&project);
+        let (again, _) = read_val(&xml).unwrap();
+        assert_eq!(again.bisectors[0].length_formula, project.bisectors[0].length_formula);
+    }
+}
@@ -0,0 +1,238 @@
+//! تشغيل valentina-cli كعملية مستقلة والتحقق من رموز الخروج ومخرجات --json
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use serde_json::Value;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_valentina-cli"))
+        .args(args)
+        .output()
+        .expect("failed to start valentina-cli")
+}
+
+fn code(out: &Output) -> i32 {
+    out.status.code().expect("terminated by a signal")
+}
+
+/// مخرجات --json: كائن واحد على stdout
+fn json(out: &Output) -> Value {
+    serde_json::from_slice(&out.stdout)
+        .unwrap_or_else(|e| panic!("stdout is not JSON ({e}): {}", String::from_utf8_lossy(&out.stdout)))
+}
+
+fn path(p: &Path) -> &str {
+    p.to_str().unwrap()
+}
+
+#[test]
+fn valid_project_exits_zero() {
+    let valid = fixture("valid.json");
+    let out = run(&["validate", path(&valid)]);
+    assert_eq!(code(&out), 0);
+    assert!(String::from_utf8_lossy(&out.stdout).contains("ok"));
+
+    let out = run(&["validate", path(&valid), "--json"]);
+    assert_eq!(code(&out), 0);
+    let v = json(&out);
+    assert_eq!(v["ok"], true);
+    assert_eq!(v["valid"], true);
+    assert_eq!(v["issues"], Value::Array(Vec::new()));
+}
+
+#[test]
+fn stats_reports_counts() {
+    let out = run(&["stats", path(&fixture("valid.json")), "--json"]);
+    assert_eq!(code(&out), 0);
+    let v = json(&out);
+    assert_eq!(v["ok"], true);
+    assert_eq!(v["points"], 4);
+    assert_eq!(v["lines"], 1);
+    assert_eq!(v["bisectors"], 1);
+}
+
+#[test]
+fn export_writes_the_output_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let svg = dir.path().join("out.svg");
+    let out = run(&["export", path(&fixture("valid.json")), "--format", "svg", "-o", path(&svg), "--json"]);
+    assert_eq!(code(&out), 0);
+    let v = json(&out);
+    assert_eq!(v["ok"], true);
+    assert_eq!(v["format"], "svg");
+    let written = std::fs::read_to_string(&svg).unwrap();
+    assert!(written.starts_with("<?xml") || written.starts_with("<svg"));
+    assert_eq!(v["bytes"], written.len());
+}
+
+#[test]
+fn convert_round_trips_through_val() {
+    let dir = tempfile::tempdir().unwrap();
+    let val = dir.path().join("p.val");
+    let back = dir.path().join("p.json");
+    assert_eq!(code(&run(&["convert", path(&fixture("valid.json")), path(&val)])), 0);
+    assert_eq!(code(&run(&["convert", path(&val), path(&back)])), 0);
+    let v = json(&run(&["stats", path(&back), "--json"]));
+    assert_eq!((v["points"].clone(), v["lines"].clone(), v["bisectors"].clone()), (4.into(), 1.into(), 1.into()));
+}
+
+#[test]
+fn validation_failure_exits_one() {
+    let degenerate = fixture("degenerate.json");
+    let out = run(&["validate", path(&degenerate)]);
+    assert_eq!(code(&out), 1);
+
+    let out = run(&["validate", path(&degenerate), "--json"]);
+    assert_eq!(code(&out), 1);
+    let v = json(&out);
+    assert_eq!(v["ok"], false);
+    assert_eq!(v["valid"], false);
+    let ids: Vec<u64> = v["issues"].as_array().unwrap().iter()
+        .filter(|i| i["severity"] == "error")
+        .map(|i| i["object_id"].as_u64().unwrap())
+        .collect();
+    assert_eq!(ids, [5, 6]);
+}
+
+#[test]
+fn bad_arguments_exit_two() {
+    let valid = fixture("valid.json");
+    let cases: [&[&str]; 5] = [
+        &[],
+        &["frob"],
+        &["validate"],
+        &["validate", path(&valid), "--bogus"],
+        &["recalc", path(&valid), "--size", "abc"],
+    ];
+    for args in cases {
+        let out = run(args);
+        assert_eq!(code(&out), 2, "{args:?}");
+        assert!(!out.stderr.is_empty(), "{args:?}: usage errors go to stderr");
+    }
+
+    let out = run(&["export", path(&valid), "--format", "foo", "--json"]);
+    assert_eq!(code(&out), 2);
+    let v = json(&out);
+    assert_eq!(v["ok"], false);
+    assert_eq!(v["code"], 2);
+    assert!(v["error"].as_str().unwrap().contains("foo"));
+}
+
+#[test]
+fn missing_or_unreadable_file_exits_three() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing.json");
+    let out = run(&["validate", path(&missing), "--json"]);
+    assert_eq!(code(&out), 3);
+    let v = json(&out);
+    assert_eq!(v["ok"], false);
+    assert_eq!(v["code"], 3);
+    assert!(v["error"].as_str().unwrap().contains("missing.json"));
+
+    let broken = dir.path().join("broken.json");
+    std::fs::write(&broken, "{ not json").unwrap();
+    let out = run(&["validate", path(&broken)]);
+    assert_eq!(code(&out), 3);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("broken.json"));
+}
+
+#[test]
+fn help_exits_zero_on_stdout() {
+    let out = run(&["--help"]);
+    assert_eq!(code(&out), 0);
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Exit codes"));
+}
+
+/// أسماء الملفات المكتوبة في مجلد التدريج
+fn file_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir).unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn grade_writes_one_file_per_size_and_a_nest() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = run(&["grade", path(&fixture("multisize.json")), "-o", path(dir.path()), "--json"]);
+    assert_eq!(code(&out), 0, "{}", String::from_utf8_lossy(&out.stdout));
+    let v = json(&out);
+    assert_eq!(v["ok"], true);
+    assert_eq!(v["files"].as_array().unwrap().len(), 3);
+    assert_eq!(v["failed"], Value::Array(Vec::new()));
+    assert_eq!(file_names(dir.path()), [
+        "multisize_48-176.svg",
+        "multisize_50-176.svg",
+        "multisize_52-176.svg",
+        "multisize_nest.svg",
+    ]);
+
+    // كل مقاس في مجموعة باسمه ولون مختلف في ملف التداخل
+    let nest = std::fs::read_to_string(dir.path().join("multisize_nest.svg")).unwrap();
+    for (label, color) in [("48-176", "#e6194b"), ("50-176", "#3cb44b"), ("52-176", "#4363d8")] {
+        assert!(nest.contains(&format!(r#"<g id="size-{label}" fill="none" stroke="{color}""#)), "{label} in {nest}");
+    }
+}
+
+#[test]
+fn grade_exits_one_when_a_size_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    // في المقاس 46 يصبح طول المنصف sqrt(سالب)
+    let table = dir.path().join("table.json");
+    std::fs::write(&table, r#"{
+        "base_size": 48, "base_height": 176, "sizes": [46, 48],
+        "measurements": [{ "name": "waist", "base": 80, "size_increase": 2 }]
+    }"#).unwrap();
+    let sizes = dir.path().join("sizes");
+    let out = run(&[
+        "grade", path(&fixture("multisize.json")), "--measurements", path(&table), "-o", path(&sizes), "--json",
+    ]);
+    assert_eq!(code(&out), 1);
+    let v = json(&out);
+    let failed = v["failed"].as_array().unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0]["grade"]["size"], 46.0);
+    // المقاسات السليمة تُكتب رغم ذلك
+    assert_eq!(file_names(&sizes), ["multisize_48.svg", "multisize_nest.svg"]);
+}
+
+#[test]
+fn png_region_option() {
+    let dir = tempfile::tempdir().unwrap();
+    let png = dir.path().join("region.png");
+    let valid = fixture("valid.json");
+    let out = run(&["export", path(&valid), "--format", "png", "--region", "-10,-10,110,110", "-o", path(&png), "--json"]);
+    assert_eq!(code(&out), 0, "{}", String::from_utf8_lossy(&out.stdout));
+    assert!(std::fs::read(&png).unwrap().starts_with(b"\x89PNG"));
+
+    for bad in ["1,2,3", "0,0,-5,10", "a,b,c,d"] {
+        let out = run(&["export", path(&valid), "--format", "png", "--region", bad, "-o", path(&png)]);
+        assert_eq!(code(&out), 2, "{bad}");
+        assert!(String::from_utf8_lossy(&out.stderr).contains("--region"), "{bad}");
+    }
+}
+
+#[test]
+fn recalc_and_script_do_not_overwrite_the_input() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join("p.json");
+    std::fs::copy(fixture("multisize.json"), &project).unwrap();
+    let original = std::fs::read(&project).unwrap();
+
+    let out = run(&["recalc", path(&project), "--size", "50", "--height", "176", "--json"]);
+    assert_eq!(code(&out), 0, "{}", String::from_utf8_lossy(&out.stdout));
+    assert_eq!(json(&out)["output"], path(&dir.path().join("p.recalc.json")));
+
+    let script = dir.path().join("add.rhai");
+    std::fs::write(&script, "point(\"E\", 10, 10);").unwrap();
+    let out = run(&["script", path(&project), path(&script), "--json"]);
+    assert_eq!(code(&out), 0, "{}", String::from_utf8_lossy(&out.stdout));
+    assert_eq!(json(&out)["output"], path(&dir.path().join("p.script.json")));
+
+    assert_eq!(std::fs::read(&project).unwrap(), original);
+}